@@ -1,12 +1,20 @@
 use anyhow::{anyhow, Result};
-use clap::Parser;
-use rust_data_inspector::DataInspector;
-use rust_data_inspector_signals::{PlotSampleSender, PlotSignalSample, PlotSignals};
+use clap::{Parser, Subcommand};
+use rust_data_inspector::{
+    export_figures, DataInspector, DataInspectorAPI, DataInspectorOptions, FigureFormat,
+    FigureOptions, FigureRange,
+};
+use rust_data_inspector_signals::{
+    ColumnarOptions, McapOptions, McapTimestamp, PlotSampleSender, PlotSignalSample, PlotSignals,
+    ReplayHandle, ServerStats, SignalServer,
+};
 use std::{
     fs::File,
     io::{self, BufRead, BufReader},
-    thread::spawn,
-    time::Instant,
+    path::PathBuf,
+    sync::{Arc, Weak},
+    thread::{sleep, spawn},
+    time::{Duration, Instant},
 };
 
 #[derive(Parser, Debug)]
@@ -15,9 +23,14 @@ use std::{
 #[command(version = "1.0")]
 #[command(about = "Plot everything, from everywhere, all at once", long_about = None)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     #[arg(short, long)]
     file: Option<String>,
 
+    /// Columns to load when opening Parquet or Arrow IPC files, or topics and fields of MCAP files.
+    /// Nested columns and fields are selected by path, eg. `imu/accel`. Everything is loaded if omitted.
     #[arg(long)]
     columns: Vec<String>,
 
@@ -32,6 +45,83 @@ struct Cli {
 
     #[arg(short, long)]
     real_time: bool,
+
+    /// Layout file to load at startup
+    #[arg(long)]
+    layout: Option<PathBuf>,
+
+    /// Layout template to load at startup, assigning signals to tabs by path
+    #[arg(long)]
+    template: Option<PathBuf>,
+
+    /// Record all received data to a file
+    #[arg(long)]
+    record: Option<PathBuf>,
+
+    /// Open a recording, Parquet (.parquet, .pq), Arrow IPC (.arrow, .ipc, .feather),
+    /// MCAP (.mcap) or ULog (.ulg) file instead of reading CSV data
+    #[arg(long, conflicts_with = "file")]
+    open: Option<PathBuf>,
+
+    /// Open a PX4 ULog flight log instead of reading CSV data
+    #[arg(long, conflicts_with_all = ["file", "open"])]
+    ulog: Option<PathBuf>,
+
+    /// Receive MAVLink telemetry on a UDP address, eg. 0.0.0.0:14550, instead of reading CSV data
+    #[arg(long, conflicts_with_all = ["file", "open", "ulog"])]
+    mavlink: Option<String>,
+
+    /// Receive signals from other processes over TCP and UDP on an address, eg. 0.0.0.0:7000,
+    /// instead of reading CSV data. See docs/protocol.md for the protocol.
+    #[arg(long, conflicts_with_all = ["file", "open", "ulog"])]
+    listen: Option<String>,
+
+    /// Column holding the timestamps of Parquet or Arrow IPC files
+    #[arg(long, requires = "open")]
+    time_column: Option<String>,
+
+    /// Factor converting the time column to seconds, if it is not a timestamp (eg. 1e-6 for µs)
+    #[arg(long, requires = "open", default_value_t = 1.0)]
+    time_scale: f64,
+
+    /// Timestamp the messages of MCAP files by publish time instead of log time
+    #[arg(long, requires = "open")]
+    publish_time: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Replay a recording as if it were live
+    Replay { file: PathBuf },
+    /// List the channels of an MCAP file
+    Channels { file: PathBuf },
+    /// Render the tabs of a layout to SVG or PDF figures, without opening a window
+    Figure {
+        /// Recording to read the data from
+        recording: PathBuf,
+        /// Layout defining the tabs to render
+        #[arg(long)]
+        layout: PathBuf,
+        /// Title of a tab to render. All time plots are rendered if omitted.
+        #[arg(long = "tab")]
+        tabs: Vec<String>,
+        /// Directory where figures are written
+        #[arg(short, long, default_value = ".")]
+        output: PathBuf,
+        /// Write PDF instead of SVG
+        #[arg(long)]
+        pdf: bool,
+        #[arg(long, default_value_t = 800.0)]
+        width: f32,
+        #[arg(long, default_value_t = 500.0)]
+        height: f32,
+        /// Render the whole recording instead of the view saved in the layout
+        #[arg(long)]
+        full: bool,
+        /// Time range to render
+        #[arg(long, num_args = 2, value_names = ["START", "END"], conflicts_with = "full")]
+        range: Option<Vec<f64>>,
+    },
 }
 
 struct CSVPlotter {
@@ -259,31 +349,189 @@ fn set_thread_panic_hook() {
     }));
 }
 
+/// Prints the changes of the statistics of the server, until it is dropped
+fn print_server_stats(server: Weak<SignalServer>) {
+    let mut last = ServerStats::default();
+    while let Some(stats) = server.upgrade().map(|server| server.stats()) {
+        if (stats.connected, stats.connections) != (last.connected, last.connections) {
+            println!(
+                "- {} clients connected, {} connections so far",
+                stats.connected, stats.connections
+            );
+        }
+        if stats.errors != last.errors {
+            if let Some(error) = &stats.last_error {
+                eprintln!("Server error: {} ({} errors so far)", error, stats.errors);
+            }
+        }
+        if stats.dropped_samples != last.dropped_samples {
+            println!(
+                "- {} out of order samples dropped so far",
+                stats.dropped_samples
+            );
+        }
+        last = stats;
+        sleep(Duration::from_secs(1));
+    }
+}
+
 fn main() -> Result<()> {
     set_thread_panic_hook();
     let cli = Cli::parse();
 
-    let input: Box<dyn BufRead + Send + 'static> = if let Some(file) = cli.file {
-        Box::new(BufReader::new(File::open(file)?))
+    if let Some(Command::Figure {
+        recording,
+        layout,
+        tabs,
+        output,
+        pdf,
+        width,
+        height,
+        full,
+        range,
+    }) = cli.command
+    {
+        let signals = PlotSignals::from_recording(recording)?;
+        let options = FigureOptions {
+            width,
+            height,
+            format: if pdf {
+                FigureFormat::Pdf
+            } else {
+                FigureFormat::Svg
+            },
+            range: match range.as_deref() {
+                Some(&[start, end]) => FigureRange::Custom([start, end]),
+                _ if full => FigureRange::Full,
+                _ => FigureRange::View,
+            },
+            tabs,
+            output,
+        };
+
+        for path in export_figures(&signals, &layout, &options)? {
+            println!("{}", path.display());
+        }
+        return Ok(());
+    }
+
+    if let Some(Command::Channels { file }) = cli.command {
+        for channel in PlotSignals::mcap_channels(file)? {
+            println!(
+                "{}\t{} messages\t{} ({}, {}){}",
+                channel.path,
+                channel.message_count,
+                channel.schema_name,
+                channel.message_encoding,
+                channel.schema_encoding,
+                if channel.supported {
+                    ""
+                } else {
+                    "\tunsupported"
+                }
+            );
+        }
+        return Ok(());
+    }
+
+    let mut replay = None;
+    let mut signals = if let Some(Command::Replay { file }) = cli.command {
+        let mut signals = PlotSignals::default();
+        let handle = ReplayHandle::open(file, &mut signals)?;
+        handle.play();
+        replay = Some(handle);
+
+        signals
+    } else if cli.mavlink.is_some() || cli.listen.is_some() {
+        PlotSignals::default()
+    } else if let Some(path) = cli.ulog {
+        PlotSignals::from_ulog(path)?
+    } else if let Some(path) = cli.open {
+        let options = ColumnarOptions {
+            time_column: cli.time_column,
+            time_scale: cli.time_scale,
+            columns: cli.columns,
+        };
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("parquet" | "pq") => PlotSignals::from_parquet(path, &options)?,
+            Some("arrow" | "ipc" | "feather") => PlotSignals::from_arrow_ipc(path, &options)?,
+            Some("ulg") => PlotSignals::from_ulog(path)?,
+            Some("mcap") => {
+                let options = McapOptions {
+                    timestamp: if cli.publish_time {
+                        McapTimestamp::Publish
+                    } else {
+                        McapTimestamp::Log
+                    },
+                    selection: options.columns,
+                };
+                PlotSignals::from_mcap(path, &options)?
+            }
+            _ => PlotSignals::from_recording(path)?,
+        }
     } else {
-        let stdin = io::stdin();
-        Box::new(BufReader::new(stdin))
+        let input: Box<dyn BufRead + Send + 'static> = if let Some(file) = cli.file {
+            Box::new(BufReader::new(File::open(file)?))
+        } else {
+            let stdin = io::stdin();
+            Box::new(BufReader::new(stdin))
+        };
+
+        let (mut csvplotter, signals) = CSVPlotter::with_signals(
+            input,
+            cli.columns,
+            cli.separator,
+            cli.time_index,
+            cli.real_time,
+            cli.col_hint,
+        );
+
+        spawn(move || {
+            csvplotter.plot_lines();
+        });
+
+        signals
     };
 
-    let (mut csvplotter, signals) = CSVPlotter::with_signals(
-        input,
-        cli.columns,
-        cli.separator,
-        cli.time_index,
-        cli.real_time,
-        cli.col_hint,
-    );
+    // Network sources receive until the viewer is closed
+    let _mavlink = match cli.mavlink {
+        Some(addr) => {
+            let receiver = signals.listen_mavlink(addr)?;
+            println!("- Receiving MAVLink on {}", receiver.local_addr());
+            Some(receiver)
+        }
+        None => None,
+    };
+    let _server = match cli.listen {
+        Some(addr) => {
+            let server = Arc::new(signals.listen(addr)?);
+            println!("- Listening on {}", server.local_addr());
+            let weak = Arc::downgrade(&server);
+            spawn(move || print_server_stats(weak));
+            Some(server)
+        }
+        None => None,
+    };
 
-    spawn(move || {
-        csvplotter.plot_lines();
-    });
+    if let Some(path) = cli.record {
+        signals.start_recording(path)?;
+    }
 
-    DataInspector::run_native("Rust Data Inspector", signals).unwrap();
+    DataInspector::run_native_with_options(
+        "Rust Data Inspector",
+        signals,
+        None::<fn(&mut _, &mut DataInspectorAPI)>,
+        DataInspectorOptions {
+            layout: cli.layout,
+            template: cli.template,
+            replay,
+        },
+    )
+    .unwrap();
     println!("App terminated");
     Ok(())
 }
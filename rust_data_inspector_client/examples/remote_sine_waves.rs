@@ -0,0 +1,50 @@
+//! Streams sine waves to a viewer started with `rdi --listen 0.0.0.0:7000`
+//!
+//! Usage: `cargo run -p rust_data_inspector_client --example remote_sine_waves [address]`
+
+use std::f64::consts::PI;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rust_data_inspector_client::{PlotClient, PlotSignalSample};
+
+fn main() {
+    let addr = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "127.0.0.1:7000".to_string());
+    let mut client = PlotClient::connect(addr, "sine_waves").unwrap();
+
+    let senders: Vec<_> = (1..=3)
+        .map(|i| {
+            let sender = client.add_signal(&format!("/s{}", i)).unwrap();
+            (i as f64, sender)
+        })
+        .collect();
+
+    let start = Instant::now();
+    let mut connected = false;
+    loop {
+        let t = start.elapsed().as_secs_f64();
+        for (f, sender) in &senders {
+            let value = f64::sin(2.0 * PI * f * t);
+            sender.send(PlotSignalSample { time: t, value }).unwrap();
+        }
+
+        if client.is_connected() != connected {
+            connected = !connected;
+            if connected {
+                client.add_event(t, "Connected");
+            }
+            println!(
+                "{}",
+                if connected {
+                    "Connected"
+                } else {
+                    "Disconnected"
+                }
+            );
+        }
+
+        thread::sleep(Duration::from_millis(1));
+    }
+}
@@ -0,0 +1,585 @@
+//! Streams signals to a remote Rust Data Inspector, started with `rdi --listen`, without
+//! depending on the GUI.
+//!
+//! The API mirrors [`PlotSignals::add_signal`] and [`PlotSampleSender::send`]:
+//!
+//! ```no_run
+//! use rust_data_inspector_client::{PlotClient, PlotSignalSample};
+//!
+//! let mut client = PlotClient::connect("192.168.1.10:7000", "drone").unwrap();
+//! let sender = client.add_signal("/sensors/accel/x").unwrap();
+//! sender.send(PlotSignalSample { time: 0.0, value: 9.81 }).unwrap();
+//! ```
+//!
+//! Samples are sent in batches from a background thread. While the viewer can't be reached,
+//! they are buffered and the connection is retried, so that producers never block.
+//!
+//! [`PlotSignals::add_signal`]: rust_data_inspector_signals::PlotSignals::add_signal
+//! [`PlotSampleSender::send`]: rust_data_inspector_signals::PlotSampleSender::send
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::mem;
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use rust_data_inspector_signals::{
+    is_valid_namespace, validate_signal_name, WireMessage, WIRE_MAGIC,
+};
+
+pub use rust_data_inspector_signals::{PlotSignalError, PlotSignalSample, PlotSignalSendError};
+
+/// Largest number of samples sent in a single frame
+const MAX_FRAME_SAMPLES: usize = 65536;
+/// A keepalive is sent after this long without data, well before the viewer closes the
+/// connection after 60 seconds
+const KEEPALIVE_PERIOD: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone)]
+pub struct ClientOptions {
+    /// Namespace of the signals in the viewer, see [`PlotClient`]. Made of letters, numbers
+    /// and underscores.
+    pub namespace: String,
+    /// How long samples are held to be sent together
+    pub batch_period: Duration,
+    /// Number of buffered samples that triggers sending a batch before the end of the period
+    pub batch_size: usize,
+    /// Largest number of samples buffered while the viewer can't be reached. Further
+    /// samples are dropped.
+    pub buffer_size: usize,
+    /// Delay between connection attempts
+    pub reconnect_period: Duration,
+    /// Timeout of connection attempts and writes
+    pub timeout: Duration,
+}
+
+impl Default for ClientOptions {
+    fn default() -> Self {
+        ClientOptions {
+            namespace: "client".to_string(),
+            batch_period: Duration::from_millis(20),
+            batch_size: 4096,
+            buffer_size: 1_000_000,
+            reconnect_period: Duration::from_secs(1),
+            timeout: Duration::from_secs(2),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// Name of each registered signal, by id
+    signals: Vec<String>,
+    /// Signals registered since the last batch, to be sent before their samples
+    new_signals: usize,
+    /// Samples waiting to be sent, by signal id
+    samples: HashMap<u32, Vec<PlotSignalSample>>,
+    events: Vec<(f64, String)>,
+    buffered: usize,
+    dropped: usize,
+    connected: bool,
+    closed: bool,
+}
+
+#[derive(Debug)]
+struct Shared {
+    state: Mutex<State>,
+    /// Notified when a batch is full, or when the client is closed
+    wake: Condvar,
+    options: ClientOptions,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Connection to a remote viewer, with the protocol described in `docs/protocol.md`.
+///
+/// Signals are added under the namespace of the client in the viewer, as
+/// `/<namespace>/<name>`. A client reconnecting with the same namespace continues its signals.
+///
+/// Buffered samples are sent, if connected, when the client is dropped.
+#[derive(Debug)]
+pub struct PlotClient {
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl PlotClient {
+    /// Starts streaming to the viewer at `addr`, under `namespace`. The connection is made in
+    /// the background, so this only fails if `addr` can't be resolved or `namespace` is invalid.
+    pub fn connect(addr: impl ToSocketAddrs, namespace: &str) -> io::Result<Self> {
+        Self::with_options(
+            addr,
+            ClientOptions {
+                namespace: namespace.to_string(),
+                ..Default::default()
+            },
+        )
+    }
+
+    pub fn with_options(addr: impl ToSocketAddrs, options: ClientOptions) -> io::Result<Self> {
+        if !is_valid_namespace(&options.namespace) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid namespace '{}'", options.namespace),
+            ));
+        }
+        let addrs: Vec<_> = addr.to_socket_addrs()?.collect();
+        if addrs.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "no address to connect to",
+            ));
+        }
+
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State::default()),
+            wake: Condvar::new(),
+            options,
+        });
+        let thread_shared = shared.clone();
+        let thread = thread::spawn(move || sender_thread(thread_shared, addrs));
+
+        Ok(PlotClient {
+            shared,
+            thread: Some(thread),
+        })
+    }
+
+    /// Creates a new signal, returning its sample producer. Names follow the rules of
+    /// [`PlotSignals::add_signal`], relative to the namespace of the client, so that
+    /// `/accel/x` becomes `/<namespace>/accel/x` in the viewer.
+    ///
+    /// [`PlotSignals::add_signal`]: rust_data_inspector_signals::PlotSignals::add_signal
+    pub fn add_signal(&mut self, name: &str) -> Result<RemoteSampleSender, PlotSignalError> {
+        let mut state = self.shared.lock();
+        validate_signal_name(name, state.signals.iter())?;
+
+        let id = state.signals.len() as u32;
+        state.signals.push(name.to_string());
+        state.new_signals += 1;
+
+        Ok(RemoteSampleSender {
+            shared: self.shared.clone(),
+            id,
+        })
+    }
+
+    /// Adds an event, shown as an annotation on the time plots of the viewer
+    pub fn add_event(&self, time: f64, text: &str) {
+        self.shared.lock().events.push((time, text.to_string()));
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.shared.lock().connected
+    }
+
+    /// Number of samples dropped because the buffer was full
+    pub fn dropped_samples(&self) -> usize {
+        self.shared.lock().dropped
+    }
+}
+
+impl Drop for PlotClient {
+    fn drop(&mut self) {
+        self.shared.lock().closed = true;
+        self.shared.wake.notify_all();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Sample producer of a signal of a [`PlotClient`]
+#[derive(Clone, Debug)]
+pub struct RemoteSampleSender {
+    shared: Arc<Shared>,
+    id: u32,
+}
+
+impl RemoteSampleSender {
+    /// Queues `sample` to be sent. Fails only if the [`PlotClient`] was dropped.
+    pub fn send(
+        &self,
+        sample: PlotSignalSample,
+    ) -> Result<(), PlotSignalSendError<PlotSignalSample>> {
+        let mut state = self.shared.lock();
+        if state.closed {
+            return Err(PlotSignalSendError { t: sample });
+        }
+
+        if state.buffered >= self.shared.options.buffer_size {
+            state.dropped += 1;
+        } else {
+            state.samples.entry(self.id).or_default().push(sample);
+            state.buffered += 1;
+            if state.buffered == self.shared.options.batch_size {
+                self.shared.wake.notify_all();
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Puts the samples and events of a batch that could not be sent back before the ones queued
+/// since. Samples that don't fit in the buffer are dropped, oldest first.
+fn requeue(
+    state: &mut State,
+    samples: HashMap<u32, Vec<PlotSignalSample>>,
+    mut events: Vec<(f64, String)>,
+    buffer_size: usize,
+) {
+    let mut room = buffer_size.saturating_sub(state.buffered);
+    for (id, mut samples) in samples {
+        let dropped = samples.len().saturating_sub(room);
+        samples.drain(..dropped);
+        state.dropped += dropped;
+        room -= samples.len();
+        state.buffered += samples.len();
+
+        if let Some(queued) = state.samples.remove(&id) {
+            samples.extend(queued);
+        }
+        state.samples.insert(id, samples);
+    }
+
+    events.append(&mut state.events);
+    state.events = events;
+}
+
+fn connect(addrs: &[SocketAddr], shared: &Shared) -> io::Result<TcpStream> {
+    let mut error = None;
+    for addr in addrs {
+        match TcpStream::connect_timeout(addr, shared.options.timeout) {
+            Ok(stream) => {
+                stream.set_write_timeout(Some(shared.options.timeout))?;
+                stream.set_nodelay(true)?;
+                return Ok(stream);
+            }
+            Err(e) => error = Some(e),
+        }
+    }
+    Err(error.unwrap())
+}
+
+/// True if the viewer closed the connection. It never sends anything, so readable data or
+/// an error means that the connection is gone.
+fn is_closed(stream: &TcpStream) -> bool {
+    let mut byte = [0];
+    if stream.set_nonblocking(true).is_err() {
+        return true;
+    }
+    let closed = !matches!(
+        stream.peek(&mut byte),
+        Err(e) if e.kind() == io::ErrorKind::WouldBlock
+    );
+    closed || stream.set_nonblocking(false).is_err()
+}
+
+fn sender_thread(shared: Arc<Shared>, addrs: Vec<SocketAddr>) {
+    let options = &shared.options;
+    let mut stream: Option<TcpStream> = None;
+    let mut buf = vec![];
+    let mut last_write = Instant::now();
+
+    loop {
+        if stream.is_none() {
+            match connect(&addrs, &shared) {
+                Ok(new_stream) => {
+                    // Everything registered so far is sent again on a new connection
+                    let mut state = shared.lock();
+                    state.new_signals = state.signals.len();
+                    state.connected = true;
+                    drop(state);
+
+                    buf.clear();
+                    buf.extend_from_slice(WIRE_MAGIC);
+                    WireMessage::Hello {
+                        namespace: options.namespace.clone(),
+                    }
+                    .encode(&mut buf);
+                    stream = Some(new_stream);
+                }
+                Err(_) => {
+                    let state = shared.lock();
+                    if state.closed {
+                        return;
+                    }
+                    let _ = shared.wake.wait_timeout(state, options.reconnect_period);
+                    continue;
+                }
+            }
+        }
+
+        // Waits for the batch to fill up, or for the end of the batch period
+        let mut state = shared.lock();
+        if !state.closed && state.buffered < options.batch_size {
+            state = shared
+                .wake
+                .wait_timeout(state, options.batch_period)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+        let closed = state.closed;
+
+        let first_new = state.signals.len() - mem::take(&mut state.new_signals);
+        for (id, name) in state.signals.iter().enumerate().skip(first_new) {
+            WireMessage::Signal {
+                id: id as u32,
+                name: name.clone(),
+            }
+            .encode(&mut buf);
+        }
+        let samples = mem::take(&mut state.samples);
+        let events = mem::take(&mut state.events);
+        state.buffered = 0;
+        drop(state);
+
+        for (&id, samples) in &samples {
+            for chunk in samples.chunks(MAX_FRAME_SAMPLES) {
+                WireMessage::Samples {
+                    id,
+                    samples: chunk.to_vec(),
+                }
+                .encode(&mut buf);
+            }
+        }
+        for (time, text) in &events {
+            WireMessage::Event {
+                time: *time,
+                text: text.clone(),
+            }
+            .encode(&mut buf);
+        }
+        if buf.is_empty() && last_write.elapsed() >= KEEPALIVE_PERIOD {
+            WireMessage::Keepalive.encode(&mut buf);
+        }
+
+        if !buf.is_empty() {
+            let sent = match &mut stream {
+                // Writing to a connection closed by the viewer may still succeed, losing the data
+                Some(stream) if is_closed(stream) => Err(io::ErrorKind::ConnectionAborted.into()),
+                Some(stream) => stream.write_all(&buf),
+                None => Ok(()),
+            };
+            buf.clear();
+            last_write = Instant::now();
+            if sent.is_err() {
+                // The batch is sent again after reconnecting, with the following samples
+                stream = None;
+                let mut state = shared.lock();
+                state.connected = false;
+                requeue(&mut state, samples, events, options.buffer_size);
+            }
+        }
+
+        if closed {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+    use std::time::Instant;
+
+    use rust_data_inspector_signals::{PlotSignals, SignalServer};
+
+    use super::*;
+
+    /// Address with a free port, to start a server on it later
+    fn free_addr() -> SocketAddr {
+        TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+    }
+
+    /// Starts a server on `addr`, waiting for a previous server on it to release its sockets
+    fn listen(addr: SocketAddr, signals: &PlotSignals) -> SignalServer {
+        let start = Instant::now();
+        loop {
+            match SignalServer::listen(addr, signals) {
+                Ok(server) => return server,
+                Err(e) => assert!(start.elapsed() < Duration::from_secs(5), "{}", e),
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    fn client(addr: SocketAddr, options: ClientOptions) -> PlotClient {
+        PlotClient::with_options(
+            addr,
+            ClientOptions {
+                namespace: "test".to_string(),
+                reconnect_period: Duration::from_millis(10),
+                ..options
+            },
+        )
+        .unwrap()
+    }
+
+    fn send(sender: &RemoteSampleSender, times: std::ops::Range<u32>) {
+        for t in times {
+            let time = t as f64;
+            sender
+                .send(PlotSignalSample { time, value: -time })
+                .unwrap();
+        }
+    }
+
+    /// Waits for `condition` to be true, checking it every millisecond. Panics after 5 seconds.
+    fn wait_until(mut condition: impl FnMut() -> bool) {
+        let start = Instant::now();
+        while !condition() {
+            assert!(start.elapsed() < Duration::from_secs(5), "timed out");
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    /// Waits for the signal `name` to have samples at exactly the times in `times`
+    fn wait_samples(signals: &mut PlotSignals, name: &str, times: std::ops::Range<u32>) {
+        let expected: Vec<_> = times.map(|t| t as f64).collect();
+        wait_until(|| {
+            signals.update();
+            let signal = signals.get_signals().values().find(|s| s.name() == name);
+            signal.is_some_and(|s| *s.time() == expected)
+        });
+        let signal = signals
+            .get_signals()
+            .values()
+            .find(|s| s.name() == name)
+            .unwrap();
+        assert!(signal
+            .time()
+            .iter()
+            .zip(signal.data())
+            .all(|(t, v)| *v == -t));
+    }
+
+    #[test]
+    fn buffers_until_connected() {
+        let addr = free_addr();
+        let mut client = client(addr, ClientOptions::default());
+        let sender = client.add_signal("/x").unwrap();
+        send(&sender, 0..1000);
+        client.add_event(1.0, "started");
+        thread::sleep(Duration::from_millis(50));
+        assert!(!client.is_connected());
+
+        let mut signals = PlotSignals::default();
+        let _server = listen(addr, &signals);
+        wait_samples(&mut signals, "/test/x", 0..1000);
+        assert!(client.is_connected());
+        assert_eq!(client.dropped_samples(), 0);
+        // The event may be sent after the samples
+        wait_until(|| {
+            signals.update();
+            !signals.events().is_empty()
+        });
+        assert_eq!(signals.events()[0].text, "test: started");
+    }
+
+    #[test]
+    fn buffer_overflow() {
+        let addr = free_addr();
+        let options = ClientOptions {
+            buffer_size: 10,
+            ..Default::default()
+        };
+        let mut client = client(addr, options);
+        let sender = client.add_signal("/x").unwrap();
+        send(&sender, 0..15);
+        assert_eq!(client.dropped_samples(), 5);
+
+        // The oldest samples are kept
+        let mut signals = PlotSignals::default();
+        let _server = listen(addr, &signals);
+        wait_samples(&mut signals, "/test/x", 0..10);
+    }
+
+    #[test]
+    fn batching() {
+        let mut signals = PlotSignals::default();
+        let server = listen(free_addr(), &signals);
+        let options = ClientOptions {
+            batch_size: 4,
+            batch_period: Duration::from_secs(60),
+            ..Default::default()
+        };
+        let mut client = client(server.local_addr(), options);
+        let sender = client.add_signal("/x").unwrap();
+
+        send(&sender, 0..3);
+        thread::sleep(Duration::from_millis(100));
+        signals.update();
+        assert!(signals.get_signals().is_empty());
+
+        // A full batch is sent right away
+        send(&sender, 3..4);
+        wait_samples(&mut signals, "/test/x", 0..4);
+    }
+
+    #[test]
+    fn reconnection() {
+        let addr = free_addr();
+        let mut signals = PlotSignals::default();
+        let server = listen(addr, &signals);
+        let mut client = client(addr, ClientOptions::default());
+        let sender = client.add_signal("/x").unwrap();
+        send(&sender, 0..10);
+        wait_samples(&mut signals, "/test/x", 0..10);
+
+        // Samples sent while the viewer is gone are sent to the next one, which doesn't know
+        // the signals yet
+        drop(server);
+        thread::sleep(Duration::from_millis(200));
+        send(&sender, 10..20);
+        thread::sleep(Duration::from_millis(50));
+        send(&sender, 20..30);
+
+        let mut signals = PlotSignals::default();
+        let _server = listen(addr, &signals);
+        wait_samples(&mut signals, "/test/x", 10..30);
+        assert!(client.is_connected());
+        assert_eq!(client.dropped_samples(), 0);
+    }
+
+    #[test]
+    fn requeue_order() {
+        let sample = |time| PlotSignalSample { time, value: 0.0 };
+        let times =
+            |state: &State, id| -> Vec<f64> { state.samples[&id].iter().map(|s| s.time).collect() };
+
+        let mut state = State {
+            samples: HashMap::from([(0, vec![sample(3.0), sample(4.0)])]),
+            events: vec![(2.0, "queued".to_string())],
+            buffered: 2,
+            ..Default::default()
+        };
+        let batch = HashMap::from([(0, vec![sample(1.0), sample(2.0)]), (1, vec![sample(0.0)])]);
+        requeue(&mut state, batch, vec![(1.0, "sent".to_string())], 10);
+        assert_eq!(times(&state, 0), [1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(times(&state, 1), [0.0]);
+        assert_eq!(state.events[0].1, "sent");
+        assert_eq!(state.events[1].1, "queued");
+        assert_eq!((state.buffered, state.dropped), (5, 0));
+
+        // The oldest samples of the batch are dropped when the buffer is full
+        let mut state = State {
+            samples: HashMap::from([(0, vec![sample(3.0), sample(4.0)])]),
+            buffered: 2,
+            ..Default::default()
+        };
+        let batch = HashMap::from([(0, vec![sample(1.0), sample(2.0)])]);
+        requeue(&mut state, batch, vec![], 3);
+        assert_eq!(times(&state, 0), [2.0, 3.0, 4.0]);
+        assert_eq!((state.buffered, state.dropped), (3, 1));
+    }
+}
@@ -0,0 +1,706 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::sync::Arc;
+
+use arrow_array::{
+    cast::AsArray, types::Float64Type, Array, ArrayRef, Float64Array, RecordBatch,
+    RecordBatchReader,
+};
+use arrow_buffer::NullBuffer;
+use arrow_cast::cast;
+use arrow_ipc::reader::FileReader;
+use arrow_schema::{ArrowError, DataType, Field, Fields, Schema, TimeUnit};
+use parquet::{
+    arrow::{arrow_reader::ParquetRecordBatchReaderBuilder, ArrowWriter, ProjectionMask},
+    basic::Compression,
+    errors::ParquetError,
+    file::properties::WriterProperties,
+};
+use thiserror::Error;
+
+use crate::{
+    export::{sample_range, union_rows},
+    selection::{self, is_selected, overlaps, sanitize},
+    PlotSignal, PlotSignalError, PlotSignalID, PlotSignalSample, PlotSignals,
+};
+
+/// Rows read or written at a time
+const BATCH_SIZE: usize = 64 * 1024;
+/// Key of the field metadata storing the unit of a signal
+const UNIT_METADATA_KEY: &str = "unit";
+const TIME_COLUMN: &str = "time";
+
+/// Options to load Parquet and Arrow IPC files
+#[derive(Debug, Clone)]
+pub struct ColumnarOptions {
+    /// Column with the timestamps. The row index is used if `None`.
+    pub time_column: Option<String>,
+    /// Converts the values of the time column to seconds, e.g. 1e-6 for microseconds.
+    /// Not applied to timestamp columns, which are always converted to seconds.
+    pub time_scale: f64,
+    /// Columns to load, as signal paths like `/imu/accel`. Selecting a struct column selects
+    /// all of its fields. All numeric columns are loaded if empty.
+    /// Characters not allowed in signal paths are replaced with `_` in the column names.
+    pub columns: Vec<String>,
+}
+
+impl Default for ColumnarOptions {
+    fn default() -> Self {
+        ColumnarOptions {
+            time_column: None,
+            time_scale: 1.0,
+            columns: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ColumnarError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Arrow error: {0}")]
+    Arrow(#[from] ArrowError),
+    #[error("Parquet error: {0}")]
+    Parquet(#[from] ParquetError),
+    #[error("Time column '{0}' not found or not numeric")]
+    TimeColumn(String),
+    #[error("Time is not monotonic at row {0}")]
+    NonMonotonicTime(usize),
+}
+
+/// Adds the paths of the numeric leaves of `fields` to `out`, with their unit
+fn numeric_leaves(fields: &Fields, parent: &str, out: &mut Vec<(String, Option<String>)>) {
+    for field in fields {
+        let path = format!("{}/{}", parent, field.name());
+        match field.data_type() {
+            DataType::Struct(children) => numeric_leaves(children, &path, out),
+            t if t.is_numeric() || *t == DataType::Boolean => {
+                out.push((path, field.metadata().get(UNIT_METADATA_KEY).cloned()))
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Converts the values of the time column to seconds. Null timestamps are converted to NaN.
+fn time_values(column: &ArrayRef, scale: f64) -> Result<Vec<f64>, ArrowError> {
+    let (column, scale) = match column.data_type() {
+        DataType::Timestamp(unit, _) => {
+            let scale = match unit {
+                TimeUnit::Second => 1.0,
+                TimeUnit::Millisecond => 1e-3,
+                TimeUnit::Microsecond => 1e-6,
+                TimeUnit::Nanosecond => 1e-9,
+            };
+            let ints = cast(column, &DataType::Int64)?;
+            (cast(&ints, &DataType::Float64)?, scale)
+        }
+        _ => (cast(column, &DataType::Float64)?, scale),
+    };
+
+    let values = column.as_primitive::<Float64Type>();
+    Ok((0..values.len())
+        .map(|i| {
+            if values.is_null(i) {
+                f64::NAN
+            } else {
+                values.value(i) * scale
+            }
+        })
+        .collect())
+}
+
+/// Column at `path` in `batch`, looking through struct columns for nested fields, along with
+/// its nulls combined with the ones of its parents
+fn column_by_path(batch: &RecordBatch, path: &str) -> Option<(ArrayRef, Option<NullBuffer>)> {
+    let mut parts = path.trim_matches('/').split('/');
+    let mut column = batch.column_by_name(parts.next()?)?.clone();
+    let mut nulls = column.logical_nulls();
+    for part in parts {
+        let DataType::Struct(_) = column.data_type() else {
+            return None;
+        };
+        let child = column.as_struct().column_by_name(part)?.clone();
+        nulls = NullBuffer::union(nulls.as_ref(), child.logical_nulls().as_ref());
+        column = child;
+    }
+    Some((column, nulls))
+}
+
+/// Adds record batches to signals created from their schema
+struct ColumnarLoader<'a> {
+    options: &'a ColumnarOptions,
+    signals: PlotSignals,
+    /// Signal of each loaded column, by column path
+    ids: HashMap<String, PlotSignalID>,
+    /// Columns that couldn't be loaded, with the reason
+    skipped: Vec<(String, PlotSignalError)>,
+    rows: usize,
+    first_time: Option<f64>,
+    last_time: Option<f64>,
+}
+
+impl<'a> ColumnarLoader<'a> {
+    fn new(schema: &Schema, options: &'a ColumnarOptions) -> Self {
+        let selection = selection::normalize(&options.columns);
+        let time_path = options
+            .time_column
+            .as_ref()
+            .map(|c| format!("/{}", c.trim_matches('/')));
+
+        let mut leaves = vec![];
+        numeric_leaves(schema.fields(), "", &mut leaves);
+
+        let mut signals = PlotSignals::default();
+        let mut ids = HashMap::new();
+        let mut skipped = vec![];
+        let mut used = HashSet::new();
+        for (path, unit) in leaves {
+            let name = sanitize(&path);
+            if Some(&path) == time_path.as_ref() || !is_selected(&name, &selection) {
+                continue;
+            }
+
+            // Columns like `temp-c` and `temp_c` get the same name once sanitized
+            let mut unique = name.clone();
+            let mut n = 1;
+            while !used.insert(unique.clone()) {
+                n += 1;
+                unique = format!("{}_{}", name, n);
+            }
+
+            match signals.add_signal(&unique) {
+                Ok((id, _)) => {
+                    if let Some(unit) = unit {
+                        signals.set_signal_unit(id, &unit);
+                    }
+                    ids.insert(path, id);
+                }
+                Err(e) => skipped.push((path, e)),
+            }
+        }
+
+        ColumnarLoader {
+            options,
+            signals,
+            ids,
+            skipped,
+            rows: 0,
+            first_time: None,
+            last_time: None,
+        }
+    }
+
+    /// Reports the skipped columns as events at the start of the data
+    fn finish(mut self) -> PlotSignals {
+        let time = self.first_time.unwrap_or(0.0);
+        for (path, error) in self.skipped {
+            self.signals
+                .add_event(time, &format!("Column '{}' was skipped: {}", path, error));
+        }
+        self.signals
+    }
+
+    fn add_batch(&mut self, batch: &RecordBatch) -> Result<(), ColumnarError> {
+        let n = batch.num_rows();
+        let time = match &self.options.time_column {
+            Some(name) => {
+                let (column, nulls) = column_by_path(batch, name)
+                    .ok_or_else(|| ColumnarError::TimeColumn(name.clone()))?;
+                let mut time = time_values(&column, self.options.time_scale)
+                    .map_err(|_| ColumnarError::TimeColumn(name.clone()))?;
+                // Rows where a parent struct is null have no time either
+                if let Some(nulls) = nulls {
+                    for (i, t) in time.iter_mut().enumerate() {
+                        if nulls.is_null(i) {
+                            *t = f64::NAN;
+                        }
+                    }
+                }
+                time
+            }
+            None => (self.rows..self.rows + n).map(|i| i as f64).collect(),
+        };
+
+        for (i, &t) in time.iter().enumerate() {
+            if t.is_nan() {
+                continue;
+            }
+            if self.last_time.is_some_and(|last| t < last) {
+                return Err(ColumnarError::NonMonotonicTime(self.rows + i));
+            }
+            self.first_time.get_or_insert(t);
+            self.last_time = Some(t);
+        }
+
+        for (field, column) in batch.schema().fields().iter().zip(batch.columns()) {
+            self.add_column(format!("/{}", field.name()), column, None, &time)?;
+        }
+
+        self.rows += n;
+        Ok(())
+    }
+
+    fn add_column(
+        &mut self,
+        path: String,
+        column: &ArrayRef,
+        parent_nulls: Option<&NullBuffer>,
+        time: &[f64],
+    ) -> Result<(), ColumnarError> {
+        let nulls = NullBuffer::union(parent_nulls, column.logical_nulls().as_ref());
+
+        if let DataType::Struct(_) = column.data_type() {
+            let column = column.as_struct();
+            for (field, child) in column.fields().iter().zip(column.columns()) {
+                let child_path = format!("{}/{}", path, field.name());
+                self.add_column(child_path, child, nulls.as_ref(), time)?;
+            }
+            return Ok(());
+        }
+
+        let Some(&id) = self.ids.get(&path) else {
+            return Ok(());
+        };
+
+        let values = cast(column, &DataType::Float64)?;
+        let values = values.as_primitive::<Float64Type>();
+        for (i, &t) in time.iter().enumerate() {
+            if t.is_nan() || nulls.as_ref().is_some_and(|n| n.is_null(i)) {
+                continue;
+            }
+            self.signals.push_sample(
+                id,
+                PlotSignalSample {
+                    time: t,
+                    value: values.value(i),
+                },
+            );
+        }
+
+        Ok(())
+    }
+}
+
+impl PlotSignals {
+    /// Loads the numeric columns of a Parquet file as signals, with nested struct fields mapped
+    /// to signal paths. Only the selected columns are read, one batch of rows at a time.
+    pub fn from_parquet(
+        path: impl AsRef<Path>,
+        options: &ColumnarOptions,
+    ) -> Result<Self, ColumnarError> {
+        let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(path)?)?
+            .with_batch_size(BATCH_SIZE);
+
+        let selection = selection::normalize(&options.columns);
+        let time_column = options.time_column.as_deref().map(|c| c.trim_matches('/'));
+        let leaves: Vec<usize> = builder
+            .parquet_schema()
+            .columns()
+            .iter()
+            .enumerate()
+            .filter(|(_, column)| {
+                let parts = column.path().parts();
+                Some(parts.join("/").as_str()) == time_column
+                    || is_selected(&sanitize(&parts.join("/")), &selection)
+            })
+            .map(|(i, _)| i)
+            .collect();
+        let mask = ProjectionMask::leaves(builder.parquet_schema(), leaves);
+
+        let reader = builder.with_projection(mask).build()?;
+        let mut loader = ColumnarLoader::new(&reader.schema(), options);
+        for batch in reader {
+            loader.add_batch(&batch?)?;
+        }
+
+        Ok(loader.finish())
+    }
+
+    /// Loads the numeric columns of an Arrow IPC file as signals, with nested struct fields mapped
+    /// to signal paths. Only the selected columns are read, one batch of rows at a time.
+    pub fn from_arrow_ipc(
+        path: impl AsRef<Path>,
+        options: &ColumnarOptions,
+    ) -> Result<Self, ColumnarError> {
+        let path = path.as_ref();
+        let schema = FileReader::try_new(BufReader::new(File::open(path)?), None)?.schema();
+
+        let selection = selection::normalize(&options.columns);
+        // Top level field containing the time column
+        let time_field = options
+            .time_column
+            .as_deref()
+            .and_then(|c| c.trim_matches('/').split('/').next());
+        let projection = schema
+            .fields()
+            .iter()
+            .enumerate()
+            .filter(|(_, field)| {
+                Some(field.name().as_str()) == time_field
+                    || overlaps(&sanitize(field.name()), &selection)
+            })
+            .map(|(i, _)| i)
+            .collect();
+
+        let reader = FileReader::try_new(BufReader::new(File::open(path)?), Some(projection))?;
+        let mut loader = ColumnarLoader::new(&reader.schema(), options);
+        for batch in reader {
+            loader.add_batch(&batch?)?;
+        }
+
+        Ok(loader.finish())
+    }
+
+    /// Writes the signals in `ids` to a Parquet file, with a `time` column and a column for
+    /// each signal named after its path. Rows are the union of the timestamps of all signals,
+    /// with null values for signals without a sample at that time.
+    /// A signal named like the time column, or like a renamed one, gets a numbered suffix.
+    /// Only samples within `range` are written, if provided.
+    pub fn export_parquet(
+        &self,
+        ids: &[PlotSignalID],
+        path: impl AsRef<Path>,
+        range: Option<[f64; 2]>,
+    ) -> Result<(), ColumnarError> {
+        let signals: Vec<&PlotSignal> = ids
+            .iter()
+            .filter_map(|id| self.get_signals().get(id))
+            .collect();
+
+        let mut fields = vec![Field::new(TIME_COLUMN, DataType::Float64, false)];
+        let mut used = HashSet::from([TIME_COLUMN.to_string()]);
+        fields.extend(signals.iter().map(|s| {
+            let name = s.name().trim_start_matches('/');
+            let mut unique = name.to_string();
+            let mut n = 1;
+            while !used.insert(unique.clone()) {
+                n += 1;
+                unique = format!("{}_{}", name, n);
+            }
+
+            let field = Field::new(unique, DataType::Float64, true);
+            match s.unit() {
+                Some(unit) => field.with_metadata(HashMap::from([(
+                    UNIT_METADATA_KEY.to_string(),
+                    unit.to_string(),
+                )])),
+                None => field,
+            }
+        }));
+        let schema = Arc::new(Schema::new(fields));
+
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+        let mut writer =
+            ArrowWriter::try_new(File::create(path)?, schema.clone(), Some(properties))?;
+
+        let ranges: Vec<_> = signals.iter().map(|s| sample_range(s, range)).collect();
+        let mut time = Vec::with_capacity(BATCH_SIZE);
+        let mut columns = vec![Vec::with_capacity(BATCH_SIZE); signals.len()];
+        union_rows(&signals, &ranges, |t, values| {
+            time.push(t);
+            for (column, value) in columns.iter_mut().zip(values) {
+                column.push(*value);
+            }
+
+            if time.len() == BATCH_SIZE {
+                write_batch(&mut writer, &schema, &mut time, &mut columns)?;
+            }
+            Ok::<_, ColumnarError>(())
+        })?;
+        write_batch(&mut writer, &schema, &mut time, &mut columns)?;
+
+        writer.close()?;
+        Ok(())
+    }
+}
+
+fn write_batch(
+    writer: &mut ArrowWriter<File>,
+    schema: &Arc<Schema>,
+    time: &mut Vec<f64>,
+    columns: &mut [Vec<Option<f64>>],
+) -> Result<(), ColumnarError> {
+    if time.is_empty() {
+        return Ok(());
+    }
+
+    let mut arrays: Vec<ArrayRef> = vec![Arc::new(Float64Array::from(std::mem::take(time)))];
+    arrays.extend(
+        columns
+            .iter_mut()
+            .map(|c| Arc::new(Float64Array::from(std::mem::take(c))) as ArrayRef),
+    );
+    writer.write(&RecordBatch::try_new(schema.clone(), arrays)?)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow_array::{
+        Int32Array, Int64Array, StringArray, StructArray, TimestampMicrosecondArray,
+        TimestampMillisecondArray, TimestampNanosecondArray, TimestampSecondArray,
+    };
+    use arrow_ipc::writer::FileWriter;
+
+    use super::*;
+    use crate::test_utils::{signals_with, TempPath};
+
+    type Dump = Vec<(String, Option<String>, Vec<(f64, f64)>)>;
+
+    /// Names, units and samples of the signals, sorted by name
+    fn dump(signals: &PlotSignals) -> Dump {
+        let mut dump: Dump = signals
+            .get_signals()
+            .values()
+            .map(|s| {
+                let samples = s.time().iter().copied().zip(s.data().iter().copied());
+                (
+                    s.name().to_string(),
+                    s.unit().map(str::to_string),
+                    samples.collect(),
+                )
+            })
+            .collect();
+        dump.sort_by(|a, b| a.0.cmp(&b.0));
+        dump
+    }
+
+    fn record_batch(columns: Vec<(&str, ArrayRef)>) -> RecordBatch {
+        RecordBatch::try_from_iter(columns).unwrap()
+    }
+
+    fn f64s(values: &[Option<f64>]) -> ArrayRef {
+        Arc::new(Float64Array::from(values.to_vec()))
+    }
+
+    /// Writes `batch` to both a Parquet and an Arrow IPC file and loads them, checking that they
+    /// give the same signals
+    fn load(batch: &RecordBatch, options: &ColumnarOptions) -> Result<PlotSignals, ColumnarError> {
+        let parquet = TempPath::new("parquet");
+        let mut writer = ArrowWriter::try_new(File::create(parquet.path())?, batch.schema(), None)?;
+        writer.write(batch)?;
+        writer.close()?;
+
+        let ipc = TempPath::new("arrow");
+        let mut writer = FileWriter::try_new(File::create(ipc.path())?, &batch.schema())?;
+        writer.write(batch)?;
+        writer.finish()?;
+
+        let from_parquet = PlotSignals::from_parquet(parquet.path(), options);
+        let from_ipc = PlotSignals::from_arrow_ipc(ipc.path(), options);
+        match (&from_parquet, from_ipc) {
+            (Ok(a), Ok(b)) => {
+                assert_eq!(dump(a), dump(&b));
+                assert_eq!(a.events(), b.events());
+            }
+            (Err(a), Err(b)) => assert_eq!(a.to_string(), b.to_string()),
+            (a, b) => panic!("Parquet: {:?}, Arrow IPC: {:?}", a.is_ok(), b.is_ok()),
+        }
+        from_parquet
+    }
+
+    #[test]
+    fn export_round_trip() {
+        let (mut signals, ids) = signals_with(&[
+            ("/a/b", &[(0.0, 1.0), (1.0, 2.0)]),
+            ("/c", &[(0.5, 3.0)]),
+            ("/time", &[(0.0, 5.0)]),
+        ]);
+        signals.set_signal_unit(ids[0], "m");
+        let options = ColumnarOptions {
+            time_column: Some(TIME_COLUMN.to_string()),
+            ..Default::default()
+        };
+
+        let file = TempPath::new("parquet");
+        signals.export_parquet(&ids, file.path(), None).unwrap();
+        let loaded = PlotSignals::from_parquet(file.path(), &options).unwrap();
+        assert_eq!(
+            dump(&loaded),
+            [
+                (
+                    "/a/b".to_string(),
+                    Some("m".to_string()),
+                    vec![(0.0, 1.0), (1.0, 2.0)]
+                ),
+                ("/c".to_string(), None, vec![(0.5, 3.0)]),
+                ("/time_2".to_string(), None, vec![(0.0, 5.0)]),
+            ]
+        );
+
+        signals
+            .export_parquet(&ids, file.path(), Some([0.5, 1.0]))
+            .unwrap();
+        let loaded = PlotSignals::from_parquet(file.path(), &options).unwrap();
+        assert_eq!(
+            dump(&loaded),
+            [
+                ("/a/b".to_string(), Some("m".to_string()), vec![(1.0, 2.0)]),
+                ("/c".to_string(), None, vec![(0.5, 3.0)]),
+                ("/time_2".to_string(), None, vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn nested_columns() {
+        let header = StructArray::from(vec![(
+            Arc::new(Field::new("stamp", DataType::Float64, true)),
+            f64s(&[Some(0.0), Some(1.0), Some(2.0), Some(3.0)]),
+        )]);
+        let accel = StructArray::from(vec![
+            (
+                Arc::new(Field::new("x", DataType::Float64, true)),
+                f64s(&[Some(1.0), None, Some(3.0), Some(4.0)]),
+            ),
+            (
+                Arc::new(Field::new("y", DataType::Float64, true)),
+                f64s(&[Some(5.0), Some(6.0), Some(7.0), Some(8.0)]),
+            ),
+        ]);
+        let temp: ArrayRef = Arc::new(Int32Array::from(vec![10, 11, 12, 13]));
+        let imu_fields = Fields::from(vec![
+            Field::new("accel", accel.data_type().clone(), true),
+            Field::new("temp-c", DataType::Int32, false),
+        ]);
+        // No IMU data in the third row
+        let imu = StructArray::try_new(
+            imu_fields,
+            vec![Arc::new(accel), temp],
+            Some(NullBuffer::from(vec![true, true, false, true])),
+        )
+        .unwrap();
+        let name: ArrayRef = Arc::new(StringArray::from(vec!["a", "b", "c", "d"]));
+        let batch = record_batch(vec![
+            ("header", Arc::new(header)),
+            ("imu", Arc::new(imu)),
+            ("name", name),
+        ]);
+
+        let mut options = ColumnarOptions {
+            time_column: Some("/header/stamp".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            dump(&load(&batch, &options).unwrap()),
+            [
+                (
+                    "/imu/accel/x".to_string(),
+                    None,
+                    vec![(0.0, 1.0), (3.0, 4.0)]
+                ),
+                (
+                    "/imu/accel/y".to_string(),
+                    None,
+                    vec![(0.0, 5.0), (1.0, 6.0), (3.0, 8.0)]
+                ),
+                (
+                    "/imu/temp_c".to_string(),
+                    None,
+                    vec![(0.0, 10.0), (1.0, 11.0), (3.0, 13.0)]
+                ),
+            ]
+        );
+
+        options.columns = vec!["imu/accel/".to_string()];
+        let names: Vec<_> = dump(&load(&batch, &options).unwrap())
+            .into_iter()
+            .map(|(name, _, _)| name)
+            .collect();
+        assert_eq!(names, ["/imu/accel/x", "/imu/accel/y"]);
+
+        options.columns = vec!["/imu/temp_c".to_string()];
+        let names: Vec<_> = dump(&load(&batch, &options).unwrap())
+            .into_iter()
+            .map(|(name, _, _)| name)
+            .collect();
+        assert_eq!(names, ["/imu/temp_c"]);
+    }
+
+    #[test]
+    fn time_units() {
+        let value = f64s(&[Some(1.0), Some(2.0), Some(3.0)]);
+        let seconds = [Some(1), None, Some(2)];
+        let columns: [(ArrayRef, f64); 4] = [
+            (Arc::new(TimestampSecondArray::from(seconds.to_vec())), 1.0),
+            (
+                Arc::new(TimestampMillisecondArray::from(seconds.to_vec())),
+                1e-3,
+            ),
+            (
+                Arc::new(TimestampMicrosecondArray::from(seconds.to_vec())),
+                1e-6,
+            ),
+            (
+                Arc::new(TimestampNanosecondArray::from(seconds.to_vec())),
+                1e-9,
+            ),
+        ];
+        let options = ColumnarOptions {
+            time_column: Some("t".to_string()),
+            // Not applied to timestamps
+            time_scale: 10.0,
+            ..Default::default()
+        };
+        for (time, scale) in columns {
+            let batch = record_batch(vec![("t", time), ("v", value.clone())]);
+            let signals = load(&batch, &options).unwrap();
+            assert_eq!(dump(&signals)[0].2, [(scale, 1.0), (2.0 * scale, 3.0)]);
+        }
+
+        let time: ArrayRef = Arc::new(Int64Array::from(vec![1000, 2000, 3000]));
+        let batch = record_batch(vec![("t", time), ("v", value.clone())]);
+        let options = ColumnarOptions {
+            time_column: Some("t".to_string()),
+            time_scale: 1e-3,
+            ..Default::default()
+        };
+        let signals = load(&batch, &options).unwrap();
+        assert_eq!(dump(&signals)[0].2, [(1.0, 1.0), (2.0, 2.0), (3.0, 3.0)]);
+
+        // Row index
+        let batch = record_batch(vec![("v", value)]);
+        let signals = load(&batch, &ColumnarOptions::default()).unwrap();
+        assert_eq!(dump(&signals)[0].2, [(0.0, 1.0), (1.0, 2.0), (2.0, 3.0)]);
+    }
+
+    #[test]
+    fn non_monotonic_time() {
+        let batch = record_batch(vec![
+            ("t", f64s(&[Some(0.0), Some(2.0), None, Some(1.0)])),
+            ("v", f64s(&[Some(1.0), Some(2.0), Some(3.0), Some(4.0)])),
+        ]);
+        let options = ColumnarOptions {
+            time_column: Some("t".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            load(&batch, &options),
+            Err(ColumnarError::NonMonotonicTime(3))
+        ));
+    }
+
+    #[test]
+    fn invalid_column_names() {
+        let value = f64s(&[Some(1.0)]);
+        let names = ["temp-c", "speed.m_s", "accel x", "temp_c", "v", "v/x"];
+        let batch = record_batch(names.iter().map(|&n| (n, value.clone())).collect());
+
+        let signals = load(&batch, &ColumnarOptions::default()).unwrap();
+        let names: Vec<_> = dump(&signals).into_iter().map(|(n, _, _)| n).collect();
+        assert_eq!(
+            names,
+            ["/accel_x", "/speed_m_s", "/temp_c", "/temp_c_2", "/v"]
+        );
+        assert_eq!(signals.events().len(), 1);
+        assert!(signals.events()[0]
+            .text
+            .starts_with("Column '/v/x' was skipped"));
+    }
+}
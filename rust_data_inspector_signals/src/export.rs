@@ -0,0 +1,339 @@
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{PlotSignal, PlotSignalID, PlotSignals};
+
+/// Largest number of rows written by a resampled export
+const MAX_RESAMPLED_ROWS: usize = 10_000_000;
+
+/// How the samples of multiple signals are arranged in CSV files
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum CsvLayout {
+    /// A single file with a row for each timestamp of any signal.
+    /// Signals without a sample at that timestamp are left empty.
+    Union,
+    /// A single file with rows at a fixed rate, in Hz, linearly interpolating the signals
+    Resampled { rate: f64 },
+    /// A file for each signal, written in the directory provided as path
+    PerSignal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CsvExportOptions {
+    pub layout: CsvLayout,
+    pub separator: String,
+    /// Number of decimal digits, or `None` to write values with full precision
+    pub precision: Option<usize>,
+    /// Time range to export, or `None` to export all samples
+    pub range: Option<[f64; 2]>,
+}
+
+impl Default for CsvExportOptions {
+    fn default() -> Self {
+        CsvExportOptions {
+            layout: CsvLayout::Union,
+            separator: ",".to_string(),
+            precision: None,
+            range: None,
+        }
+    }
+}
+
+impl CsvExportOptions {
+    fn format(&self, v: f64) -> String {
+        match self.precision {
+            Some(precision) => format!("{:.*}", precision, v),
+            None => v.to_string(),
+        }
+    }
+}
+
+/// Range of the samples of `signal` within `range`, or all of them if `None`
+pub(crate) fn sample_range(signal: &PlotSignal, range: Option<[f64; 2]>) -> Range<usize> {
+    let time = signal.time();
+    match range {
+        Some([start, end]) => {
+            time.partition_point(|&t| t < start)..time.partition_point(|&t| t <= end)
+        }
+        None => 0..time.len(),
+    }
+}
+
+impl PlotSignals {
+    /// Writes the samples of the signals in `ids` to CSV, returning the paths of the written files.
+    /// Signals are written in the order they are provided, with their name as column header.
+    pub fn export_csv(
+        &self,
+        ids: &[PlotSignalID],
+        path: impl AsRef<Path>,
+        options: &CsvExportOptions,
+    ) -> io::Result<Vec<PathBuf>> {
+        let path = path.as_ref();
+        let signals: Vec<&PlotSignal> = ids
+            .iter()
+            .filter_map(|id| self.get_signals().get(id))
+            .collect();
+
+        match options.layout {
+            CsvLayout::Union => {
+                write_union(path, &signals, options)?;
+                Ok(vec![path.to_path_buf()])
+            }
+            CsvLayout::Resampled { rate } => {
+                write_resampled(path, &signals, rate, options)?;
+                Ok(vec![path.to_path_buf()])
+            }
+            CsvLayout::PerSignal => {
+                fs::create_dir_all(path)?;
+
+                let mut paths = vec![];
+                let mut used = HashSet::new();
+                for signal in signals {
+                    let name = signal.name().trim_start_matches('/').replace('/', "_");
+                    // Names like `/a/b_c` and `/a_b/c` would otherwise write the same file,
+                    // also on case insensitive file systems
+                    let mut unique = name.clone();
+                    let mut n = 1;
+                    while !used.insert(unique.to_lowercase()) {
+                        n += 1;
+                        unique = format!("{}_{}", name, n);
+                    }
+                    let file = path.join(format!("{}.csv", unique));
+                    write_union(&file, &[signal], options)?;
+                    paths.push(file);
+                }
+                Ok(paths)
+            }
+        }
+    }
+}
+
+fn write_header(
+    w: &mut impl Write,
+    signals: &[&PlotSignal],
+    options: &CsvExportOptions,
+) -> io::Result<()> {
+    let mut header = vec!["time"];
+    header.extend(signals.iter().map(|s| s.name()));
+    writeln!(w, "{}", header.join(&options.separator))
+}
+
+fn write_union(path: &Path, signals: &[&PlotSignal], options: &CsvExportOptions) -> io::Result<()> {
+    let mut w = BufWriter::new(File::create(path)?);
+    write_header(&mut w, signals, options)?;
+
+    let ranges: Vec<_> = signals
+        .iter()
+        .map(|s| sample_range(s, options.range))
+        .collect();
+    union_rows(signals, &ranges, |t, values| {
+        let mut row = vec![options.format(t)];
+        row.extend(
+            values
+                .iter()
+                .map(|v| v.map(|v| options.format(v)).unwrap_or_default()),
+        );
+        writeln!(w, "{}", row.join(&options.separator))
+    })?;
+
+    w.flush()
+}
+
+/// Calls `row` for each timestamp of any of the samples in `ranges`, in order,
+/// with the value of each signal at that timestamp, if it has one.
+pub(crate) fn union_rows<E>(
+    signals: &[&PlotSignal],
+    ranges: &[Range<usize>],
+    mut row: impl FnMut(f64, &[Option<f64>]) -> Result<(), E>,
+) -> Result<(), E> {
+    // Next sample of each signal
+    let mut cursors: Vec<usize> = ranges.iter().map(|r| r.start).collect();
+    let mut values = vec![None; signals.len()];
+    loop {
+        let next = signals
+            .iter()
+            .zip(&cursors)
+            .zip(ranges)
+            .filter(|((_, &i), range)| i < range.end)
+            .map(|((s, &i), _)| s.time()[i])
+            .min_by(f64::total_cmp);
+        let Some(t) = next else {
+            return Ok(());
+        };
+
+        for (((signal, i), range), value) in signals
+            .iter()
+            .zip(cursors.iter_mut())
+            .zip(ranges)
+            .zip(values.iter_mut())
+        {
+            *value = None;
+            if *i < range.end && signal.time()[*i] == t {
+                *value = Some(signal.data()[*i]);
+                *i += 1;
+            }
+        }
+        row(t, &values)?;
+    }
+}
+
+fn write_resampled(
+    path: &Path,
+    signals: &[&PlotSignal],
+    rate: f64,
+    options: &CsvExportOptions,
+) -> io::Result<()> {
+    if !rate.is_finite() || rate <= 0.0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Invalid resampling rate: {}", rate),
+        ));
+    }
+
+    // Resample over the time span of the samples in range of all signals
+    let span = signals
+        .iter()
+        .filter_map(|s| {
+            let range = sample_range(s, options.range);
+            (!range.is_empty()).then(|| [s.time()[range.start], s.time()[range.end - 1]])
+        })
+        .reduce(|a, b| [a[0].min(b[0]), a[1].max(b[1])]);
+
+    if let Some([start, end]) = span {
+        let rows = (end - start) * rate + 1.0;
+        if rows.is_nan() || rows > MAX_RESAMPLED_ROWS as f64 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Resampling at {} Hz would write {:.0} rows, more than the maximum of {}",
+                    rate, rows, MAX_RESAMPLED_ROWS
+                ),
+            ));
+        }
+    }
+
+    let mut w = BufWriter::new(File::create(path)?);
+    write_header(&mut w, signals, options)?;
+    let Some([start, end]) = span else {
+        return w.flush();
+    };
+
+    let mut i = 0;
+    loop {
+        let t = start + i as f64 / rate;
+        if t > end {
+            break;
+        }
+
+        let mut row = vec![options.format(t)];
+        row.extend(
+            signals
+                .iter()
+                .map(|s| s.value_at(t).map(|v| options.format(v)).unwrap_or_default()),
+        );
+        writeln!(w, "{}", row.join(&options.separator))?;
+        i += 1;
+    }
+
+    w.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{signals_with, TempPath};
+
+    fn export(
+        data: &[(&str, &[(f64, f64)])],
+        layout: CsvLayout,
+        range: Option<[f64; 2]>,
+    ) -> io::Result<String> {
+        let (signals, ids) = signals_with(data);
+        let file = TempPath::new("csv");
+        let options = CsvExportOptions {
+            layout,
+            range,
+            ..Default::default()
+        };
+        signals.export_csv(&ids, file.path(), &options)?;
+        Ok(fs::read_to_string(file.path()).unwrap())
+    }
+
+    #[test]
+    fn union() {
+        let a: &[_] = &[(0.0, 1.0), (1.0, 2.0), (2.0, 3.0)];
+        let b: &[_] = &[(0.5, 10.0), (1.0, 20.0)];
+        assert_eq!(
+            export(&[("/a", a), ("/b", b)], CsvLayout::Union, None).unwrap(),
+            "time,/a,/b\n0,1,\n0.5,,10\n1,2,20\n2,3,\n"
+        );
+        assert_eq!(
+            export(&[("/a", a), ("/b", b)], CsvLayout::Union, Some([0.5, 1.0])).unwrap(),
+            "time,/a,/b\n0.5,,10\n1,2,20\n"
+        );
+        assert_eq!(
+            export(&[("/a", a)], CsvLayout::Union, Some([3.0, 4.0])).unwrap(),
+            "time,/a\n"
+        );
+    }
+
+    #[test]
+    fn resampled() {
+        let a: &[_] = &[(0.0, 0.0), (1.0, 10.0)];
+        let b: &[_] = &[(0.5, 5.0), (1.0, 0.0)];
+        let layout = CsvLayout::Resampled { rate: 4.0 };
+        assert_eq!(
+            export(&[("/a", a), ("/b", b)], layout, None).unwrap(),
+            "time,/a,/b\n0,0,\n0.25,2.5,\n0.5,5,5\n0.75,7.5,2.5\n1,10,0\n"
+        );
+        assert_eq!(
+            export(&[("/a", a), ("/b", b)], layout, Some([0.5, 2.0])).unwrap(),
+            "time,/a,/b\n0.5,5,5\n0.75,7.5,2.5\n1,10,0\n"
+        );
+    }
+
+    #[test]
+    fn resampled_errors() {
+        let a: &[_] = &[(0.0, 0.0), (1.0, 10.0)];
+        // The last one would write one row more than the maximum
+        for rate in [
+            0.0,
+            -1.0,
+            f64::NAN,
+            f64::INFINITY,
+            MAX_RESAMPLED_ROWS as f64,
+        ] {
+            let err = export(&[("/a", a)], CsvLayout::Resampled { rate }, None).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput, "rate {}", rate);
+        }
+    }
+
+    #[test]
+    fn per_signal_names() {
+        let sample: &[_] = &[(0.0, 1.0)];
+        let (signals, ids) = signals_with(&[
+            ("/a/b_c", sample),
+            ("/a_b/c", sample),
+            ("/X", sample),
+            ("/x", sample),
+        ]);
+        let dir = TempPath::new("d");
+        let options = CsvExportOptions {
+            layout: CsvLayout::PerSignal,
+            ..Default::default()
+        };
+
+        let paths = signals.export_csv(&ids, dir.path(), &options).unwrap();
+        let names: Vec<_> = paths
+            .iter()
+            .map(|p| p.strip_prefix(dir.path()).unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(names, ["a_b_c.csv", "a_b_c_2.csv", "X.csv", "x_2.csv"]);
+        assert_eq!(fs::read_to_string(&paths[1]).unwrap(), "time,/a_b/c\n0,1\n");
+    }
+}
@@ -1,3 +1,31 @@
+#[cfg(feature = "parquet")]
+mod columnar;
+mod export;
+mod mavlink;
+#[cfg(feature = "mcap")]
+mod mcap;
+mod recording;
+mod replay;
+#[cfg(any(feature = "parquet", feature = "mcap"))]
+mod selection;
+mod server;
 mod signal;
+#[cfg(test)]
+mod test_utils;
+mod ulog;
 
-pub use signal::{PlotSignal, PlotSignalError, PlotSignalID, PlotSampleSender, PlotSignalSample, PlotSignalSendError, PlotSignals};
+#[cfg(feature = "parquet")]
+pub use columnar::{ColumnarError, ColumnarOptions};
+pub use export::{CsvExportOptions, CsvLayout};
+pub use mavlink::MavlinkReceiver;
+#[cfg(feature = "mcap")]
+pub use mcap::{McapChannel, McapError, McapOptions, McapTimestamp};
+pub use recording::{
+    RecordedSignal, Recorder, RecordingError, RecordingIndexEntry, RecordingReader,
+};
+pub use replay::{ReplayHandle, MAX_REPLAY_SPEED, MIN_REPLAY_SPEED};
+pub use server::{
+    is_valid_namespace, ProtocolError, ServerStats, SignalServer, WireMessage, WIRE_MAGIC,
+};
+pub use signal::{PlotEvent, PlotSignal, PlotSignalError, PlotSignalID, PlotSampleSender, PlotSignalSample, PlotSignalSendError, PlotSignals, SignalRegistry, validate_signal_name};
+pub use ulog::UlogError;
@@ -0,0 +1,103 @@
+//! Parsing of MAVLink v1 and v2 frames, see https://mavlink.io/en/guide/serialization.html
+
+const STX_V1: u8 = 0xFE;
+const STX_V2: u8 = 0xFD;
+const HEADER_LEN_V1: usize = 6;
+const HEADER_LEN_V2: usize = 10;
+const CHECKSUM_LEN: usize = 2;
+const SIGNATURE_LEN: usize = 13;
+const IFLAG_SIGNED: u8 = 0x01;
+
+/// CRC-16/MCRF4XX, called X.25 by MAVLink
+pub(super) fn crc_accumulate(crc: u16, data: &[u8]) -> u16 {
+    data.iter().fold(crc, |crc, &byte| {
+        let tmp = byte ^ (crc as u8);
+        let tmp = tmp ^ (tmp << 4);
+        (crc >> 8) ^ ((tmp as u16) << 8) ^ ((tmp as u16) << 3) ^ ((tmp as u16) >> 4)
+    })
+}
+
+pub(super) const CRC_INIT: u16 = 0xFFFF;
+
+#[derive(Debug)]
+pub(super) struct Frame<'a> {
+    pub v2: bool,
+    pub seq: u8,
+    pub system_id: u8,
+    pub component_id: u8,
+    pub message_id: u32,
+    pub payload: &'a [u8],
+    /// Header, without the start byte, and payload, covered by the checksum
+    checked: &'a [u8],
+    checksum: u16,
+}
+
+impl Frame<'_> {
+    /// Verifies the checksum, which is seeded with the `CRC_EXTRA` of the message definition
+    pub fn checksum_ok(&self, crc_extra: u8) -> bool {
+        let crc = crc_accumulate(crc_accumulate(CRC_INIT, self.checked), &[crc_extra]);
+        crc == self.checksum
+    }
+}
+
+/// Finds the next frame in `data`, skipping anything before its start byte. Returns the frame
+/// and the data following it, or `None` if there is no complete frame left.
+///
+/// The checksum can't be verified without the message definition, so if it turns out to be
+/// wrong, parsing should resume right after the start byte with [`skip_start`].
+pub(super) fn next_frame(mut data: &[u8]) -> Option<(Frame<'_>, &[u8])> {
+    loop {
+        let start = data.iter().position(|&b| b == STX_V1 || b == STX_V2)?;
+        data = &data[start..];
+        match parse_frame(data) {
+            Some(parsed) => return Some(parsed),
+            None => data = skip_start(data),
+        }
+    }
+}
+
+/// Skips the start byte of a frame
+pub(super) fn skip_start(data: &[u8]) -> &[u8] {
+    data.get(1..).unwrap_or_default()
+}
+
+fn parse_frame(data: &[u8]) -> Option<(Frame<'_>, &[u8])> {
+    let v2 = data[0] == STX_V2;
+    let header_len = if v2 { HEADER_LEN_V2 } else { HEADER_LEN_V1 };
+    let header = data.get(..header_len)?;
+    let payload_len = header[1] as usize;
+
+    let (seq, system_id, component_id, message_id, signature_len) = if v2 {
+        let incompat_flags = header[2];
+        if incompat_flags & !IFLAG_SIGNED != 0 {
+            return None;
+        }
+        let message_id = u32::from_le_bytes([header[7], header[8], header[9], 0]);
+        let signature_len = if incompat_flags & IFLAG_SIGNED != 0 {
+            SIGNATURE_LEN
+        } else {
+            0
+        };
+        (header[4], header[5], header[6], message_id, signature_len)
+    } else {
+        (header[2], header[3], header[4], header[5] as u32, 0)
+    };
+
+    let checksum_start = header_len + payload_len;
+    let end = checksum_start + CHECKSUM_LEN + signature_len;
+    if data.len() < end {
+        return None;
+    }
+
+    let frame = Frame {
+        v2,
+        seq,
+        system_id,
+        component_id,
+        message_id,
+        payload: &data[header_len..checksum_start],
+        checked: &data[1..checksum_start],
+        checksum: u16::from_le_bytes([data[checksum_start], data[checksum_start + 1]]),
+    };
+    Some((frame, &data[end..]))
+}
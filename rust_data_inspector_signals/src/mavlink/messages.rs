@@ -0,0 +1,263 @@
+//! Definitions of the telemetry messages of the MAVLink `common` dialect
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use super::frame::{crc_accumulate, CRC_INIT};
+
+/// Message id, name, fields and extension fields, in the order of the XML definition
+const DEFINITIONS: &[(u32, &str, &str, &str)] = &[
+    (0, "HEARTBEAT", "uint8_t type, uint8_t autopilot, uint8_t base_mode, uint32_t custom_mode, uint8_t system_status, uint8_t mavlink_version", ""),
+    (1, "SYS_STATUS", "uint32_t onboard_control_sensors_present, uint32_t onboard_control_sensors_enabled, uint32_t onboard_control_sensors_health, uint16_t load, uint16_t voltage_battery, int16_t current_battery, int8_t battery_remaining, uint16_t drop_rate_comm, uint16_t errors_comm, uint16_t errors_count1, uint16_t errors_count2, uint16_t errors_count3, uint16_t errors_count4", "uint32_t onboard_control_sensors_present_extended, uint32_t onboard_control_sensors_enabled_extended, uint32_t onboard_control_sensors_health_extended"),
+    (2, "SYSTEM_TIME", "uint64_t time_unix_usec, uint32_t time_boot_ms", ""),
+    (4, "PING", "uint64_t time_usec, uint32_t seq, uint8_t target_system, uint8_t target_component", ""),
+    (24, "GPS_RAW_INT", "uint64_t time_usec, uint8_t fix_type, int32_t lat, int32_t lon, int32_t alt, uint16_t eph, uint16_t epv, uint16_t vel, uint16_t cog, uint8_t satellites_visible", "int32_t alt_ellipsoid, uint32_t h_acc, uint32_t v_acc, uint32_t vel_acc, uint32_t hdg_acc, uint16_t yaw"),
+    (26, "SCALED_IMU", "uint32_t time_boot_ms, int16_t xacc, int16_t yacc, int16_t zacc, int16_t xgyro, int16_t ygyro, int16_t zgyro, int16_t xmag, int16_t ymag, int16_t zmag", "int16_t temperature"),
+    (27, "RAW_IMU", "uint64_t time_usec, int16_t xacc, int16_t yacc, int16_t zacc, int16_t xgyro, int16_t ygyro, int16_t zgyro, int16_t xmag, int16_t ymag, int16_t zmag", "uint8_t id, int16_t temperature"),
+    (28, "RAW_PRESSURE", "uint64_t time_usec, int16_t press_abs, int16_t press_diff1, int16_t press_diff2, int16_t temperature", ""),
+    (29, "SCALED_PRESSURE", "uint32_t time_boot_ms, float press_abs, float press_diff, int16_t temperature", "int16_t temperature_press_diff"),
+    (30, "ATTITUDE", "uint32_t time_boot_ms, float roll, float pitch, float yaw, float rollspeed, float pitchspeed, float yawspeed", ""),
+    (31, "ATTITUDE_QUATERNION", "uint32_t time_boot_ms, float q1, float q2, float q3, float q4, float rollspeed, float pitchspeed, float yawspeed", "float[4] repr_offset_q"),
+    (32, "LOCAL_POSITION_NED", "uint32_t time_boot_ms, float x, float y, float z, float vx, float vy, float vz", ""),
+    (33, "GLOBAL_POSITION_INT", "uint32_t time_boot_ms, int32_t lat, int32_t lon, int32_t alt, int32_t relative_alt, int16_t vx, int16_t vy, int16_t vz, uint16_t hdg", ""),
+    (35, "RC_CHANNELS_RAW", "uint32_t time_boot_ms, uint8_t port, uint16_t chan1_raw, uint16_t chan2_raw, uint16_t chan3_raw, uint16_t chan4_raw, uint16_t chan5_raw, uint16_t chan6_raw, uint16_t chan7_raw, uint16_t chan8_raw, uint8_t rssi", ""),
+    (36, "SERVO_OUTPUT_RAW", "uint32_t time_usec, uint8_t port, uint16_t servo1_raw, uint16_t servo2_raw, uint16_t servo3_raw, uint16_t servo4_raw, uint16_t servo5_raw, uint16_t servo6_raw, uint16_t servo7_raw, uint16_t servo8_raw", "uint16_t servo9_raw, uint16_t servo10_raw, uint16_t servo11_raw, uint16_t servo12_raw, uint16_t servo13_raw, uint16_t servo14_raw, uint16_t servo15_raw, uint16_t servo16_raw"),
+    (42, "MISSION_CURRENT", "uint16_t seq", "uint16_t total, uint8_t mission_state, uint8_t mission_mode, uint32_t mission_id, uint32_t fence_id, uint32_t rally_points_id"),
+    (62, "NAV_CONTROLLER_OUTPUT", "float nav_roll, float nav_pitch, int16_t nav_bearing, int16_t target_bearing, uint16_t wp_dist, float alt_error, float aspd_error, float xtrack_error", ""),
+    (65, "RC_CHANNELS", "uint32_t time_boot_ms, uint8_t chancount, uint16_t chan1_raw, uint16_t chan2_raw, uint16_t chan3_raw, uint16_t chan4_raw, uint16_t chan5_raw, uint16_t chan6_raw, uint16_t chan7_raw, uint16_t chan8_raw, uint16_t chan9_raw, uint16_t chan10_raw, uint16_t chan11_raw, uint16_t chan12_raw, uint16_t chan13_raw, uint16_t chan14_raw, uint16_t chan15_raw, uint16_t chan16_raw, uint16_t chan17_raw, uint16_t chan18_raw, uint8_t rssi", ""),
+    (74, "VFR_HUD", "float airspeed, float groundspeed, int16_t heading, uint16_t throttle, float alt, float climb", ""),
+    (83, "ATTITUDE_TARGET", "uint32_t time_boot_ms, uint8_t type_mask, float[4] q, float body_roll_rate, float body_pitch_rate, float body_yaw_rate, float thrust", "float[3] thrust_body"),
+    (85, "POSITION_TARGET_LOCAL_NED", "uint32_t time_boot_ms, uint8_t coordinate_frame, uint16_t type_mask, float x, float y, float z, float vx, float vy, float vz, float afx, float afy, float afz, float yaw, float yaw_rate", ""),
+    (87, "POSITION_TARGET_GLOBAL_INT", "uint32_t time_boot_ms, uint8_t coordinate_frame, uint16_t type_mask, int32_t lat_int, int32_t lon_int, float alt, float vx, float vy, float vz, float afx, float afy, float afz, float yaw, float yaw_rate", ""),
+    (105, "HIGHRES_IMU", "uint64_t time_usec, float xacc, float yacc, float zacc, float xgyro, float ygyro, float zgyro, float xmag, float ymag, float zmag, float abs_pressure, float diff_pressure, float pressure_alt, float temperature, uint16_t fields_updated", "uint8_t id"),
+    (109, "RADIO_STATUS", "uint8_t rssi, uint8_t remrssi, uint8_t txbuf, uint8_t noise, uint8_t remnoise, uint16_t rxerrors, uint16_t fixed", ""),
+    (111, "TIMESYNC", "int64_t tc1, int64_t ts1", "uint8_t target_system, uint8_t target_component"),
+    (116, "SCALED_IMU2", "uint32_t time_boot_ms, int16_t xacc, int16_t yacc, int16_t zacc, int16_t xgyro, int16_t ygyro, int16_t zgyro, int16_t xmag, int16_t ymag, int16_t zmag", "int16_t temperature"),
+    (129, "SCALED_IMU3", "uint32_t time_boot_ms, int16_t xacc, int16_t yacc, int16_t zacc, int16_t xgyro, int16_t ygyro, int16_t zgyro, int16_t xmag, int16_t ymag, int16_t zmag", "int16_t temperature"),
+    (137, "SCALED_PRESSURE2", "uint32_t time_boot_ms, float press_abs, float press_diff, int16_t temperature", "int16_t temperature_press_diff"),
+    (141, "ALTITUDE", "uint64_t time_usec, float altitude_monotonic, float altitude_amsl, float altitude_local, float altitude_relative, float altitude_terrain, float bottom_clearance", ""),
+    (143, "SCALED_PRESSURE3", "uint32_t time_boot_ms, float press_abs, float press_diff, int16_t temperature", "int16_t temperature_press_diff"),
+    (147, "BATTERY_STATUS", "uint8_t id, uint8_t battery_function, uint8_t type, int16_t temperature, uint16_t[10] voltages, int16_t current_battery, int32_t current_consumed, int32_t energy_consumed, int8_t battery_remaining", "int32_t time_remaining, uint8_t charge_state, uint16_t[4] voltages_ext, uint8_t mode, uint32_t fault_bitmask"),
+    (230, "ESTIMATOR_STATUS", "uint64_t time_usec, uint16_t flags, float vel_ratio, float pos_horiz_ratio, float pos_vert_ratio, float mag_ratio, float hagl_ratio, float tas_ratio, float pos_horiz_accuracy, float pos_vert_accuracy", ""),
+    (241, "VIBRATION", "uint64_t time_usec, float vibration_x, float vibration_y, float vibration_z, uint32_t clipping_0, uint32_t clipping_1, uint32_t clipping_2", ""),
+    (245, "EXTENDED_SYS_STATE", "uint8_t vtol_state, uint8_t landed_state", ""),
+    (253, "STATUSTEXT", "uint8_t severity, char[50] text", "uint16_t id, uint8_t chunk_seq"),
+];
+
+pub(super) const STATUSTEXT: u32 = 253;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum FieldType {
+    U8,
+    I8,
+    Char,
+    U16,
+    I16,
+    U32,
+    I32,
+    F32,
+    U64,
+    I64,
+    F64,
+}
+
+impl FieldType {
+    fn parse(name: &str) -> Self {
+        match name {
+            "uint8_t" => FieldType::U8,
+            "int8_t" => FieldType::I8,
+            "char" => FieldType::Char,
+            "uint16_t" => FieldType::U16,
+            "int16_t" => FieldType::I16,
+            "uint32_t" => FieldType::U32,
+            "int32_t" => FieldType::I32,
+            "float" => FieldType::F32,
+            "uint64_t" => FieldType::U64,
+            "int64_t" => FieldType::I64,
+            "double" => FieldType::F64,
+            _ => panic!("Unknown MAVLink type {name}"),
+        }
+    }
+
+    fn c_name(self) -> &'static str {
+        match self {
+            FieldType::U8 => "uint8_t",
+            FieldType::I8 => "int8_t",
+            FieldType::Char => "char",
+            FieldType::U16 => "uint16_t",
+            FieldType::I16 => "int16_t",
+            FieldType::U32 => "uint32_t",
+            FieldType::I32 => "int32_t",
+            FieldType::F32 => "float",
+            FieldType::U64 => "uint64_t",
+            FieldType::I64 => "int64_t",
+            FieldType::F64 => "double",
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            FieldType::U8 | FieldType::I8 | FieldType::Char => 1,
+            FieldType::U16 | FieldType::I16 => 2,
+            FieldType::U32 | FieldType::I32 | FieldType::F32 => 4,
+            FieldType::U64 | FieldType::I64 | FieldType::F64 => 8,
+        }
+    }
+
+    fn read(self, bytes: &[u8]) -> f64 {
+        match self {
+            FieldType::U8 | FieldType::Char => bytes[0] as f64,
+            FieldType::I8 => bytes[0] as i8 as f64,
+            FieldType::U16 => u16::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            FieldType::I16 => i16::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            FieldType::U32 => u32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            FieldType::I32 => i32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            FieldType::F32 => f32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            FieldType::U64 => u64::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            FieldType::I64 => i64::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            FieldType::F64 => f64::from_le_bytes(bytes.try_into().unwrap()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(super) struct Field {
+    pub name: &'static str,
+    pub ty: FieldType,
+    /// Array length, or `None` for scalars
+    pub array: Option<usize>,
+    pub extension: bool,
+    /// Offset in the payload
+    pub offset: usize,
+}
+
+impl Field {
+    fn len(&self) -> usize {
+        self.ty.size() * self.array.unwrap_or(1)
+    }
+}
+
+#[derive(Debug)]
+pub(super) struct MessageDefinition {
+    pub name: &'static str,
+    /// Fields in the order of the XML definition
+    pub fields: Vec<Field>,
+    pub crc_extra: u8,
+    /// Payload length including extension fields
+    pub len: usize,
+}
+
+impl MessageDefinition {
+    fn new(name: &'static str, fields: &'static str, extensions: &'static str) -> Self {
+        let parse = |fields: &'static str, extension: bool| {
+            fields
+                .split(',')
+                .map(str::trim)
+                .filter(|f| !f.is_empty())
+                .map(move |f| {
+                    let (ty, name) = f.split_once(' ').unwrap();
+                    let (ty, array) = match ty.split_once('[') {
+                        Some((ty, len)) => (ty, Some(len.trim_end_matches(']').parse().unwrap())),
+                        None => (ty, None),
+                    };
+                    Field {
+                        name,
+                        ty: FieldType::parse(ty),
+                        array,
+                        extension,
+                        offset: 0,
+                    }
+                })
+        };
+        let mut fields: Vec<Field> = parse(fields, false)
+            .chain(parse(extensions, true))
+            .collect();
+
+        // On the wire, fields are sorted by decreasing type size, with extensions appended
+        // in the order of the definition. The sort is stable, as required.
+        let base_len = fields.iter().filter(|f| !f.extension).count();
+        let mut wire_order: Vec<usize> = (0..fields.len()).collect();
+        wire_order[..base_len].sort_by_key(|&i| std::cmp::Reverse(fields[i].ty.size()));
+
+        let mut crc = crc_accumulate(CRC_INIT, format!("{} ", name).as_bytes());
+        let mut offset = 0;
+        for i in wire_order {
+            let field = &mut fields[i];
+            field.offset = offset;
+            offset += field.len();
+
+            if !field.extension {
+                crc = crc_accumulate(
+                    crc,
+                    format!("{} {} ", field.ty.c_name(), field.name).as_bytes(),
+                );
+                if let Some(len) = field.array {
+                    crc = crc_accumulate(crc, &[len as u8]);
+                }
+            }
+        }
+
+        MessageDefinition {
+            name,
+            fields,
+            crc_extra: ((crc & 0xFF) ^ (crc >> 8)) as u8,
+            len: offset,
+        }
+    }
+
+    /// Calls `out` with the value of each numeric field, in the order of the definition, with
+    /// arrays flattened. Extension fields are skipped unless `extensions` is set.
+    ///
+    /// The payload may be shorter than the message, as MAVLink v2 strips its trailing zeros.
+    pub fn decode(&self, payload: &[u8], extensions: bool, mut out: impl FnMut(f64)) {
+        let mut buf = vec![0; self.len];
+        let n = payload.len().min(self.len);
+        buf[..n].copy_from_slice(&payload[..n]);
+
+        for field in self.numeric_fields(extensions) {
+            let size = field.ty.size();
+            for i in 0..field.array.unwrap_or(1) {
+                let start = field.offset + i * size;
+                out(field.ty.read(&buf[start..start + size]));
+            }
+        }
+    }
+
+    /// Names of the values produced by [`MessageDefinition::decode`], relative to the message
+    pub fn value_names(&self, extensions: bool) -> Vec<String> {
+        self.numeric_fields(extensions)
+            .flat_map(|field| match field.array {
+                None => vec![field.name.to_string()],
+                Some(len) => (0..len).map(|i| format!("{}/{}", field.name, i)).collect(),
+            })
+            .collect()
+    }
+
+    /// Reads a `char` array field as text, up to its first null byte
+    pub fn text(&self, payload: &[u8], name: &str) -> Option<String> {
+        let field = self.fields.iter().find(|f| f.name == name)?;
+        let bytes = payload.get(field.offset..).unwrap_or_default();
+        let bytes = &bytes[..bytes.len().min(field.len())];
+        let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+        Some(String::from_utf8_lossy(&bytes[..end]).into_owned())
+    }
+
+    fn numeric_fields(&self, extensions: bool) -> impl Iterator<Item = &Field> {
+        self.fields
+            .iter()
+            .filter(move |f| f.ty != FieldType::Char && (extensions || !f.extension))
+    }
+}
+
+/// Definitions of the supported messages, by id
+pub(super) fn definitions() -> &'static HashMap<u32, MessageDefinition> {
+    static DEFINITIONS_BY_ID: OnceLock<HashMap<u32, MessageDefinition>> = OnceLock::new();
+    DEFINITIONS_BY_ID.get_or_init(|| {
+        DEFINITIONS
+            .iter()
+            .map(|&(id, name, fields, extensions)| {
+                (id, MessageDefinition::new(name, fields, extensions))
+            })
+            .collect()
+    })
+}
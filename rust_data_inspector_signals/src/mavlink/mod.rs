@@ -0,0 +1,338 @@
+//! Reception of MAVLink telemetry over UDP, like the one of a SITL simulation or a telemetry
+//! radio forwarded by a ground station.
+//!
+//! Messages of the `common` dialect are decoded into signals named
+//! `/<system id>/<component id>/<MESSAGE>/<field>`, created as they are first received.
+//! Statistics of each link, computed from the sequence numbers of the frames, are available
+//! as `/<system id>/<component id>/link/...` signals. Reception errors are reported as events.
+
+mod frame;
+mod messages;
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{PlotSampleSender, PlotSignalSample, PlotSignals, SignalRegistry};
+
+use frame::{next_frame, skip_start, Frame};
+use messages::{definitions, STATUSTEXT};
+
+/// Largest UDP datagram
+const MAX_DATAGRAM_LEN: usize = 65536;
+/// How often the link statistics are updated
+const STATS_PERIOD: Duration = Duration::from_secs(1);
+/// Sequence numbers further ahead than this are frames arriving out of order, behind the last one
+const MAX_SEQ_GAP: u8 = 128;
+
+const SEVERITIES: [&str; 8] = [
+    "EMERGENCY",
+    "ALERT",
+    "CRITICAL",
+    "ERROR",
+    "WARNING",
+    "NOTICE",
+    "INFO",
+    "DEBUG",
+];
+
+/// Receives MAVLink v1 and v2 telemetry on a UDP socket, adding signals as messages of new
+/// systems, components or types arrive. Samples are timestamped with the time of reception,
+/// in seconds since the receiver started.
+///
+/// `STATUSTEXT` messages are added as events. Extension fields are only decoded from
+/// MAVLink v2 frames, as v1 senders don't know about them.
+///
+/// Reception stops when the receiver is dropped.
+#[derive(Debug)]
+pub struct MavlinkReceiver {
+    local_addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+}
+
+impl MavlinkReceiver {
+    /// Binds a UDP socket to `addr`, like `0.0.0.0:14550`, and starts receiving
+    pub fn listen(addr: impl ToSocketAddrs, signals: &PlotSignals) -> io::Result<Self> {
+        let socket = UdpSocket::bind(addr)?;
+        // Wakes the thread up to stop and to update the statistics of silent links
+        socket.set_read_timeout(Some(STATS_PERIOD / 4))?;
+
+        let receiver = MavlinkReceiver {
+            local_addr: socket.local_addr()?,
+            stop: Arc::new(AtomicBool::new(false)),
+        };
+
+        let stop = receiver.stop.clone();
+        let mut decoder = Decoder::new(signals.registry());
+        thread::spawn(move || {
+            let mut buf = vec![0; MAX_DATAGRAM_LEN];
+            while !stop.load(Ordering::Relaxed) {
+                match socket.recv(&mut buf) {
+                    Ok(len) => decoder.decode_datagram(&buf[..len]),
+                    Err(e)
+                        if matches!(
+                            e.kind(),
+                            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                        ) => {}
+                    Err(e) => {
+                        decoder.report(&format!("MAVLink reception failed: {}", e));
+                        return;
+                    }
+                }
+                decoder.update_stats();
+            }
+        });
+
+        Ok(receiver)
+    }
+
+    /// Address the socket is bound to, useful when binding to port 0
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+impl Drop for MavlinkReceiver {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+impl PlotSignals {
+    /// Starts receiving MAVLink telemetry on `addr`, see [`MavlinkReceiver`]
+    pub fn listen_mavlink(&self, addr: impl ToSocketAddrs) -> io::Result<MavlinkReceiver> {
+        MavlinkReceiver::listen(addr, self)
+    }
+}
+
+/// Statistics of the frames received from a component
+#[derive(Default)]
+struct Link {
+    last_seq: Option<u8>,
+    received: u64,
+    lost: u64,
+    crc_errors: u64,
+    /// Received and lost since the last update of the statistics signals
+    period_received: u64,
+    period_lost: u64,
+}
+
+impl Link {
+    fn receive(&mut self, seq: u8) {
+        self.received += 1;
+        self.period_received += 1;
+        let Some(last) = self.last_seq else {
+            self.last_seq = Some(seq);
+            return;
+        };
+        match seq.wrapping_sub(last) {
+            // Duplicated frame
+            0 => {}
+            // The frames between the last one and this one were lost
+            gap @ 1..=MAX_SEQ_GAP => {
+                let lost = (gap - 1) as u64;
+                self.lost += lost;
+                self.period_lost += lost;
+                self.last_seq = Some(seq);
+            }
+            // Frame sent before the last one, arriving out of order
+            _ => {}
+        }
+    }
+}
+
+struct Decoder {
+    registry: SignalRegistry,
+    start: Instant,
+    /// Links by system and component id
+    links: BTreeMap<(u8, u8), Link>,
+    /// Sender of each value of the messages of each component, created on first use
+    senders: HashMap<(u8, u8, u32), Vec<Option<PlotSampleSender>>>,
+    stats_senders: HashMap<String, PlotSampleSender>,
+    /// Signals that could not be added, reported only once
+    invalid_signals: HashSet<String>,
+    last_stats: Instant,
+}
+
+impl Decoder {
+    fn new(registry: SignalRegistry) -> Self {
+        let now = Instant::now();
+        Decoder {
+            registry,
+            start: now,
+            links: BTreeMap::new(),
+            senders: HashMap::new(),
+            stats_senders: HashMap::new(),
+            invalid_signals: HashSet::new(),
+            last_stats: now,
+        }
+    }
+
+    /// Reports an error as an event at the current time
+    fn report(&self, text: &str) {
+        self.registry
+            .add_event(self.start.elapsed().as_secs_f64(), text);
+    }
+
+    fn decode_datagram(&mut self, mut data: &[u8]) {
+        let time = self.start.elapsed().as_secs_f64();
+        while let Some((frame, rest)) = next_frame(data) {
+            let key = (frame.system_id, frame.component_id);
+            match definitions().get(&frame.message_id) {
+                Some(definition) if frame.checksum_ok(definition.crc_extra) => {
+                    self.links.entry(key).or_default().receive(frame.seq);
+                    self.decode_message(&frame, time);
+                }
+                Some(_) => {
+                    // Likely a corrupted frame, or a start byte in the middle of something else
+                    if let Some(link) = self.links.get_mut(&key) {
+                        link.crc_errors += 1;
+                    }
+                    data = skip_start(data);
+                    continue;
+                }
+                None => {
+                    // Unknown messages can't be verified, so they are only trusted to come
+                    // from a component that already sent valid frames
+                    if let Some(link) = self.links.get_mut(&key) {
+                        link.receive(frame.seq);
+                    }
+                }
+            }
+            data = rest;
+        }
+    }
+
+    fn decode_message(&mut self, frame: &Frame, time: f64) {
+        let definition = &definitions()[&frame.message_id];
+        let key = (frame.system_id, frame.component_id, frame.message_id);
+
+        if frame.message_id == STATUSTEXT {
+            if let Some(text) = definition.text(frame.payload, "text") {
+                let severity = frame.payload.first().map_or(SEVERITIES[0], |&s| {
+                    SEVERITIES.get(s as usize).copied().unwrap_or("UNKNOWN")
+                });
+                self.registry.add_event(
+                    time,
+                    &format!(
+                        "{}/{} {}: {}",
+                        frame.system_id, frame.component_id, severity, text
+                    ),
+                );
+            }
+        }
+
+        let senders = self.senders.entry(key).or_insert_with(|| {
+            let names = definition.value_names(true);
+            vec![None; names.len()]
+        });
+
+        let registry = &self.registry;
+        let invalid_signals = &mut self.invalid_signals;
+        let mut names = None;
+        let mut i = 0;
+        definition.decode(frame.payload, frame.v2, |value| {
+            if senders[i].is_none() {
+                let names = names.get_or_insert_with(|| definition.value_names(true));
+                let name = format!(
+                    "/{}/{}/{}/{}",
+                    frame.system_id, frame.component_id, definition.name, names[i]
+                );
+                if !invalid_signals.contains(&name) {
+                    match registry.add_signal(&name) {
+                        Ok(sender) => senders[i] = Some(sender),
+                        Err(e) => {
+                            registry.add_event(time, &format!("Can't add MAVLink signal: {}", e));
+                            invalid_signals.insert(name);
+                        }
+                    }
+                }
+            }
+            if let Some(sender) = &senders[i] {
+                let _ = sender.send(PlotSignalSample { time, value });
+            }
+            i += 1;
+        });
+    }
+
+    /// Sends the statistics of each link, once per [`STATS_PERIOD`]
+    fn update_stats(&mut self) {
+        let elapsed = self.last_stats.elapsed();
+        if elapsed < STATS_PERIOD {
+            return;
+        }
+        self.last_stats = Instant::now();
+        let time = self.start.elapsed().as_secs_f64();
+
+        for (&(system_id, component_id), link) in self.links.iter_mut() {
+            let expected = link.period_received + link.period_lost;
+            let loss = if expected > 0 {
+                100.0 * link.period_lost as f64 / expected as f64
+            } else {
+                0.0
+            };
+            let stats = [
+                ("received", link.received as f64),
+                ("lost", link.lost as f64),
+                ("loss_percent", loss),
+                ("rate", link.period_received as f64 / elapsed.as_secs_f64()),
+                ("crc_errors", link.crc_errors as f64),
+            ];
+            link.period_received = 0;
+            link.period_lost = 0;
+
+            for (stat, value) in stats {
+                let name = format!("/{}/{}/link/{}", system_id, component_id, stat);
+                let sender = match self.stats_senders.get(&name) {
+                    Some(sender) => sender,
+                    None if self.invalid_signals.contains(&name) => continue,
+                    None => match self.registry.add_signal(&name) {
+                        Ok(sender) => self.stats_senders.entry(name).or_insert(sender),
+                        Err(e) => {
+                            self.registry
+                                .add_event(time, &format!("Can't add MAVLink signal: {}", e));
+                            self.invalid_signals.insert(name);
+                            continue;
+                        }
+                    },
+                };
+                let _ = sender.send(PlotSignalSample { time, value });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn receive(seqs: &[u8]) -> Link {
+        let mut link = Link::default();
+        for &seq in seqs {
+            link.receive(seq);
+        }
+        link
+    }
+
+    #[test]
+    fn lost_frames() {
+        let link = receive(&[250, 251, 254, 255, 0, 3]);
+        assert_eq!(link.received, 6);
+        assert_eq!(link.lost, 4);
+    }
+
+    #[test]
+    fn duplicated_and_reordered_frames() {
+        // Duplicates are not lost frames
+        assert_eq!(receive(&[7, 7, 8]).lost, 0);
+        // Late frames don't count the whole sequence range as lost
+        let link = receive(&[5, 7, 6, 8]);
+        assert_eq!(link.received, 4);
+        assert_eq!(link.lost, 1);
+        assert_eq!(link.last_seq, Some(8));
+    }
+}
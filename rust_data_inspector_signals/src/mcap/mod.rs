@@ -0,0 +1,419 @@
+mod reader;
+mod ros2;
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use prost_reflect::{
+    DescriptorPool, DynamicMessage, MessageDescriptor, ReflectMessage, Value as ProtoValue,
+};
+use serde_json::Value as JsonValue;
+use thiserror::Error;
+
+use crate::{
+    selection::{self, is_selected, overlaps, sanitize},
+    PlotSignalError, PlotSignalID, PlotSignalSample, PlotSignals,
+};
+use reader::{Channel, Fields, Message, Schema};
+use ros2::MessageDefinitions;
+
+/// Arrays with more elements than this are not loaded, as they usually hold bulk data,
+/// like images or point clouds, rather than signals
+const MAX_ARRAY_LEN: usize = 32;
+
+/// Which timestamp of the messages is used as time of the samples
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum McapTimestamp {
+    /// Time the message was recorded
+    #[default]
+    Log,
+    /// Time the message was published
+    Publish,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct McapOptions {
+    pub timestamp: McapTimestamp,
+    /// Channels or fields to load, as signal paths like `/imu/linear_acceleration/x`.
+    /// Selecting a topic or a message field selects all of its fields. Everything is loaded if empty.
+    pub selection: Vec<String>,
+}
+
+/// A channel of an MCAP file
+#[derive(Debug, Clone)]
+pub struct McapChannel {
+    pub topic: String,
+    /// Path of the signals of the channel
+    pub path: String,
+    pub message_encoding: String,
+    pub schema_name: String,
+    pub schema_encoding: String,
+    pub message_count: u64,
+    /// True if the messages of the channel can be decoded
+    pub supported: bool,
+}
+
+#[derive(Debug, Error)]
+pub enum McapError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Malformed MCAP file: {0}")]
+    Malformed(String),
+    #[error("Unsupported chunk compression '{0}'")]
+    Compression(String),
+    #[error("Invalid schema: {0}")]
+    Schema(String),
+    #[error("Error decoding message: {0}")]
+    Decode(String),
+    #[error("Error decoding message of '{topic}': {error}")]
+    Message {
+        topic: String,
+        error: Box<McapError>,
+    },
+    #[error("Invalid signal: {0}")]
+    Signal(#[from] PlotSignalError),
+}
+
+fn is_supported(message_encoding: &str, schema: Option<&Schema>) -> bool {
+    let schema_encoding = schema.map(|s| s.encoding.as_str()).unwrap_or_default();
+    matches!(
+        (message_encoding, schema_encoding),
+        ("json", _) | ("protobuf", "protobuf") | ("cdr", "ros2msg")
+    )
+}
+
+/// Decodes the messages of a channel
+enum Decoder {
+    Json,
+    Protobuf(MessageDescriptor),
+    Cdr(MessageDefinitions),
+}
+
+impl Decoder {
+    fn new(message_encoding: &str, schema: Option<&Schema>) -> Result<Option<Self>, McapError> {
+        if !is_supported(message_encoding, schema) {
+            return Ok(None);
+        }
+        let decoder = match (message_encoding, schema) {
+            ("protobuf", Some(schema)) => {
+                let pool = DescriptorPool::decode(schema.data.as_slice())
+                    .map_err(|e| McapError::Schema(e.to_string()))?;
+                let descriptor = pool.get_message_by_name(&schema.name).ok_or_else(|| {
+                    McapError::Schema(format!("message '{}' not found", schema.name))
+                })?;
+                Decoder::Protobuf(descriptor)
+            }
+            ("cdr", Some(schema)) => {
+                let definition = std::str::from_utf8(&schema.data)
+                    .map_err(|_| McapError::Schema("definition is not valid UTF-8".to_string()))?;
+                Decoder::Cdr(MessageDefinitions::parse(&schema.name, definition)?)
+            }
+            _ => Decoder::Json,
+        };
+        Ok(Some(decoder))
+    }
+
+    /// Calls `out` with the path of each numeric field of the message, relative to it, and its value
+    fn decode(&self, data: &[u8], out: &mut impl FnMut(&str, f64)) -> Result<(), McapError> {
+        let mut path = String::new();
+        match self {
+            Decoder::Json => {
+                let value: JsonValue =
+                    serde_json::from_slice(data).map_err(|e| McapError::Decode(e.to_string()))?;
+                visit_json(&value, &mut path, out);
+            }
+            Decoder::Protobuf(descriptor) => {
+                let message = DynamicMessage::decode(descriptor.clone(), data)
+                    .map_err(|e| McapError::Decode(e.to_string()))?;
+                visit_protobuf(&message, &mut path, out);
+            }
+            Decoder::Cdr(definitions) => definitions.decode(data, out)?,
+        }
+        Ok(())
+    }
+}
+
+fn visit_json(value: &JsonValue, path: &mut String, out: &mut impl FnMut(&str, f64)) {
+    let len = path.len();
+    match value {
+        JsonValue::Bool(b) => out(path, *b as u8 as f64),
+        JsonValue::Number(n) => {
+            if let Some(v) = n.as_f64() {
+                out(path, v);
+            }
+        }
+        JsonValue::Object(fields) => {
+            for (name, value) in fields {
+                path.push('/');
+                path.push_str(name);
+                visit_json(value, path, out);
+                path.truncate(len);
+            }
+        }
+        JsonValue::Array(values) if values.len() <= MAX_ARRAY_LEN => {
+            for (i, value) in values.iter().enumerate() {
+                path.push('/');
+                path.push_str(&i.to_string());
+                visit_json(value, path, out);
+                path.truncate(len);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn visit_protobuf_value(value: &ProtoValue, path: &mut String, out: &mut impl FnMut(&str, f64)) {
+    let len = path.len();
+    match value {
+        ProtoValue::Bool(v) => out(path, *v as u8 as f64),
+        ProtoValue::I32(v) | ProtoValue::EnumNumber(v) => out(path, *v as f64),
+        ProtoValue::I64(v) => out(path, *v as f64),
+        ProtoValue::U32(v) => out(path, *v as f64),
+        ProtoValue::U64(v) => out(path, *v as f64),
+        ProtoValue::F32(v) => out(path, *v as f64),
+        ProtoValue::F64(v) => out(path, *v),
+        ProtoValue::Message(message) => visit_protobuf(message, path, out),
+        ProtoValue::List(values) if values.len() <= MAX_ARRAY_LEN => {
+            for (i, value) in values.iter().enumerate() {
+                path.push('/');
+                path.push_str(&i.to_string());
+                visit_protobuf_value(value, path, out);
+                path.truncate(len);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn visit_protobuf(message: &DynamicMessage, path: &mut String, out: &mut impl FnMut(&str, f64)) {
+    let len = path.len();
+    for field in message.descriptor().fields() {
+        // Only the field set in a oneof has a value
+        if field.containing_oneof().is_some() && !message.has_field(&field) {
+            continue;
+        }
+        path.push('/');
+        path.push_str(field.name());
+        visit_protobuf_value(&message.get_field(&field), path, out);
+        path.truncate(len);
+    }
+}
+
+/// Messages of a topic that could not be decoded
+struct SkippedMessages {
+    count: usize,
+    /// Time of the first skipped message
+    time: f64,
+    /// Error decoding the first skipped message
+    error: McapError,
+}
+
+/// Adds the messages of the selected channels to signals
+struct McapLoader<'a> {
+    options: &'a McapOptions,
+    selection: Vec<String>,
+    schemas: HashMap<u16, Schema>,
+    /// Path and decoder of each channel, or `None` if it is not selected or not supported
+    channels: HashMap<u16, Option<(String, Decoder)>>,
+    signals: PlotSignals,
+    /// Signal of each path, or `None` if the path is not selected or not a valid signal
+    ids: HashMap<String, Option<PlotSignalID>>,
+    samples: HashMap<PlotSignalID, Vec<PlotSignalSample>>,
+    /// Values of the message being decoded, added to `samples` only if it is decoded entirely
+    values: Vec<(PlotSignalID, f64)>,
+    /// Messages that could not be decoded, by topic
+    skipped: BTreeMap<String, SkippedMessages>,
+}
+
+impl McapLoader<'_> {
+    fn add_record(&mut self, opcode: u8, content: &[u8]) -> Result<(), McapError> {
+        match opcode {
+            reader::OP_SCHEMA => {
+                let (id, schema) = Schema::parse(content)?;
+                self.schemas.insert(id, schema);
+            }
+            reader::OP_CHANNEL => {
+                let (id, channel) = Channel::parse(content)?;
+                if self.channels.contains_key(&id) {
+                    return Ok(());
+                }
+                let path = sanitize(&channel.topic);
+                let decoder = if overlaps(&path, &self.selection) {
+                    let schema = self.schemas.get(&channel.schema_id);
+                    Decoder::new(&channel.message_encoding, schema).map_err(|e| {
+                        McapError::Message {
+                            topic: channel.topic.clone(),
+                            error: Box::new(e),
+                        }
+                    })?
+                } else {
+                    None
+                };
+                self.channels.insert(id, decoder.map(|d| (path, d)));
+            }
+            reader::OP_MESSAGE => self.add_message(Message::parse(content)?),
+            reader::OP_METADATA => {
+                let mut fields = Fields::new(content);
+                let name = fields.string()?;
+                for (key, value) in fields.string_map()? {
+                    self.signals
+                        .set_metadata(&format!("{}/{}", name, key), &value);
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Adds the values of a message to the samples. Messages that cannot be decoded are skipped.
+    fn add_message(&mut self, message: Message) {
+        let Some(Some((topic_path, decoder))) = self.channels.get(&message.channel_id) else {
+            return;
+        };
+        let time = match self.options.timestamp {
+            McapTimestamp::Log => message.log_time,
+            McapTimestamp::Publish => message.publish_time,
+        } as f64
+            * 1e-9;
+
+        let signals = &mut self.signals;
+        let ids = &mut self.ids;
+        let values = &mut self.values;
+        let selection = &self.selection;
+        values.clear();
+        let result = decoder.decode(message.data, &mut |field, value| {
+            let path = format!("{}{}", topic_path, field);
+            let id = *ids.entry(path).or_insert_with_key(|path| {
+                let path = sanitize(path);
+                if !is_selected(&path, selection) {
+                    return None;
+                }
+                // Paths conflicting with another signal are skipped
+                signals.add_signal(&path).ok().map(|(id, _)| id)
+            });
+            if let Some(id) = id {
+                values.push((id, value));
+            }
+        });
+
+        match result {
+            Ok(()) => {
+                for &(id, value) in values.iter() {
+                    self.samples
+                        .entry(id)
+                        .or_default()
+                        .push(PlotSignalSample { time, value });
+                }
+            }
+            Err(error) => {
+                self.skipped
+                    .entry(topic_path.clone())
+                    .or_insert(SkippedMessages {
+                        count: 0,
+                        time,
+                        error,
+                    })
+                    .count += 1;
+            }
+        }
+    }
+
+    fn finish(mut self) -> PlotSignals {
+        for (topic, skipped) in self.skipped {
+            self.signals.add_event(
+                skipped.time,
+                &format!(
+                    "{} messages of '{}' could not be decoded: {}",
+                    skipped.count, topic, skipped.error
+                ),
+            );
+        }
+        // Chunks may overlap in time, so messages are not necessarily in order
+        for (id, samples) in self.samples {
+            self.signals.push_samples(id, samples);
+        }
+        self.signals
+    }
+}
+
+impl PlotSignals {
+    /// Loads the numeric fields of the messages of an MCAP file as signals, with paths like
+    /// `/topic/field/subfield`. Messages encoded as JSON, protobuf or ROS2 CDR are decoded
+    /// using the schemas in the file. Messages of channels that are not selected are not decoded.
+    /// Messages that cannot be decoded are skipped, and reported by an event for each topic.
+    pub fn from_mcap(path: impl AsRef<Path>, options: &McapOptions) -> Result<Self, McapError> {
+        let mut loader = McapLoader {
+            options,
+            selection: selection::normalize(&options.selection),
+            schemas: HashMap::new(),
+            channels: HashMap::new(),
+            signals: PlotSignals::default(),
+            ids: HashMap::new(),
+            samples: HashMap::new(),
+            values: vec![],
+            skipped: BTreeMap::new(),
+        };
+
+        reader::for_each_record(BufReader::new(File::open(path)?), |opcode, content| {
+            loader.add_record(opcode, content)
+        })?;
+
+        Ok(loader.finish())
+    }
+
+    /// Lists the channels of an MCAP file, to select which ones to load.
+    /// Only the summary at the end of the file is read, if present.
+    pub fn mcap_channels(path: impl AsRef<Path>) -> Result<Vec<McapChannel>, McapError> {
+        let path = path.as_ref();
+        let summary = reader::read_summary(BufReader::new(File::open(path)?))?;
+
+        let (schemas, channels, counts) = match summary {
+            Some(summary) => (summary.schemas, summary.channels, summary.message_counts),
+            None => {
+                let mut schemas = HashMap::new();
+                let mut channels = HashMap::new();
+                let mut counts = HashMap::new();
+                reader::for_each_record(BufReader::new(File::open(path)?), |opcode, content| {
+                    match opcode {
+                        reader::OP_SCHEMA => {
+                            let (id, schema) = Schema::parse(content)?;
+                            schemas.insert(id, schema);
+                        }
+                        reader::OP_CHANNEL => {
+                            let (id, channel) = Channel::parse(content)?;
+                            channels.insert(id, channel);
+                        }
+                        reader::OP_MESSAGE => {
+                            *counts
+                                .entry(Message::parse(content)?.channel_id)
+                                .or_default() += 1;
+                        }
+                        _ => {}
+                    }
+                    Ok(())
+                })?;
+                (schemas, channels, counts)
+            }
+        };
+
+        let mut list: Vec<_> = channels
+            .into_iter()
+            .map(|(id, channel)| {
+                let schema = schemas.get(&channel.schema_id);
+                McapChannel {
+                    path: sanitize(&channel.topic),
+                    supported: is_supported(&channel.message_encoding, schema),
+                    schema_name: schema.map(|s| s.name.clone()).unwrap_or_default(),
+                    schema_encoding: schema.map(|s| s.encoding.clone()).unwrap_or_default(),
+                    message_count: counts.get(&id).copied().unwrap_or_default(),
+                    message_encoding: channel.message_encoding,
+                    topic: channel.topic,
+                }
+            })
+            .collect();
+        list.sort_by(|a, b| a.topic.cmp(&b.topic));
+        Ok(list)
+    }
+}
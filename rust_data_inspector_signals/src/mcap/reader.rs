@@ -0,0 +1,425 @@
+//! Reading of MCAP records, see https://mcap.dev/spec
+
+use std::collections::HashMap;
+use std::io::{self, Read, Seek, SeekFrom};
+
+use super::McapError;
+
+pub(super) const MAGIC: &[u8; 8] = b"\x89MCAP0\r\n";
+
+pub(super) const OP_FOOTER: u8 = 0x02;
+pub(super) const OP_SCHEMA: u8 = 0x03;
+pub(super) const OP_CHANNEL: u8 = 0x04;
+pub(super) const OP_MESSAGE: u8 = 0x05;
+pub(super) const OP_CHUNK: u8 = 0x06;
+pub(super) const OP_STATISTICS: u8 = 0x0B;
+pub(super) const OP_METADATA: u8 = 0x0C;
+pub(super) const OP_DATA_END: u8 = 0x0F;
+
+/// Opcode, length and content of the footer record
+const FOOTER_RECORD_LEN: i64 = 1 + 8 + 20;
+/// The uncompressed size of a chunk comes from the file, so at most this many times the size of
+/// its compressed data is preallocated when decompressing it
+const MAX_PREALLOCATED_RATIO: usize = 16;
+
+/// Little endian reader of the fields of a record
+pub(super) struct Fields<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Fields<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Fields { data }
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], McapError> {
+        if n > self.data.len() {
+            return Err(McapError::Malformed("record is truncated".to_string()));
+        }
+        let (head, tail) = self.data.split_at(n);
+        self.data = tail;
+        Ok(head)
+    }
+
+    pub fn u16(&mut self) -> Result<u16, McapError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    pub fn u32(&mut self) -> Result<u32, McapError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, McapError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    pub fn string(&mut self) -> Result<String, McapError> {
+        let len = self.u32()? as usize;
+        String::from_utf8(self.take(len)?.to_vec())
+            .map_err(|_| McapError::Malformed("string is not valid UTF-8".to_string()))
+    }
+
+    /// Byte array prefixed by a u32 length
+    pub fn bytes(&mut self) -> Result<&'a [u8], McapError> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+
+    /// Byte array prefixed by a u64 length
+    pub fn long_bytes(&mut self) -> Result<&'a [u8], McapError> {
+        let len = self.u64()? as usize;
+        self.take(len)
+    }
+
+    pub fn string_map(&mut self) -> Result<Vec<(String, String)>, McapError> {
+        let mut map = Fields::new(self.bytes()?);
+        let mut pairs = vec![];
+        while !map.data.is_empty() {
+            pairs.push((map.string()?, map.string()?));
+        }
+        Ok(pairs)
+    }
+
+    pub fn rest(self) -> &'a [u8] {
+        self.data
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct Schema {
+    pub name: String,
+    pub encoding: String,
+    pub data: Vec<u8>,
+}
+
+impl Schema {
+    pub fn parse(content: &[u8]) -> Result<(u16, Self), McapError> {
+        let mut f = Fields::new(content);
+        let id = f.u16()?;
+        let schema = Schema {
+            name: f.string()?,
+            encoding: f.string()?,
+            data: f.bytes()?.to_vec(),
+        };
+        Ok((id, schema))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct Channel {
+    pub schema_id: u16,
+    pub topic: String,
+    pub message_encoding: String,
+}
+
+impl Channel {
+    pub fn parse(content: &[u8]) -> Result<(u16, Self), McapError> {
+        let mut f = Fields::new(content);
+        let id = f.u16()?;
+        let channel = Channel {
+            schema_id: f.u16()?,
+            topic: f.string()?,
+            message_encoding: f.string()?,
+        };
+        Ok((id, channel))
+    }
+}
+
+pub(super) struct Message<'a> {
+    pub channel_id: u16,
+    pub log_time: u64,
+    pub publish_time: u64,
+    pub data: &'a [u8],
+}
+
+impl<'a> Message<'a> {
+    pub fn parse(content: &'a [u8]) -> Result<Self, McapError> {
+        let mut f = Fields::new(content);
+        let channel_id = f.u16()?;
+        let _sequence = f.u32()?;
+        Ok(Message {
+            channel_id,
+            log_time: f.u64()?,
+            publish_time: f.u64()?,
+            data: f.rest(),
+        })
+    }
+}
+
+/// Reads the next record, returning its opcode and content, or `None` at the end of the file
+fn read_record(r: &mut impl Read, content: &mut Vec<u8>) -> Result<Option<u8>, McapError> {
+    let mut header = [0; 9];
+    match r.read_exact(&mut header[..1]) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    r.read_exact(&mut header[1..])?;
+    let len = u64::from_le_bytes(header[1..].try_into().unwrap());
+
+    content.clear();
+    // Not preallocated, as the length of a corrupted record may be arbitrarily large
+    r.take(len).read_to_end(content)?;
+    if content.len() as u64 != len {
+        return Err(McapError::Malformed("record is truncated".to_string()));
+    }
+    Ok(Some(header[0]))
+}
+
+/// Decompresses the records of a chunk, which must not be larger than its uncompressed `size`
+fn decompress(compression: &str, data: &[u8], size: u64) -> Result<Vec<u8>, McapError> {
+    let capacity = data.len().saturating_mul(MAX_PREALLOCATED_RATIO);
+    let mut out = Vec::with_capacity(size.min(capacity as u64) as usize);
+    // One more byte than expected is read, to detect chunks larger than their size
+    let limit = size.saturating_add(1);
+    match compression {
+        "" => out.extend_from_slice(data),
+        "zstd" => {
+            zstd::stream::read::Decoder::new(data)?
+                .take(limit)
+                .read_to_end(&mut out)?;
+        }
+        "lz4" => {
+            lz4_flex::frame::FrameDecoder::new(data)
+                .take(limit)
+                .read_to_end(&mut out)?;
+        }
+        other => return Err(McapError::Compression(other.to_string())),
+    }
+    if out.len() as u64 > size {
+        return Err(McapError::Malformed(
+            "chunk is larger than its uncompressed size".to_string(),
+        ));
+    }
+    Ok(out)
+}
+
+/// Calls `f` with the opcode and content of each record of the data section of an MCAP file,
+/// including the ones in chunks, which are decompressed one at a time.
+pub(super) fn for_each_record(
+    mut r: impl Read,
+    mut f: impl FnMut(u8, &[u8]) -> Result<(), McapError>,
+) -> Result<(), McapError> {
+    let mut magic = [0; 8];
+    r.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(McapError::Malformed("not an MCAP file".to_string()));
+    }
+
+    let mut content = vec![];
+    while let Some(opcode) = read_record(&mut r, &mut content)? {
+        match opcode {
+            OP_CHUNK => {
+                let mut fields = Fields::new(&content);
+                let _start_time = fields.u64()?;
+                let _end_time = fields.u64()?;
+                let size = fields.u64()?;
+                let _crc = fields.u32()?;
+                let compression = fields.string()?;
+                let records = decompress(&compression, fields.long_bytes()?, size)?;
+
+                let mut records = records.as_slice();
+                let mut inner = vec![];
+                while let Some(opcode) = read_record(&mut records, &mut inner)? {
+                    f(opcode, &inner)?;
+                }
+            }
+            OP_DATA_END | OP_FOOTER => break,
+            _ => f(opcode, &content)?,
+        }
+    }
+
+    Ok(())
+}
+
+/// Records of the summary section relevant to listing channels
+pub(super) struct Summary {
+    pub schemas: HashMap<u16, Schema>,
+    pub channels: HashMap<u16, Channel>,
+    /// Message count of each channel, from the statistics record
+    pub message_counts: HashMap<u16, u64>,
+}
+
+/// Reads the summary section at the end of the file, if it has one with statistics
+pub(super) fn read_summary(mut r: impl Read + Seek) -> Result<Option<Summary>, McapError> {
+    r.seek(SeekFrom::End(-(FOOTER_RECORD_LEN + MAGIC.len() as i64)))?;
+    let mut footer = [0; FOOTER_RECORD_LEN as usize];
+    r.read_exact(&mut footer)?;
+    if footer[0] != OP_FOOTER {
+        return Err(McapError::Malformed("footer not found".to_string()));
+    }
+    let summary_start = Fields::new(&footer[9..]).u64()?;
+    if summary_start == 0 {
+        return Ok(None);
+    }
+
+    let mut summary = Summary {
+        schemas: HashMap::new(),
+        channels: HashMap::new(),
+        message_counts: HashMap::new(),
+    };
+    let mut statistics = false;
+
+    r.seek(SeekFrom::Start(summary_start))?;
+    let mut content = vec![];
+    while let Some(opcode) = read_record(&mut r, &mut content)? {
+        match opcode {
+            OP_SCHEMA => {
+                let (id, schema) = Schema::parse(&content)?;
+                summary.schemas.insert(id, schema);
+            }
+            OP_CHANNEL => {
+                let (id, channel) = Channel::parse(&content)?;
+                summary.channels.insert(id, channel);
+            }
+            OP_STATISTICS => {
+                let mut f = Fields::new(&content);
+                let _message_count = f.u64()?;
+                let _schema_count = f.u16()?;
+                let _channel_count = f.u32()?;
+                let _attachment_count = f.u32()?;
+                let _metadata_count = f.u32()?;
+                let _chunk_count = f.u32()?;
+                let _start_time = f.u64()?;
+                let _end_time = f.u64()?;
+                let mut counts = Fields::new(f.bytes()?);
+                while !counts.data.is_empty() {
+                    summary.message_counts.insert(counts.u16()?, counts.u64()?);
+                }
+                statistics = true;
+            }
+            OP_FOOTER => break,
+            _ => {}
+        }
+    }
+
+    Ok(statistics.then_some(summary))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(opcode: u8, content: &[u8]) -> Vec<u8> {
+        let mut data = vec![opcode];
+        data.extend_from_slice(&(content.len() as u64).to_le_bytes());
+        data.extend_from_slice(content);
+        data
+    }
+
+    fn string(s: &str) -> Vec<u8> {
+        let mut data = (s.len() as u32).to_le_bytes().to_vec();
+        data.extend_from_slice(s.as_bytes());
+        data
+    }
+
+    fn chunk(compression: &str, records: &[u8], size: u64) -> Vec<u8> {
+        let compressed = match compression {
+            "zstd" => zstd::encode_all(records, 0).unwrap(),
+            _ => records.to_vec(),
+        };
+        let mut content = vec![];
+        content.extend_from_slice(&0u64.to_le_bytes());
+        content.extend_from_slice(&0u64.to_le_bytes());
+        content.extend_from_slice(&size.to_le_bytes());
+        content.extend_from_slice(&0u32.to_le_bytes());
+        content.extend(string(compression));
+        content.extend_from_slice(&(compressed.len() as u64).to_le_bytes());
+        content.extend(compressed);
+        record(OP_CHUNK, &content)
+    }
+
+    fn records(file: &[u8]) -> Result<Vec<(u8, Vec<u8>)>, McapError> {
+        let mut records = vec![];
+        for_each_record(file, |opcode, content| {
+            records.push((opcode, content.to_vec()));
+            Ok(())
+        })?;
+        Ok(records)
+    }
+
+    #[test]
+    fn fields() {
+        let mut data = vec![];
+        data.extend_from_slice(&1u16.to_le_bytes());
+        data.extend_from_slice(&2u32.to_le_bytes());
+        data.extend_from_slice(&3u64.to_le_bytes());
+        data.extend(string("topic"));
+        let map = [string("key"), string("value")].concat();
+        data.extend_from_slice(&(map.len() as u32).to_le_bytes());
+        data.extend(map);
+        data.extend_from_slice(b"rest");
+
+        let mut f = Fields::new(&data);
+        assert_eq!(f.u16().unwrap(), 1);
+        assert_eq!(f.u32().unwrap(), 2);
+        assert_eq!(f.u64().unwrap(), 3);
+        assert_eq!(f.string().unwrap(), "topic");
+        assert_eq!(
+            f.string_map().unwrap(),
+            vec![("key".to_string(), "value".to_string())]
+        );
+        assert_eq!(f.rest(), b"rest");
+    }
+
+    #[test]
+    fn fields_truncated() {
+        assert!(Fields::new(&[1]).u16().is_err());
+        // String longer than the remaining data
+        let mut data = 10u32.to_le_bytes().to_vec();
+        data.extend_from_slice(b"abc");
+        assert!(matches!(
+            Fields::new(&data).string(),
+            Err(McapError::Malformed(_))
+        ));
+        assert!(Fields::new(&u64::MAX.to_le_bytes()).long_bytes().is_err());
+    }
+
+    #[test]
+    fn records_in_chunks() {
+        let inner = [record(OP_SCHEMA, b"schema"), record(OP_MESSAGE, b"message")].concat();
+        for compression in ["", "zstd"] {
+            let file = [
+                MAGIC.to_vec(),
+                record(OP_CHANNEL, b"channel"),
+                chunk(compression, &inner, inner.len() as u64),
+                record(OP_DATA_END, &[]),
+                record(OP_MESSAGE, b"summary"),
+            ]
+            .concat();
+
+            assert_eq!(
+                records(&file).unwrap(),
+                vec![
+                    (OP_CHANNEL, b"channel".to_vec()),
+                    (OP_SCHEMA, b"schema".to_vec()),
+                    (OP_MESSAGE, b"message".to_vec()),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn chunk_larger_than_its_size() {
+        let inner = record(OP_MESSAGE, &[0; 100]);
+        for compression in ["", "zstd"] {
+            let file = [MAGIC.to_vec(), chunk(compression, &inner, 10)].concat();
+            assert!(matches!(records(&file), Err(McapError::Malformed(_))));
+        }
+    }
+
+    #[test]
+    fn malformed_files() {
+        assert!(matches!(records(b"not mcap"), Err(McapError::Malformed(_))));
+
+        // Record longer than the file
+        let mut file = MAGIC.to_vec();
+        file.push(OP_MESSAGE);
+        file.extend_from_slice(&u64::MAX.to_le_bytes());
+        file.extend_from_slice(b"data");
+        assert!(matches!(records(&file), Err(McapError::Malformed(_))));
+
+        let file = [MAGIC.to_vec(), chunk("brotli", b"", 0)].concat();
+        assert!(matches!(records(&file), Err(McapError::Compression(_))));
+    }
+}
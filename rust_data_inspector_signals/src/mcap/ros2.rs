@@ -0,0 +1,514 @@
+//! Decoding of ROS2 messages serialized with CDR, from their `.msg` definition
+
+use std::collections::HashMap;
+
+use super::{McapError, MAX_ARRAY_LEN};
+
+#[derive(Debug, Clone, Copy)]
+enum Primitive {
+    Bool,
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    I64,
+    U64,
+    F32,
+    F64,
+}
+
+impl Primitive {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "bool" => Primitive::Bool,
+            "int8" => Primitive::I8,
+            "uint8" | "byte" | "char" => Primitive::U8,
+            "int16" => Primitive::I16,
+            "uint16" => Primitive::U16,
+            "int32" => Primitive::I32,
+            "uint32" => Primitive::U32,
+            "int64" => Primitive::I64,
+            "uint64" => Primitive::U64,
+            "float32" => Primitive::F32,
+            "float64" => Primitive::F64,
+            _ => return None,
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Primitive::Bool | Primitive::I8 | Primitive::U8 => 1,
+            Primitive::I16 | Primitive::U16 => 2,
+            Primitive::I32 | Primitive::U32 | Primitive::F32 => 4,
+            Primitive::I64 | Primitive::U64 | Primitive::F64 => 8,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum FieldType {
+    Primitive(Primitive),
+    String,
+    WString,
+    /// Index of the message definition
+    Message(usize),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Array {
+    None,
+    Fixed(usize),
+    /// Unbounded or bounded sequence, prefixed by its length
+    Sequence,
+}
+
+#[derive(Debug)]
+struct Field {
+    name: String,
+    ty: FieldType,
+    array: Array,
+}
+
+/// Message definitions of a schema, with the root message first
+#[derive(Debug)]
+pub(super) struct MessageDefinitions {
+    messages: Vec<Vec<Field>>,
+}
+
+/// Removes the `msg` part of `pkg/msg/Type`
+fn normalize_type_name(name: &str) -> String {
+    match name.split('/').collect::<Vec<_>>()[..] {
+        [package, "msg", name] => format!("{}/{}", package, name),
+        _ => name.to_string(),
+    }
+}
+
+/// Builtin types that may not be included in the schema
+fn builtin_definition(name: &str) -> Option<&'static str> {
+    match name {
+        "builtin_interfaces/Time" => Some("int32 sec\nuint32 nanosec"),
+        "builtin_interfaces/Duration" => Some("int32 sec\nuint32 nanosec"),
+        _ => None,
+    }
+}
+
+struct Parser<'a> {
+    sources: HashMap<String, &'a str>,
+    indices: HashMap<String, usize>,
+    messages: Vec<Vec<Field>>,
+}
+
+impl<'a> Parser<'a> {
+    fn resolve(&mut self, name: &str) -> Result<usize, McapError> {
+        if let Some(&index) = self.indices.get(name) {
+            return Ok(index);
+        }
+        let source = self
+            .sources
+            .get(name)
+            .copied()
+            .or_else(|| builtin_definition(name))
+            .ok_or_else(|| McapError::Schema(format!("definition of '{}' not found", name)))?;
+
+        let index = self.messages.len();
+        self.indices.insert(name.to_string(), index);
+        self.messages.push(vec![]);
+
+        let package = name.split_once('/').map(|(p, _)| p).unwrap_or_default();
+        let fields = self.parse_fields(source, package)?;
+        self.messages[index] = fields;
+        Ok(index)
+    }
+
+    fn parse_fields(&mut self, source: &str, package: &str) -> Result<Vec<Field>, McapError> {
+        let mut fields = vec![];
+        for line in source.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let mut tokens = line.splitn(2, char::is_whitespace);
+            let (Some(ty), Some(rest)) = (tokens.next(), tokens.next()) else {
+                continue;
+            };
+            // Constants, like `uint8 MODE_AUTO=1`
+            if rest.contains('=') {
+                continue;
+            }
+            let name = rest
+                .split_whitespace()
+                .next()
+                .unwrap_or_default()
+                .to_string();
+
+            let (ty, array) = match ty.split_once('[') {
+                Some((ty, size)) => {
+                    let size = size.trim_end_matches(']');
+                    let array = if size.is_empty() || size.starts_with("<=") {
+                        Array::Sequence
+                    } else {
+                        Array::Fixed(size.parse().map_err(|_| {
+                            McapError::Schema(format!("invalid array size of '{}'", name))
+                        })?)
+                    };
+                    (ty, array)
+                }
+                None => (ty, Array::None),
+            };
+            // Bounded strings, like `string<=10`
+            let ty = ty.split("<=").next().unwrap_or_default();
+
+            let ty = if let Some(primitive) = Primitive::parse(ty) {
+                FieldType::Primitive(primitive)
+            } else if ty == "string" {
+                FieldType::String
+            } else if ty == "wstring" {
+                FieldType::WString
+            } else {
+                let full_name = if ty.contains('/') {
+                    normalize_type_name(ty)
+                } else if ty == "Header" {
+                    "std_msgs/Header".to_string()
+                } else {
+                    format!("{}/{}", package, ty)
+                };
+                FieldType::Message(self.resolve(&full_name)?)
+            };
+
+            fields.push(Field { name, ty, array });
+        }
+        Ok(fields)
+    }
+}
+
+impl MessageDefinitions {
+    /// Parses a `ros2msg` schema: the definition of `name`, followed by the definitions of
+    /// the types it depends on, each separated by a line of `=` and starting with `MSG: <type>`
+    pub fn parse(name: &str, schema: &str) -> Result<Self, McapError> {
+        let name = normalize_type_name(name);
+        let mut sections = vec![];
+        let mut section = String::new();
+        for line in schema.lines() {
+            if line.starts_with("===") {
+                sections.push(std::mem::take(&mut section));
+            } else {
+                section.push_str(line);
+                section.push('\n');
+            }
+        }
+        sections.push(section);
+
+        let mut sources = HashMap::new();
+        for (i, section) in sections.iter().enumerate() {
+            if i == 0 {
+                sources.insert(name.clone(), section.as_str());
+            } else if let Some((header, body)) = section.trim_start().split_once('\n') {
+                if let Some(dependency) = header.trim().strip_prefix("MSG:") {
+                    sources.insert(normalize_type_name(dependency.trim()), body);
+                }
+            }
+        }
+
+        let mut parser = Parser {
+            sources,
+            indices: HashMap::new(),
+            messages: vec![],
+        };
+        parser.resolve(&name)?;
+        Ok(MessageDefinitions {
+            messages: parser.messages,
+        })
+    }
+
+    /// Decodes a CDR serialized message, calling `out` with the path of each numeric field,
+    /// relative to the message, and its value
+    pub fn decode(&self, data: &[u8], out: &mut impl FnMut(&str, f64)) -> Result<(), McapError> {
+        if data.len() < 4 {
+            return Err(McapError::Decode("missing CDR header".to_string()));
+        }
+        let (little_endian, max_align) = match data[1] {
+            0x00 => (false, 8),
+            0x01 => (true, 8),
+            // Plain CDR2, which aligns 64 bit values to 4 bytes
+            0x06 => (false, 4),
+            0x07 => (true, 4),
+            kind => {
+                return Err(McapError::Decode(format!(
+                    "unsupported CDR encapsulation kind {:#04x}",
+                    kind
+                )))
+            }
+        };
+
+        let mut reader = CdrReader {
+            data: &data[4..],
+            pos: 0,
+            little_endian,
+            max_align,
+        };
+        let mut path = String::new();
+        self.decode_message(0, &mut reader, &mut path, true, out)
+    }
+
+    fn decode_message(
+        &self,
+        index: usize,
+        r: &mut CdrReader,
+        path: &mut String,
+        emit: bool,
+        out: &mut impl FnMut(&str, f64),
+    ) -> Result<(), McapError> {
+        let fields = &self.messages[index];
+        if fields.is_empty() {
+            // Empty messages are serialized with a placeholder byte
+            r.take(1)?;
+        }
+
+        for field in fields {
+            let len = path.len();
+            path.push('/');
+            path.push_str(&field.name);
+
+            let count = match field.array {
+                Array::None => None,
+                Array::Fixed(n) => Some(n),
+                Array::Sequence => Some(r.u32()? as usize),
+            };
+            match count {
+                None => self.decode_value(field.ty, r, path, emit, out)?,
+                Some(n) => {
+                    let emit = emit && n <= MAX_ARRAY_LEN;
+                    match field.ty {
+                        FieldType::Primitive(p) if !emit => {
+                            r.align(p.size());
+                            r.take(p.size() * n)?;
+                        }
+                        ty => {
+                            for i in 0..n {
+                                let len = path.len();
+                                path.push('/');
+                                path.push_str(&i.to_string());
+                                self.decode_value(ty, r, path, emit, out)?;
+                                path.truncate(len);
+                            }
+                        }
+                    }
+                }
+            }
+
+            path.truncate(len);
+        }
+        Ok(())
+    }
+
+    fn decode_value(
+        &self,
+        ty: FieldType,
+        r: &mut CdrReader,
+        path: &mut String,
+        emit: bool,
+        out: &mut impl FnMut(&str, f64),
+    ) -> Result<(), McapError> {
+        match ty {
+            FieldType::Primitive(p) => {
+                let value = r.primitive(p)?;
+                if emit {
+                    out(path, value);
+                }
+            }
+            FieldType::String => {
+                let len = r.u32()? as usize;
+                r.take(len)?;
+            }
+            FieldType::WString => {
+                return Err(McapError::Decode(
+                    "wstring fields are not supported".to_string(),
+                ))
+            }
+            FieldType::Message(index) => self.decode_message(index, r, path, emit, out)?,
+        }
+        Ok(())
+    }
+}
+
+struct CdrReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    little_endian: bool,
+    max_align: usize,
+}
+
+impl CdrReader<'_> {
+    fn align(&mut self, size: usize) {
+        let align = size.min(self.max_align);
+        self.pos = self.pos.div_ceil(align) * align;
+    }
+
+    fn take(&mut self, n: usize) -> Result<&[u8], McapError> {
+        let end = self
+            .pos
+            .checked_add(n)
+            .filter(|&end| end <= self.data.len())
+            .ok_or_else(|| McapError::Decode("message is truncated".to_string()))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn bytes<const N: usize>(&mut self) -> Result<[u8; N], McapError> {
+        self.align(N);
+        let mut bytes: [u8; N] = self.take(N)?.try_into().unwrap();
+        if !self.little_endian {
+            bytes.reverse();
+        }
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, McapError> {
+        Ok(u32::from_le_bytes(self.bytes()?))
+    }
+
+    fn primitive(&mut self, p: Primitive) -> Result<f64, McapError> {
+        Ok(match p {
+            Primitive::Bool => (self.take(1)?[0] != 0) as u8 as f64,
+            Primitive::I8 => self.take(1)?[0] as i8 as f64,
+            Primitive::U8 => self.take(1)?[0] as f64,
+            Primitive::I16 => i16::from_le_bytes(self.bytes()?) as f64,
+            Primitive::U16 => u16::from_le_bytes(self.bytes()?) as f64,
+            Primitive::I32 => i32::from_le_bytes(self.bytes()?) as f64,
+            Primitive::U32 => u32::from_le_bytes(self.bytes()?) as f64,
+            Primitive::I64 => i64::from_le_bytes(self.bytes()?) as f64,
+            Primitive::U64 => u64::from_le_bytes(self.bytes()?) as f64,
+            Primitive::F32 => f32::from_le_bytes(self.bytes()?) as f64,
+            Primitive::F64 => f64::from_le_bytes(self.bytes()?),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMA: &str = "\
+std_msgs/Header header
+float64 x  # comment
+int16[2] pair
+uint8[] data
+uint8 MODE_AUTO=1
+================================================================================
+MSG: std_msgs/Header
+builtin_interfaces/Time stamp
+string frame_id
+";
+
+    /// Writer of CDR messages
+    struct Cdr {
+        data: Vec<u8>,
+        little_endian: bool,
+    }
+
+    impl Cdr {
+        fn new(little_endian: bool) -> Self {
+            Cdr {
+                data: vec![0, little_endian as u8, 0, 0],
+                little_endian,
+            }
+        }
+
+        fn put<const N: usize>(&mut self, mut bytes: [u8; N]) -> &mut Self {
+            while !(self.data.len() - 4).is_multiple_of(N) {
+                self.data.push(0);
+            }
+            if !self.little_endian {
+                bytes.reverse();
+            }
+            self.data.extend_from_slice(&bytes);
+            self
+        }
+
+        fn string(&mut self, s: &str) -> &mut Self {
+            self.put((s.len() as u32 + 1).to_le_bytes());
+            self.data.extend_from_slice(s.as_bytes());
+            self.data.push(0);
+            self
+        }
+    }
+
+    fn message(little_endian: bool, data: &[u8]) -> Vec<u8> {
+        let mut cdr = Cdr::new(little_endian);
+        cdr.put(5i32.to_le_bytes())
+            .put(6u32.to_le_bytes())
+            .string("map")
+            .put(1.5f64.to_le_bytes())
+            .put((-1i16).to_le_bytes())
+            .put(2i16.to_le_bytes())
+            .put((data.len() as u32).to_le_bytes());
+        cdr.data.extend_from_slice(data);
+        cdr.data
+    }
+
+    fn decode(
+        definitions: &MessageDefinitions,
+        data: &[u8],
+    ) -> Result<Vec<(String, f64)>, McapError> {
+        let mut values = vec![];
+        definitions.decode(data, &mut |path, value| {
+            values.push((path.to_string(), value))
+        })?;
+        Ok(values)
+    }
+
+    #[test]
+    fn decode_message() {
+        let definitions = MessageDefinitions::parse("pkg/msg/Test", SCHEMA).unwrap();
+        let expected: Vec<_> = [
+            ("/header/stamp/sec", 5.0),
+            ("/header/stamp/nanosec", 6.0),
+            ("/x", 1.5),
+            ("/pair/0", -1.0),
+            ("/pair/1", 2.0),
+            ("/data/0", 7.0),
+            ("/data/1", 8.0),
+        ]
+        .into_iter()
+        .map(|(path, value)| (path.to_string(), value))
+        .collect();
+
+        for little_endian in [true, false] {
+            let values = decode(&definitions, &message(little_endian, &[7, 8])).unwrap();
+            assert_eq!(values, expected);
+        }
+    }
+
+    #[test]
+    fn long_arrays_are_skipped() {
+        let definitions = MessageDefinitions::parse("pkg/Test", SCHEMA).unwrap();
+        let values = decode(&definitions, &message(true, &[0; MAX_ARRAY_LEN + 1])).unwrap();
+        assert!(values.iter().all(|(path, _)| !path.starts_with("/data")));
+        assert_eq!(values.len(), 5);
+    }
+
+    #[test]
+    fn truncated_message() {
+        let definitions = MessageDefinitions::parse("pkg/Test", SCHEMA).unwrap();
+        let data = message(true, &[7, 8]);
+        for len in [2, 10, data.len() - 1] {
+            assert!(matches!(
+                decode(&definitions, &data[..len]),
+                Err(McapError::Decode(_))
+            ));
+        }
+        // Sequence longer than the message
+        let mut data = message(true, &[]);
+        let len = data.len();
+        data[len - 4..].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(decode(&definitions, &data).is_err());
+    }
+
+    #[test]
+    fn missing_definition() {
+        assert!(matches!(
+            MessageDefinitions::parse("pkg/Test", "pkg/Other other\n"),
+            Err(McapError::Schema(_))
+        ));
+    }
+}
@@ -0,0 +1,671 @@
+//! Recording of signals to file.
+//!
+//! A recording is an append-only sequence of records following a short header.
+//! Each record is made of a one byte kind, the length of the payload as u32, the payload and the
+//! CRC32 of all the previous fields. All values are little endian.
+//!
+//! Samples are written in chunks, each one followed by an index record with the time span of
+//! the chunk, so that a time range can be read without decoding the whole file.
+//! A file truncated by a crash can still be read up to its last complete record.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use thiserror::Error;
+
+use crate::{PlotSignalError, PlotSignalID, PlotSignalSample};
+
+const MAGIC: &[u8; 6] = b"RDIREC";
+const VERSION: u16 = 1;
+const HEADER_LEN: u64 = MAGIC.len() as u64 + 2;
+/// Kind, length and CRC
+const RECORD_OVERHEAD: u64 = 1 + 4 + 4;
+/// Data is synced to disk at least this often
+const SYNC_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+enum RecordKind {
+    /// Signal id (u32), name
+    Signal = 1,
+    /// Signal id (u32), number of samples (u32), samples as (time f64, value f64)
+    Samples = 2,
+    /// Signal id (u32), unit
+    Unit = 3,
+    /// Key, value
+    Metadata = 4,
+    /// Offset of the first record of the chunk (u64), first and last time in the chunk (f64)
+    Index = 5,
+}
+
+impl RecordKind {
+    fn from_u8(v: u8) -> Option<Self> {
+        match v {
+            1 => Some(RecordKind::Signal),
+            2 => Some(RecordKind::Samples),
+            3 => Some(RecordKind::Unit),
+            4 => Some(RecordKind::Metadata),
+            5 => Some(RecordKind::Index),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum RecordingError {
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Not a recording file")]
+    BadHeader,
+    #[error("Unsupported recording version: {0}")]
+    UnsupportedVersion(u16),
+    #[error("Invalid signal in recording: {0}")]
+    Signal(#[from] PlotSignalError),
+    #[error("Record of {0} bytes extends past the end of the data")]
+    RecordTooLong(u64),
+}
+
+/// Writes signals to a recording file as they are received
+#[derive(Debug)]
+pub struct Recorder {
+    path: PathBuf,
+    file: BufWriter<File>,
+    /// Ids of the signals in the file
+    ids: HashMap<PlotSignalID, u32>,
+    bytes_written: u64,
+
+    /// Offset and time span of the chunk being written
+    chunk: Option<(u64, f64, f64)>,
+    last_sync: Instant,
+
+    /// First error encountered. No more data is written after an error.
+    error: Option<String>,
+}
+
+impl Recorder {
+    /// Creates a new recording, overwriting `path` if it exists
+    pub fn create(path: impl AsRef<Path>) -> Result<Self, RecordingError> {
+        let mut file = BufWriter::new(File::create(path.as_ref())?);
+        file.write_all(MAGIC)?;
+        file.write_all(&VERSION.to_le_bytes())?;
+        file.flush()?;
+
+        Ok(Recorder {
+            path: path.as_ref().to_path_buf(),
+            file,
+            ids: HashMap::new(),
+            bytes_written: HEADER_LEN,
+            chunk: None,
+            last_sync: Instant::now(),
+            error: None,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    /// Error that stopped the recording, if any
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    pub(crate) fn add_signal(&mut self, id: PlotSignalID, name: &str) {
+        let file_id = self.ids.len() as u32;
+        self.ids.insert(id, file_id);
+
+        let mut payload = vec![];
+        put_u32(&mut payload, file_id);
+        put_str(&mut payload, name);
+        self.write_record(RecordKind::Signal, &payload);
+    }
+
+    pub(crate) fn set_unit(&mut self, id: PlotSignalID, unit: &str) {
+        let Some(&file_id) = self.ids.get(&id) else {
+            return;
+        };
+
+        let mut payload = vec![];
+        put_u32(&mut payload, file_id);
+        put_str(&mut payload, unit);
+        self.write_record(RecordKind::Unit, &payload);
+    }
+
+    pub(crate) fn set_metadata(&mut self, key: &str, value: &str) {
+        let mut payload = vec![];
+        put_str(&mut payload, key);
+        put_str(&mut payload, value);
+        self.write_record(RecordKind::Metadata, &payload);
+    }
+
+    /// Writes samples to the current chunk. Call [`Recorder::end_chunk`] to complete it.
+    pub(crate) fn write_samples(&mut self, id: PlotSignalID, samples: &[PlotSignalSample]) {
+        let (Some(&file_id), Some(first), Some(last)) =
+            (self.ids.get(&id), samples.first(), samples.last())
+        else {
+            return;
+        };
+
+        self.chunk = Some(match self.chunk {
+            Some((offset, start, end)) => (offset, start.min(first.time), end.max(last.time)),
+            None => (self.bytes_written, first.time, last.time),
+        });
+
+        let mut payload = Vec::with_capacity(8 + samples.len() * 16);
+        put_u32(&mut payload, file_id);
+        put_u32(&mut payload, samples.len() as u32);
+        for sample in samples {
+            put_f64(&mut payload, sample.time);
+            put_f64(&mut payload, sample.value);
+        }
+        self.write_record(RecordKind::Samples, &payload);
+    }
+
+    /// Indexes the samples written since the last chunk and flushes them to disk
+    pub(crate) fn end_chunk(&mut self) {
+        if let Some((offset, start, end)) = self.chunk.take() {
+            let mut payload = vec![];
+            put_u64(&mut payload, offset);
+            put_f64(&mut payload, start);
+            put_f64(&mut payload, end);
+            self.write_record(RecordKind::Index, &payload);
+        }
+
+        let res = self.file.flush().and_then(|_| {
+            if self.last_sync.elapsed() >= SYNC_INTERVAL {
+                self.last_sync = Instant::now();
+                self.file.get_ref().sync_data()
+            } else {
+                Ok(())
+            }
+        });
+        self.check(res);
+    }
+
+    fn write_record(&mut self, kind: RecordKind, payload: &[u8]) {
+        if self.error.is_some() {
+            return;
+        }
+
+        let len = (payload.len() as u32).to_le_bytes();
+        let mut crc = crc32fast::Hasher::new();
+        crc.update(&[kind as u8]);
+        crc.update(&len);
+        crc.update(payload);
+
+        let res = self
+            .file
+            .write_all(&[kind as u8])
+            .and_then(|_| self.file.write_all(&len))
+            .and_then(|_| self.file.write_all(payload))
+            .and_then(|_| self.file.write_all(&crc.finalize().to_le_bytes()));
+
+        if res.is_ok() {
+            self.bytes_written += RECORD_OVERHEAD + payload.len() as u64;
+        }
+        self.check(res);
+    }
+
+    fn check(&mut self, res: io::Result<()>) {
+        if let Err(e) = res {
+            self.error.get_or_insert(e.to_string());
+        }
+    }
+}
+
+/// A signal stored in a recording
+#[derive(Debug, Clone)]
+pub struct RecordedSignal {
+    pub name: String,
+    pub unit: Option<String>,
+}
+
+/// Span of the samples written in a chunk
+#[derive(Debug, Clone, Copy)]
+pub struct RecordingIndexEntry {
+    /// Offset of the first record of the chunk
+    pub start_offset: u64,
+    /// Offset after the index record of the chunk
+    pub end_offset: u64,
+    pub start_time: f64,
+    pub end_time: f64,
+}
+
+/// Reads a recording file, validating its records.
+/// Samples are only decoded on request, using the index to read only the needed chunks.
+#[derive(Debug)]
+pub struct RecordingReader {
+    file: BufReader<File>,
+
+    signals: Vec<RecordedSignal>,
+    metadata: BTreeMap<String, String>,
+    index: Vec<RecordingIndexEntry>,
+    /// Time span of the samples written after the last index record
+    unindexed_span: Option<[f64; 2]>,
+
+    /// Offset after the last valid record
+    end_offset: u64,
+    truncated: bool,
+}
+
+impl RecordingReader {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, RecordingError> {
+        let mut file = BufReader::new(File::open(path)?);
+        let file_len = file.get_ref().metadata()?.len();
+
+        let mut header = [0u8; HEADER_LEN as usize];
+        file.read_exact(&mut header)
+            .map_err(|_| RecordingError::BadHeader)?;
+        if &header[..MAGIC.len()] != MAGIC {
+            return Err(RecordingError::BadHeader);
+        }
+        let version = u16::from_le_bytes([header[6], header[7]]);
+        if version != VERSION {
+            return Err(RecordingError::UnsupportedVersion(version));
+        }
+
+        let mut reader = RecordingReader {
+            file,
+            signals: vec![],
+            metadata: BTreeMap::new(),
+            index: vec![],
+            unindexed_span: None,
+            end_offset: HEADER_LEN,
+            truncated: false,
+        };
+
+        loop {
+            let offset = reader.end_offset;
+            match reader.read_record(file_len.saturating_sub(offset)) {
+                Ok(Some((kind, payload))) => {
+                    reader.end_offset += RECORD_OVERHEAD + payload.len() as u64;
+                    if !reader.parse_record(kind, &payload, offset) {
+                        reader.truncated = true;
+                        break;
+                    }
+                }
+                Ok(None) => break,
+                // Record cut by a crash
+                Err(RecordingError::RecordTooLong(_)) => {
+                    reader.truncated = true;
+                    break;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(reader)
+    }
+
+    pub fn signals(&self) -> &[RecordedSignal] {
+        &self.signals
+    }
+
+    pub fn metadata(&self) -> &BTreeMap<String, String> {
+        &self.metadata
+    }
+
+    pub fn index(&self) -> &[RecordingIndexEntry] {
+        &self.index
+    }
+
+    /// True if the file ends with an incomplete or corrupted record, as after a crash
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Time span of the recorded samples
+    pub fn time_span(&self) -> Option<[f64; 2]> {
+        self.index
+            .iter()
+            .map(|e| [e.start_time, e.end_time])
+            .chain(self.unindexed_span)
+            .reduce(|a, b| [a[0].min(b[0]), a[1].max(b[1])])
+    }
+
+    /// Reads all the samples, for each signal in the order of [`RecordingReader::signals`]
+    pub fn read_all(&mut self) -> Result<Vec<Vec<PlotSignalSample>>, RecordingError> {
+        self.read_range(f64::NEG_INFINITY, f64::INFINITY)
+    }
+
+    /// Reads the samples of the chunks overlapping the `[start, end]` time range.
+    /// Samples outside the range are discarded.
+    pub fn read_range(
+        &mut self,
+        start: f64,
+        end: f64,
+    ) -> Result<Vec<Vec<PlotSignalSample>>, RecordingError> {
+        let mut regions: Vec<_> = self
+            .index
+            .iter()
+            .filter(|e| e.end_time >= start && e.start_time <= end)
+            .map(|e| (e.start_offset, e.end_offset))
+            .collect();
+
+        // Samples written after the last index record, if the recording was interrupted
+        let indexed_end = self.index.last().map_or(HEADER_LEN, |e| e.end_offset);
+        if indexed_end < self.end_offset {
+            regions.push((indexed_end, self.end_offset));
+        }
+
+        let mut samples = vec![vec![]; self.signals.len()];
+        for (from, to) in regions {
+            self.file.seek(SeekFrom::Start(from))?;
+
+            let mut offset = from;
+            while offset < to {
+                let Some((kind, payload)) = self.read_record(to - offset)? else {
+                    break;
+                };
+                offset += RECORD_OVERHEAD + payload.len() as u64;
+
+                if kind != RecordKind::Samples {
+                    continue;
+                }
+
+                let mut data = payload.as_slice();
+                let (Some(id), Some(count)) = (get_u32(&mut data), get_u32(&mut data)) else {
+                    continue;
+                };
+                let Some(signal) = samples.get_mut(id as usize) else {
+                    continue;
+                };
+
+                for _ in 0..count {
+                    let (Some(time), Some(value)) = (get_f64(&mut data), get_f64(&mut data)) else {
+                        break;
+                    };
+                    if time >= start && time <= end {
+                        signal.push(PlotSignalSample { time, value });
+                    }
+                }
+            }
+        }
+
+        Ok(samples)
+    }
+
+    /// Reads the next valid record, returning `None` at the end of the file or at the first
+    /// incomplete or corrupted record. Fails if the record is longer than the `available` bytes.
+    fn read_record(
+        &mut self,
+        available: u64,
+    ) -> Result<Option<(RecordKind, Vec<u8>)>, RecordingError> {
+        if self.file.fill_buf()?.is_empty() {
+            return Ok(None);
+        }
+
+        let mut head = [0u8; 5];
+        if !read_full(&mut self.file, &mut head)? {
+            self.truncated = true;
+            return Ok(None);
+        }
+
+        let len = u32::from_le_bytes([head[1], head[2], head[3], head[4]]) as u64;
+        if RECORD_OVERHEAD + len > available {
+            return Err(RecordingError::RecordTooLong(len));
+        }
+
+        let mut payload = vec![0u8; len as usize];
+        let mut crc = [0u8; 4];
+        if !read_full(&mut self.file, &mut payload)? || !read_full(&mut self.file, &mut crc)? {
+            self.truncated = true;
+            return Ok(None);
+        }
+
+        let mut hasher = crc32fast::Hasher::new();
+        hasher.update(&head);
+        hasher.update(&payload);
+        if hasher.finalize() != u32::from_le_bytes(crc) {
+            self.truncated = true;
+            return Ok(None);
+        }
+
+        match RecordKind::from_u8(head[0]) {
+            Some(kind) => Ok(Some((kind, payload))),
+            None => {
+                self.truncated = true;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Returns false if the record is malformed
+    fn parse_record(&mut self, kind: RecordKind, payload: &[u8], offset: u64) -> bool {
+        let mut data = payload;
+        match kind {
+            RecordKind::Signal => {
+                let (Some(id), Some(name)) = (get_u32(&mut data), get_str(&mut data)) else {
+                    return false;
+                };
+                if id as usize != self.signals.len() {
+                    return false;
+                }
+                self.signals.push(RecordedSignal { name, unit: None });
+            }
+            RecordKind::Unit => {
+                let (Some(id), Some(unit)) = (get_u32(&mut data), get_str(&mut data)) else {
+                    return false;
+                };
+                let Some(signal) = self.signals.get_mut(id as usize) else {
+                    return false;
+                };
+                signal.unit = Some(unit);
+            }
+            RecordKind::Metadata => {
+                let (Some(key), Some(value)) = (get_str(&mut data), get_str(&mut data)) else {
+                    return false;
+                };
+                self.metadata.insert(key, value);
+            }
+            RecordKind::Index => {
+                let (Some(start_offset), Some(start_time), Some(end_time)) =
+                    (get_u64(&mut data), get_f64(&mut data), get_f64(&mut data))
+                else {
+                    return false;
+                };
+                self.index.push(RecordingIndexEntry {
+                    start_offset,
+                    end_offset: offset + RECORD_OVERHEAD + payload.len() as u64,
+                    start_time,
+                    end_time,
+                });
+                self.unindexed_span = None;
+            }
+            // Decoded on request, except for the time span of the ones not indexed yet
+            RecordKind::Samples => {
+                let (Some(_), Some(count)) = (get_u32(&mut data), get_u32(&mut data)) else {
+                    return true;
+                };
+                for _ in 0..count {
+                    let (Some(time), Some(_)) = (get_f64(&mut data), get_f64(&mut data)) else {
+                        break;
+                    };
+                    self.unindexed_span = Some(match self.unindexed_span {
+                        Some([start, end]) => [start.min(time), end.max(time)],
+                        None => [time, time],
+                    });
+                }
+            }
+        }
+
+        true
+    }
+}
+
+/// Fills `buf`, returning false if the end of the file is reached first
+fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<bool> {
+    match reader.read_exact(buf) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+fn put_u32(buf: &mut Vec<u8>, v: u32) {
+    buf.extend_from_slice(&v.to_le_bytes());
+}
+
+fn put_u64(buf: &mut Vec<u8>, v: u64) {
+    buf.extend_from_slice(&v.to_le_bytes());
+}
+
+fn put_f64(buf: &mut Vec<u8>, v: f64) {
+    buf.extend_from_slice(&v.to_le_bytes());
+}
+
+fn put_str(buf: &mut Vec<u8>, s: &str) {
+    put_u32(buf, s.len() as u32);
+    buf.extend_from_slice(s.as_bytes());
+}
+
+fn get_bytes<'a>(data: &mut &'a [u8], n: usize) -> Option<&'a [u8]> {
+    if data.len() < n {
+        return None;
+    }
+    let (bytes, rest) = data.split_at(n);
+    *data = rest;
+    Some(bytes)
+}
+
+fn get_u32(data: &mut &[u8]) -> Option<u32> {
+    get_bytes(data, 4).map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+}
+
+fn get_u64(data: &mut &[u8]) -> Option<u64> {
+    get_bytes(data, 8).map(|b| u64::from_le_bytes(b.try_into().unwrap()))
+}
+
+fn get_f64(data: &mut &[u8]) -> Option<f64> {
+    get_bytes(data, 8).map(|b| f64::from_le_bytes(b.try_into().unwrap()))
+}
+
+fn get_str(data: &mut &[u8]) -> Option<String> {
+    let len = get_u32(data)? as usize;
+    get_bytes(data, len).and_then(|b| String::from_utf8(b.to_vec()).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TempPath;
+    use crate::PlotSignals;
+
+    fn samples(times: &[f64]) -> Vec<PlotSignalSample> {
+        times
+            .iter()
+            .map(|&time| PlotSignalSample {
+                time,
+                value: time * 10.0,
+            })
+            .collect()
+    }
+
+    /// Records two signals in three chunks of samples, at times 0-1, 2-3 and 4-5
+    fn record(file: &TempPath) -> Vec<u64> {
+        let mut signals = PlotSignals::default();
+        let (a, _) = signals.add_signal("/a").unwrap();
+        let (b, _) = signals.add_signal("/b").unwrap();
+
+        let mut recorder = Recorder::create(file.path()).unwrap();
+        recorder.set_metadata("vehicle", "test");
+        recorder.add_signal(a, "/a");
+        recorder.add_signal(b, "/b");
+        recorder.set_unit(b, "m");
+
+        let mut chunk_ends = vec![];
+        for chunk in 0..3 {
+            let t = chunk as f64 * 2.0;
+            recorder.write_samples(a, &samples(&[t, t + 1.0]));
+            recorder.write_samples(b, &samples(&[t + 0.5]));
+            recorder.end_chunk();
+            chunk_ends.push(recorder.bytes_written());
+        }
+
+        assert!(recorder.error().is_none());
+        assert_eq!(
+            std::fs::metadata(file.path()).unwrap().len(),
+            recorder.bytes_written()
+        );
+        chunk_ends
+    }
+
+    #[test]
+    fn round_trip() {
+        let file = TempPath::new("rdirec");
+        let chunk_ends = record(&file);
+
+        let mut reader = RecordingReader::open(file.path()).unwrap();
+        assert!(!reader.is_truncated());
+        assert_eq!(reader.metadata()["vehicle"], "test");
+        assert_eq!(reader.signals().len(), 2);
+        assert_eq!(reader.signals()[0].name, "/a");
+        assert_eq!(reader.signals()[0].unit, None);
+        assert_eq!(reader.signals()[1].name, "/b");
+        assert_eq!(reader.signals()[1].unit.as_deref(), Some("m"));
+
+        let ends: Vec<_> = reader.index().iter().map(|e| e.end_offset).collect();
+        assert_eq!(ends, chunk_ends);
+        assert_eq!(reader.time_span(), Some([0.0, 5.0]));
+
+        assert_eq!(
+            reader.read_all().unwrap(),
+            vec![
+                samples(&[0.0, 1.0, 2.0, 3.0, 4.0, 5.0]),
+                samples(&[0.5, 2.5, 4.5])
+            ]
+        );
+        assert_eq!(
+            reader.read_range(2.0, 3.0).unwrap(),
+            vec![samples(&[2.0, 3.0]), samples(&[2.5])]
+        );
+    }
+
+    #[test]
+    fn truncated() {
+        let file = TempPath::new("rdirec");
+        let chunk_ends = record(&file);
+
+        // Cut in the middle of the first record of the last chunk
+        let data = std::fs::read(file.path()).unwrap();
+        let cut = TempPath::with_data("rdirec", &data[..chunk_ends[1] as usize + 12]);
+
+        let mut reader = RecordingReader::open(cut.path()).unwrap();
+        assert!(reader.is_truncated());
+        assert_eq!(reader.index().len(), 2);
+        assert_eq!(reader.time_span(), Some([0.0, 3.0]));
+        assert_eq!(
+            reader.read_all().unwrap(),
+            vec![samples(&[0.0, 1.0, 2.0, 3.0]), samples(&[0.5, 2.5])]
+        );
+    }
+
+    #[test]
+    fn corrupted() {
+        let file = TempPath::new("rdirec");
+        let chunk_ends = record(&file);
+
+        // Flip a byte of the CRC of the index record of the last chunk
+        let mut data = std::fs::read(file.path()).unwrap();
+        data[chunk_ends[2] as usize - 1] ^= 0xff;
+        let corrupted = TempPath::with_data("rdirec", &data);
+
+        let mut reader = RecordingReader::open(corrupted.path()).unwrap();
+        assert!(reader.is_truncated());
+        assert_eq!(reader.index().len(), 2);
+        assert_eq!(
+            reader.read_all().unwrap(),
+            vec![
+                samples(&[0.0, 1.0, 2.0, 3.0, 4.0, 5.0]),
+                samples(&[0.5, 2.5, 4.5])
+            ]
+        );
+    }
+}
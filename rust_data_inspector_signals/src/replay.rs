@@ -0,0 +1,432 @@
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{PlotSampleSender, PlotSignalSample, PlotSignals, RecordingError, RecordingReader};
+
+pub const MIN_REPLAY_SPEED: f64 = 0.1;
+pub const MAX_REPLAY_SPEED: f64 = 100.0;
+const REPLAY_PERIOD: Duration = Duration::from_millis(5);
+/// Average number of chunks of the recording read at once
+const WINDOW_CHUNKS: usize = 64;
+
+#[derive(Debug)]
+struct ReplayState {
+    playing: bool,
+    speed: f64,
+    /// Time of the recording up to which samples have been sent
+    position: f64,
+    /// Seeking backwards requires the signals to be cleared before sending samples again
+    clear_requested: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ReplayCommand {
+    /// Wakes the paused replay up to play
+    Play,
+    Step,
+    Seek(f64),
+    /// The signals were cleared as requested
+    Cleared,
+}
+
+/// Replays a recording as if it were live, sending its samples through the usual
+/// [`PlotSampleSender`]s at the pace they were recorded, or faster or slower.
+///
+/// The handle controls the replay and can be cloned to be shared with other threads.
+/// [`ReplayHandle::sync`] must be called periodically by the owner of the [`PlotSignals`],
+/// before updating them.
+#[derive(Debug, Clone)]
+pub struct ReplayHandle {
+    state: Arc<Mutex<ReplayState>>,
+    commands: Sender<ReplayCommand>,
+    time_span: [f64; 2],
+}
+
+impl ReplayHandle {
+    /// Adds the signals in the recording at `path` to `signals` and starts a paused replay.
+    /// Samples are read from the file as the replay progresses.
+    pub fn open(path: impl AsRef<Path>, signals: &mut PlotSignals) -> Result<Self, RecordingError> {
+        let reader = RecordingReader::open(path)?;
+
+        for (key, value) in reader.metadata() {
+            signals.set_metadata(key, value);
+        }
+
+        let mut senders = vec![];
+        for signal in reader.signals() {
+            let (id, sender) = signals.add_signal(&signal.name)?;
+            if let Some(unit) = &signal.unit {
+                signals.set_signal_unit(id, unit);
+            }
+            senders.push(sender);
+        }
+
+        let time_span = reader.time_span().unwrap_or([0.0, 0.0]);
+        let (commands, receiver) = channel();
+        let handle = ReplayHandle {
+            state: Arc::new(Mutex::new(ReplayState {
+                playing: false,
+                speed: 1.0,
+                position: time_span[0],
+                clear_requested: false,
+            })),
+            commands,
+            time_span,
+        };
+
+        let state = handle.state.clone();
+        let stream = SampleStream::new(reader, time_span);
+        thread::spawn(move || replay_thread(state, receiver, senders, stream));
+
+        Ok(handle)
+    }
+
+    pub fn time_span(&self) -> [f64; 2] {
+        self.time_span
+    }
+
+    pub fn position(&self) -> f64 {
+        self.lock().position
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.lock().playing
+    }
+
+    pub fn play(&self) {
+        let mut state = self.lock();
+        if state.position >= self.time_span[1] {
+            // Restart from the beginning
+            self.send(ReplayCommand::Seek(self.time_span[0]));
+        }
+        state.playing = true;
+        self.send(ReplayCommand::Play);
+    }
+
+    pub fn pause(&self) {
+        self.lock().playing = false;
+    }
+
+    pub fn speed(&self) -> f64 {
+        self.lock().speed
+    }
+
+    /// Sets the replay speed, clamped between [`MIN_REPLAY_SPEED`] and [`MAX_REPLAY_SPEED`]
+    pub fn set_speed(&self, speed: f64) {
+        self.lock().speed = speed.clamp(MIN_REPLAY_SPEED, MAX_REPLAY_SPEED);
+    }
+
+    /// Pauses the replay and sends the samples with the next timestamp
+    pub fn step(&self) {
+        self.lock().playing = false;
+        self.send(ReplayCommand::Step);
+    }
+
+    /// Moves the replay to time `t`, sending all the samples up to it
+    pub fn seek(&self, t: f64) {
+        self.send(ReplayCommand::Seek(
+            t.clamp(self.time_span[0], self.time_span[1]),
+        ));
+    }
+
+    /// Clears `signals` if needed to seek backwards. Returns true if they were cleared.
+    pub fn sync(&self, signals: &mut PlotSignals) -> bool {
+        if std::mem::take(&mut self.lock().clear_requested) {
+            signals.clear_timeseries();
+            self.send(ReplayCommand::Cleared);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ReplayState> {
+        self.state.lock().unwrap()
+    }
+
+    fn send(&self, command: ReplayCommand) {
+        // The replay only ends when the signals are dropped, along with the handle
+        let _ = self.commands.send(command);
+    }
+}
+
+/// Reads the samples of a recording in time order, a window of a few chunks at a time
+#[derive(Debug)]
+struct SampleStream {
+    reader: RecordingReader,
+    time_span: [f64; 2],
+    window: f64,
+    /// Start of the next window to read
+    next: f64,
+    done: bool,
+    /// Samples read and not sent yet, with the index of their signal
+    buffer: VecDeque<(usize, PlotSignalSample)>,
+}
+
+impl SampleStream {
+    fn new(reader: RecordingReader, time_span: [f64; 2]) -> Self {
+        let chunks = reader.index().len().max(1);
+        SampleStream {
+            reader,
+            time_span,
+            window: (time_span[1] - time_span[0]) * WINDOW_CHUNKS as f64 / chunks as f64,
+            next: time_span[0],
+            done: false,
+            buffer: VecDeque::new(),
+        }
+    }
+
+    fn rewind(&mut self) {
+        self.next = self.time_span[0];
+        self.done = false;
+        self.buffer.clear();
+    }
+
+    /// Returns the next sample, reading the next windows if needed
+    fn peek(&mut self) -> Option<(usize, PlotSignalSample)> {
+        while self.buffer.is_empty() && !self.done {
+            self.read_window();
+        }
+        self.buffer.front().copied()
+    }
+
+    fn read_window(&mut self) {
+        let start = self.next;
+        let mut end = start + self.window;
+        if !(end > start && end < self.time_span[1]) {
+            // Last window, also when the window is too small to make progress
+            end = f64::INFINITY;
+            self.done = true;
+        }
+        self.next = end;
+
+        let Ok(samples) = self.reader.read_range(start, end) else {
+            // The file can't be read anymore, as if the recording ended here
+            self.done = true;
+            return;
+        };
+
+        // Samples at the end of the window are read again by the next one
+        let mut window: Vec<_> = samples
+            .into_iter()
+            .enumerate()
+            .flat_map(|(i, samples)| samples.into_iter().map(move |s| (i, s)))
+            .filter(|(_, s)| s.time < end)
+            .collect();
+        // Stable, so samples of the same signal stay in order
+        window.sort_by(|a, b| a.1.time.total_cmp(&b.1.time));
+        self.buffer.extend(window);
+    }
+}
+
+fn replay_thread(
+    state: Arc<Mutex<ReplayState>>,
+    commands: Receiver<ReplayCommand>,
+    senders: Vec<PlotSampleSender>,
+    mut stream: SampleStream,
+) {
+    let end = stream.time_span[1];
+
+    // Sends the samples up to time `t`. Returns false if the receivers are gone.
+    let send_until = |stream: &mut SampleStream, state: &mut ReplayState, t: f64| -> bool {
+        while let Some((i, sample)) = stream.peek() {
+            if sample.time > t {
+                break;
+            }
+            if senders[i].send(sample).is_err() {
+                return false;
+            }
+            stream.buffer.pop_front();
+        }
+        state.position = state.position.max(t);
+        true
+    };
+
+    // Time to seek to once the signals are cleared
+    let mut clearing: Option<f64> = None;
+    let mut last_tick = Instant::now();
+    loop {
+        let was_playing = state.lock().unwrap().playing && clearing.is_none();
+
+        // Blocks until the next command while paused
+        let command = if was_playing {
+            match commands.recv_timeout(REPLAY_PERIOD) {
+                Ok(command) => Some(command),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        } else {
+            match commands.recv() {
+                Ok(command) => Some(command),
+                Err(_) => return,
+            }
+        };
+        let elapsed = last_tick.elapsed().as_secs_f64();
+        last_tick = Instant::now();
+
+        let mut state = state.lock().unwrap();
+        let mut sent = match command {
+            Some(ReplayCommand::Cleared) => {
+                let t = clearing.take().unwrap_or(state.position);
+                stream.rewind();
+                state.position = f64::NEG_INFINITY;
+                send_until(&mut stream, &mut state, t)
+            }
+            Some(ReplayCommand::Seek(t)) if clearing.is_some() || t < state.position => {
+                clearing = Some(t);
+                state.clear_requested = true;
+                true
+            }
+            // Nothing is sent until the signals are cleared
+            _ if clearing.is_some() => true,
+            Some(ReplayCommand::Seek(t)) => send_until(&mut stream, &mut state, t),
+            Some(ReplayCommand::Step) => match stream.peek() {
+                Some((_, next)) => send_until(&mut stream, &mut state, next.time),
+                None => true,
+            },
+            Some(ReplayCommand::Play) | None => true,
+        };
+
+        if sent && was_playing && state.playing && clearing.is_none() {
+            let t = state.position + elapsed * state.speed;
+            sent = send_until(&mut stream, &mut state, t);
+        }
+
+        if !sent {
+            return;
+        }
+
+        if clearing.is_none() && state.position >= end {
+            state.position = end;
+            state.playing = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{wait_until, TempPath};
+    use crate::Recorder;
+
+    /// Records `/a` with a sample every second from 0 to 4 and `/b` with samples at 1 and 3
+    fn record() -> TempPath {
+        let file = TempPath::new("rdirec");
+        let mut signals = PlotSignals::default();
+        let (a, _) = signals.add_signal("/a").unwrap();
+        let (b, _) = signals.add_signal("/b").unwrap();
+
+        let mut recorder = Recorder::create(file.path()).unwrap();
+        recorder.add_signal(a, "/a");
+        recorder.add_signal(b, "/b");
+        for t in 0..5 {
+            let sample = PlotSignalSample {
+                time: t as f64,
+                value: 0.0,
+            };
+            recorder.write_samples(a, &[sample]);
+            if t % 2 == 1 {
+                recorder.write_samples(b, &[sample]);
+            }
+            recorder.end_chunk();
+        }
+        file
+    }
+
+    fn open() -> (TempPath, ReplayHandle, PlotSignals) {
+        let file = record();
+        let mut signals = PlotSignals::default();
+        let handle = ReplayHandle::open(file.path(), &mut signals).unwrap();
+        (file, handle, signals)
+    }
+
+    /// Times of the samples received by the signal `name`
+    fn times(signals: &mut PlotSignals, name: &str) -> Vec<f64> {
+        signals.update();
+        signals
+            .get_signals()
+            .values()
+            .find(|s| s.name() == name)
+            .unwrap()
+            .time()
+            .clone()
+    }
+
+    /// Waits for `signals` to receive exactly the samples up to `t`
+    fn wait_samples_until(signals: &mut PlotSignals, t: f64) {
+        let a: Vec<_> = [0.0, 1.0, 2.0, 3.0, 4.0]
+            .into_iter()
+            .filter(|&s| s <= t)
+            .collect();
+        let b: Vec<_> = [1.0, 3.0].into_iter().filter(|&s| s <= t).collect();
+        wait_until(|| times(signals, "/a") == a && times(signals, "/b") == b);
+
+        // Nothing else is sent
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(times(signals, "/a"), a);
+        assert_eq!(times(signals, "/b"), b);
+    }
+
+    #[test]
+    fn step() {
+        let (_file, handle, mut signals) = open();
+        assert_eq!(handle.time_span(), [0.0, 4.0]);
+
+        handle.step();
+        wait_samples_until(&mut signals, 0.0);
+        handle.step();
+        wait_samples_until(&mut signals, 1.0);
+        assert_eq!(handle.position(), 1.0);
+        assert!(!handle.is_playing());
+    }
+
+    #[test]
+    fn seek_backwards() {
+        let (_file, handle, mut signals) = open();
+
+        handle.seek(3.0);
+        wait_samples_until(&mut signals, 3.0);
+        assert!(!handle.sync(&mut signals));
+
+        handle.seek(1.0);
+        wait_until(|| handle.sync(&mut signals));
+        wait_samples_until(&mut signals, 1.0);
+        assert_eq!(handle.position(), 1.0);
+    }
+
+    #[test]
+    fn commands_while_clearing() {
+        let (_file, handle, mut signals) = open();
+
+        handle.seek(3.0);
+        wait_samples_until(&mut signals, 3.0);
+
+        // Only the last seek is applied once the signals are cleared
+        handle.seek(0.0);
+        handle.step();
+        handle.seek(2.0);
+        handle.step();
+        wait_until(|| handle.sync(&mut signals));
+        wait_samples_until(&mut signals, 2.0);
+    }
+
+    #[test]
+    fn play_restarts_at_end() {
+        let (_file, handle, mut signals) = open();
+        handle.set_speed(MAX_REPLAY_SPEED);
+
+        handle.seek(4.0);
+        wait_samples_until(&mut signals, 4.0);
+        assert_eq!(handle.position(), 4.0);
+
+        handle.play();
+        wait_until(|| handle.sync(&mut signals));
+        wait_until(|| !handle.is_playing());
+        wait_samples_until(&mut signals, 4.0);
+        assert_eq!(handle.position(), 4.0);
+    }
+}
@@ -0,0 +1,43 @@
+//! Paths of the signals loaded from files, and their selection
+
+/// Normalizes the selected paths to start with a single `/` and have no trailing `/`
+pub(crate) fn normalize(paths: &[String]) -> Vec<String> {
+    paths
+        .iter()
+        .map(|p| format!("/{}", p.trim_matches('/')))
+        .collect()
+}
+
+/// True if `path` is selected, or is the parent of a selected path
+pub(crate) fn overlaps(path: &str, selection: &[String]) -> bool {
+    selection.is_empty()
+        || selection.iter().any(|s| {
+            path == s
+                || path.starts_with(&format!("{}/", s))
+                || s.starts_with(&format!("{}/", path))
+        })
+}
+
+/// True if `path` or one of its parents is selected. Everything is selected if `selection` is empty.
+pub(crate) fn is_selected(path: &str, selection: &[String]) -> bool {
+    selection.is_empty()
+        || selection
+            .iter()
+            .any(|s| path == s || path.starts_with(&format!("{}/", s)))
+}
+
+/// Converts a topic or field name to a valid signal path, replacing illegal characters with `_`
+pub(crate) fn sanitize(path: &str) -> String {
+    let path: String = path
+        .chars()
+        .map(|c| {
+            if c == '/' || c.is_alphanumeric() || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let parts: Vec<_> = path.split('/').filter(|p| !p.is_empty()).collect();
+    format!("/{}", parts.join("/"))
+}
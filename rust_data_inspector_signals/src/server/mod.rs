@@ -0,0 +1,640 @@
+//! Ingestion of signals streamed by other processes over TCP or UDP, with the protocol
+//! described in `docs/protocol.md`.
+
+mod protocol;
+
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Read};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{PlotSampleSender, PlotSignals, SignalRegistry};
+
+pub use protocol::{is_valid_namespace, ProtocolError, WireMessage, WIRE_MAGIC};
+
+/// How often blocked threads check if the server was stopped
+const POLL_PERIOD: Duration = Duration::from_millis(100);
+/// UDP sources silent for this long are considered disconnected
+const UDP_TIMEOUT: Duration = Duration::from_secs(10);
+/// TCP connections silent for this long are closed, as the client may be gone without closing
+/// them, keeping its namespace in use
+const TCP_TIMEOUT: Duration = Duration::from_secs(60);
+const MAX_DATAGRAM_LEN: usize = 65536;
+/// Longest line of the text protocol
+const MAX_LINE_LEN: usize = 64 * 1024;
+
+/// Receives signals from other processes, over TCP connections and UDP datagrams on the same
+/// address, using the protocol described in `docs/protocol.md`.
+///
+/// The signals of each connection are added under its own namespace, like `/<namespace>/...`.
+/// The namespace is chosen by the client, or generated if it doesn't send one. A client
+/// reconnecting with the same namespace continues its previous signals. Samples older than
+/// the last one of their signal are dropped. Connections and errors are counted in [`ServerStats`].
+///
+/// The server stops when dropped.
+#[derive(Debug)]
+pub struct SignalServer {
+    local_addr: SocketAddr,
+    shared: Arc<Shared>,
+}
+
+impl SignalServer {
+    /// Binds a TCP listener and a UDP socket to `addr`, like `0.0.0.0:7000`, and starts
+    /// accepting data
+    pub fn listen(addr: impl ToSocketAddrs, signals: &PlotSignals) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let local_addr = listener.local_addr()?;
+        // Same port as the listener, in case it was chosen by the OS
+        let socket = UdpSocket::bind(local_addr)?;
+
+        listener.set_nonblocking(true)?;
+        socket.set_read_timeout(Some(POLL_PERIOD))?;
+
+        let shared = Arc::new(Shared {
+            registry: signals.registry(),
+            stop: AtomicBool::new(false),
+            namespaces: Mutex::new(Namespaces::default()),
+            stats: Mutex::new(ServerStats::default()),
+        });
+
+        let tcp_shared = shared.clone();
+        thread::spawn(move || accept_thread(tcp_shared, listener));
+        let udp_shared = shared.clone();
+        thread::spawn(move || udp_thread(udp_shared, socket));
+
+        Ok(SignalServer { local_addr, shared })
+    }
+
+    /// Address the server is bound to, useful when binding to port 0
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Counters of the clients and their errors, since the server started
+    pub fn stats(&self) -> ServerStats {
+        self.shared.stats().clone()
+    }
+}
+
+/// Counters of the clients of a [`SignalServer`] and of their errors
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ServerStats {
+    /// Clients currently connected, over TCP or UDP
+    pub connected: usize,
+    /// Connections since the server started
+    pub connections: u64,
+    /// Messages that could not be handled, and connections closed because of an error
+    pub errors: u64,
+    /// Last error, prefixed by the address of the client it comes from
+    pub last_error: Option<String>,
+    /// Samples older than the last one of their signal
+    pub dropped_samples: u64,
+}
+
+impl Drop for SignalServer {
+    fn drop(&mut self) {
+        self.shared.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+impl PlotSignals {
+    /// Starts receiving signals from other processes on `addr`, see [`SignalServer`]
+    pub fn listen(&self, addr: impl ToSocketAddrs) -> io::Result<SignalServer> {
+        SignalServer::listen(addr, self)
+    }
+}
+
+#[derive(Debug)]
+struct Shared {
+    registry: SignalRegistry,
+    stop: AtomicBool,
+    namespaces: Mutex<Namespaces>,
+    stats: Mutex<ServerStats>,
+}
+
+impl Shared {
+    fn stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+
+    fn namespaces(&self) -> MutexGuard<'_, Namespaces> {
+        self.namespaces.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn stats(&self) -> MutexGuard<'_, ServerStats> {
+        self.stats.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Claims a namespace for a new connection, see [`Namespaces::claim`]
+    fn connect(&self, requested: Option<&str>) -> Namespace {
+        let namespace = self.namespaces().claim(requested);
+        let mut stats = self.stats();
+        stats.connected += 1;
+        stats.connections += 1;
+        namespace
+    }
+
+    fn disconnect(&self, namespace: Namespace) {
+        self.namespaces().release(namespace);
+        self.stats().connected -= 1;
+    }
+
+    fn report(&self, error: String) {
+        let mut stats = self.stats();
+        stats.errors += 1;
+        stats.last_error = Some(error);
+    }
+}
+
+#[derive(Debug)]
+struct NetworkSignal {
+    sender: PlotSampleSender,
+    last_time: f64,
+}
+
+/// Signals of a namespace, by name relative to it
+#[derive(Debug)]
+struct Namespace {
+    name: String,
+    signals: HashMap<String, NetworkSignal>,
+}
+
+#[derive(Debug, Default)]
+struct Namespaces {
+    /// Namespaces of the connected clients
+    active: HashSet<String>,
+    /// Namespaces of the clients that disconnected, kept to continue their signals if they
+    /// reconnect
+    idle: HashMap<String, Namespace>,
+    clients: usize,
+}
+
+impl Namespaces {
+    /// Claims the namespace `requested`, or a generated one. If it is in use by another
+    /// connection, a numbered suffix is added.
+    fn claim(&mut self, requested: Option<&str>) -> Namespace {
+        self.clients += 1;
+        let base = match requested {
+            Some(name) => name.to_string(),
+            None => format!("client{}", self.clients),
+        };
+
+        let mut name = base.clone();
+        let mut n = 1;
+        while self.active.contains(&name) {
+            n += 1;
+            name = format!("{}_{}", base, n);
+        }
+
+        self.active.insert(name.clone());
+        self.idle.remove(&name).unwrap_or(Namespace {
+            name,
+            signals: HashMap::new(),
+        })
+    }
+
+    fn release(&mut self, namespace: Namespace) {
+        self.active.remove(&namespace.name);
+        self.idle.insert(namespace.name.clone(), namespace);
+    }
+}
+
+/// State of a client, for the lifetime of its connection
+struct Connection {
+    shared: Arc<Shared>,
+    peer: SocketAddr,
+    namespace: Option<Namespace>,
+    /// Namespace requested by the client
+    hello: Option<String>,
+    /// Name of each signal, relative to the namespace, by the id chosen by the client
+    ids: HashMap<u32, String>,
+    /// Ids of samples received before their signal, reported only once
+    unknown_ids: HashSet<u32>,
+}
+
+impl Connection {
+    fn new(shared: Arc<Shared>, peer: SocketAddr) -> Self {
+        Connection {
+            shared,
+            peer,
+            namespace: None,
+            hello: None,
+            ids: HashMap::new(),
+            unknown_ids: HashSet::new(),
+        }
+    }
+
+    fn namespace(&mut self) -> &mut Namespace {
+        let shared = &self.shared;
+        self.namespace.get_or_insert_with(|| shared.connect(None))
+    }
+
+    /// Handles the binary frames at the start of `data`, returning the length handled
+    fn handle_binary(&mut self, data: &[u8]) -> Result<usize, ProtocolError> {
+        let mut pos = 0;
+        while let Some((message, len)) = WireMessage::decode(&data[pos..]) {
+            pos += len;
+            match message {
+                Ok(message) => self.handle(message),
+                Err(e @ ProtocolError::FrameTooLong(_)) => return Err(e),
+                Err(e) => self.report(e),
+            }
+        }
+        Ok(pos)
+    }
+
+    /// Handles the complete lines at the start of `data`, returning the length handled
+    fn handle_text(&mut self, data: &[u8]) -> usize {
+        let Some(end) = data.iter().rposition(|&b| b == b'\n') else {
+            return 0;
+        };
+        self.handle_lines(&data[..end + 1]);
+        end + 1
+    }
+
+    fn handle_lines(&mut self, data: &[u8]) {
+        for line in String::from_utf8_lossy(data).lines() {
+            match WireMessage::parse_line(line) {
+                Ok(Some(message)) => self.handle(message),
+                Ok(None) => {}
+                Err(e) => self.report(e),
+            }
+        }
+    }
+
+    fn handle(&mut self, message: WireMessage) {
+        match message {
+            WireMessage::Hello { namespace } => {
+                if self.namespace.is_some() {
+                    // Repeated by UDP producers, in case the first one was lost
+                    if self.hello.as_ref() != Some(&namespace) {
+                        self.report("hello must be sent before anything else");
+                    }
+                } else if !is_valid_namespace(&namespace) {
+                    self.report(format!("invalid namespace '{}'", namespace));
+                } else {
+                    self.namespace = Some(self.shared.connect(Some(&namespace)));
+                    self.hello = Some(namespace);
+                }
+            }
+            WireMessage::Signal { id, name } => {
+                let name = name.trim_start_matches('/').to_string();
+                let registry = self.shared.registry.clone();
+                let namespace = self.namespace();
+                if !namespace.signals.contains_key(&name) {
+                    let full_name = format!("/{}/{}", namespace.name, name);
+                    match registry.add_signal(&full_name) {
+                        Ok(sender) => {
+                            namespace.signals.insert(
+                                name.clone(),
+                                NetworkSignal {
+                                    sender,
+                                    last_time: f64::NEG_INFINITY,
+                                },
+                            );
+                        }
+                        Err(e) => return self.report(e),
+                    }
+                }
+                self.unknown_ids.remove(&id);
+                self.ids.insert(id, name);
+            }
+            WireMessage::Samples { id, samples } => {
+                let Some(name) = self.ids.get(&id).cloned() else {
+                    if self.unknown_ids.insert(id) {
+                        self.report(format!("samples of unknown signal id {}", id));
+                    }
+                    return;
+                };
+                let mut dropped = 0;
+                let signal = self.namespace().signals.get_mut(&name).unwrap();
+                for sample in samples {
+                    if sample.time.is_nan() || sample.time < signal.last_time {
+                        dropped += 1;
+                        continue;
+                    }
+                    signal.last_time = sample.time;
+                    let _ = signal.sender.send(sample);
+                }
+                if dropped > 0 {
+                    self.shared.stats().dropped_samples += dropped;
+                }
+            }
+            WireMessage::Event { time, text } => {
+                let text = format!("{}: {}", self.namespace().name, text);
+                self.shared.registry.add_event(time, &text);
+            }
+            WireMessage::Keepalive => {}
+        }
+    }
+
+    fn report(&self, error: impl std::fmt::Display) {
+        self.shared.report(format!("{}: {}", self.peer, error));
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        if let Some(namespace) = self.namespace.take() {
+            self.shared.disconnect(namespace);
+        }
+    }
+}
+
+fn accept_thread(shared: Arc<Shared>, listener: TcpListener) {
+    while !shared.stopped() {
+        match listener.accept() {
+            Ok((stream, peer)) => {
+                let shared = shared.clone();
+                thread::spawn(move || {
+                    if let Err(e) = tcp_thread(shared.clone(), stream, peer) {
+                        shared.report(format!("{}: connection failed: {}", peer, e));
+                    }
+                });
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => thread::sleep(POLL_PERIOD),
+            Err(e) => {
+                shared.report(format!("failed to accept a connection: {}", e));
+                thread::sleep(POLL_PERIOD);
+            }
+        }
+    }
+}
+
+fn tcp_thread(shared: Arc<Shared>, mut stream: TcpStream, peer: SocketAddr) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(POLL_PERIOD))?;
+
+    let mut connection = Connection::new(shared.clone(), peer);
+    let mut binary = None;
+    let mut buf = vec![];
+    let mut chunk = vec![0; MAX_DATAGRAM_LEN];
+    let mut last_seen = Instant::now();
+    while !shared.stopped() {
+        let n = match stream.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                if last_seen.elapsed() >= TCP_TIMEOUT {
+                    return Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        "no data received for too long",
+                    ));
+                }
+                continue;
+            }
+            Err(e) if e.kind() == io::ErrorKind::ConnectionReset => break,
+            Err(e) => return Err(e),
+        };
+        buf.extend_from_slice(&chunk[..n]);
+        last_seen = Instant::now();
+
+        // The variant of the protocol is chosen by the first bytes of the connection
+        let binary = match binary {
+            Some(binary) => binary,
+            None if buf.len() < WIRE_MAGIC.len() && WIRE_MAGIC.starts_with(&buf) => continue,
+            None => {
+                let is_binary = buf.starts_with(WIRE_MAGIC);
+                if is_binary {
+                    buf.drain(..WIRE_MAGIC.len());
+                }
+                *binary.insert(is_binary)
+            }
+        };
+
+        let handled = if binary {
+            connection
+                .handle_binary(&buf)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+        } else {
+            let handled = connection.handle_text(&buf);
+            if buf.len() - handled > MAX_LINE_LEN {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "line is too long",
+                ));
+            }
+            handled
+        };
+        buf.drain(..handled);
+    }
+    Ok(())
+}
+
+fn udp_thread(shared: Arc<Shared>, socket: UdpSocket) {
+    let mut sources: HashMap<SocketAddr, (Connection, Instant)> = HashMap::new();
+    let mut buf = vec![0; MAX_DATAGRAM_LEN];
+    while !shared.stopped() {
+        match socket.recv_from(&mut buf) {
+            Ok((len, peer)) => {
+                let (connection, last_seen) = sources
+                    .entry(peer)
+                    .or_insert_with(|| (Connection::new(shared.clone(), peer), Instant::now()));
+                *last_seen = Instant::now();
+
+                // Each datagram holds complete frames or lines
+                let data = &buf[..len];
+                if let Some(frames) = data.strip_prefix(WIRE_MAGIC) {
+                    if let Err(e) = connection.handle_binary(frames) {
+                        connection.report(e);
+                    }
+                } else {
+                    connection.handle_lines(data);
+                }
+            }
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) => {}
+            Err(e) => {
+                shared.report(format!("UDP reception failed: {}", e));
+                return;
+            }
+        }
+
+        sources.retain(|_, (_, last_seen)| last_seen.elapsed() < UDP_TIMEOUT);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+    use crate::test_utils::{samples_of, wait_until};
+    use crate::PlotSignalSample;
+
+    fn listen() -> (PlotSignals, SignalServer) {
+        let signals = PlotSignals::default();
+        let server = SignalServer::listen("127.0.0.1:0", &signals).unwrap();
+        (signals, server)
+    }
+
+    fn connect(server: &SignalServer, data: &[u8]) -> TcpStream {
+        let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+        stream.write_all(data).unwrap();
+        stream
+    }
+
+    fn binary(messages: &[WireMessage]) -> Vec<u8> {
+        let mut data = WIRE_MAGIC.to_vec();
+        for message in messages {
+            message.encode(&mut data);
+        }
+        data
+    }
+
+    /// Waits for the signal `name` to have exactly `expected` samples
+    fn wait_samples(signals: &mut PlotSignals, name: &str, expected: &[(f64, f64)]) {
+        wait_until(|| {
+            signals.update();
+            samples_of(signals, name).is_some_and(|s| s == expected)
+        });
+    }
+
+    fn names(signals: &PlotSignals) -> Vec<&str> {
+        let mut names: Vec<_> = signals.get_signals().values().map(|s| s.name()).collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn namespace_claims() {
+        let (mut signals, server) = listen();
+
+        let _a = connect(&server, b"hello imu\nsignal 1 x\nsample 1 0 1\n");
+        wait_samples(&mut signals, "/imu/x", &[(0.0, 1.0)]);
+        let _b = connect(&server, b"hello imu\nsignal 1 x\nsample 1 0 2\n");
+        wait_samples(&mut signals, "/imu_2/x", &[(0.0, 2.0)]);
+        let _c = connect(&server, b"signal 1 x\nsample 1 0 3\n");
+        wait_samples(&mut signals, "/client3/x", &[(0.0, 3.0)]);
+
+        assert_eq!(names(&signals), ["/client3/x", "/imu/x", "/imu_2/x"]);
+        let stats = server.stats();
+        assert_eq!(
+            (stats.connected, stats.connections, stats.errors),
+            (3, 3, 0)
+        );
+    }
+
+    #[test]
+    fn reconnection() {
+        let (mut signals, server) = listen();
+
+        let a = connect(
+            &server,
+            b"hello imu\nsignal 1 x\nsample 1 0 1\nsample 1 1 2\n",
+        );
+        wait_samples(&mut signals, "/imu/x", &[(0.0, 1.0), (1.0, 2.0)]);
+        drop(a);
+        wait_until(|| server.stats().connected == 0);
+
+        // Same signal with another id, older samples are still dropped
+        let _b = connect(
+            &server,
+            b"hello imu\nsignal 7 x\nsample 7 0.5 3\nsample 7 2 4\n",
+        );
+        wait_samples(
+            &mut signals,
+            "/imu/x",
+            &[(0.0, 1.0), (1.0, 2.0), (2.0, 4.0)],
+        );
+
+        assert_eq!(names(&signals), ["/imu/x"]);
+        let stats = server.stats();
+        assert_eq!((stats.connected, stats.connections), (1, 2));
+        assert_eq!(stats.dropped_samples, 1);
+    }
+
+    #[test]
+    fn dropped_samples() {
+        let (mut signals, server) = listen();
+
+        let samples = [
+            (0.0, 1.0),
+            (2.0, 2.0),
+            (1.0, 3.0),
+            (f64::NAN, 4.0),
+            (2.0, 5.0),
+        ];
+        let _stream = connect(
+            &server,
+            &binary(&[
+                WireMessage::Hello {
+                    namespace: "imu".to_string(),
+                },
+                WireMessage::Signal {
+                    id: 1,
+                    name: "x".to_string(),
+                },
+                WireMessage::Samples {
+                    id: 1,
+                    samples: samples
+                        .iter()
+                        .map(|&(time, value)| PlotSignalSample { time, value })
+                        .collect(),
+                },
+            ]),
+        );
+        wait_samples(
+            &mut signals,
+            "/imu/x",
+            &[(0.0, 1.0), (2.0, 2.0), (2.0, 5.0)],
+        );
+        // Counted once all the samples of the message are handled
+        wait_until(|| server.stats().dropped_samples == 2);
+        assert_eq!(server.stats().errors, 0);
+    }
+
+    #[test]
+    fn text_and_binary() {
+        let (mut signals, server) = listen();
+
+        let mut text = connect(&server, b"hello text\nsignal 1 x\nevent 0 started\n");
+        let mut binary_stream = connect(
+            &server,
+            &binary(&[
+                WireMessage::Hello {
+                    namespace: "binary".to_string(),
+                },
+                WireMessage::Signal {
+                    id: 1,
+                    name: "x".to_string(),
+                },
+            ]),
+        );
+        wait_until(|| {
+            signals.update();
+            signals.get_signals().len() == 2 && signals.events().len() == 1
+        });
+
+        // Both connections stay open and keep their variant
+        text.write_all(b"sample 1 1 2\n").unwrap();
+        let mut data = vec![];
+        WireMessage::Samples {
+            id: 1,
+            samples: vec![PlotSignalSample {
+                time: 1.0,
+                value: 3.0,
+            }],
+        }
+        .encode(&mut data);
+        binary_stream.write_all(&data).unwrap();
+        wait_samples(&mut signals, "/text/x", &[(1.0, 2.0)]);
+        wait_samples(&mut signals, "/binary/x", &[(1.0, 3.0)]);
+
+        assert_eq!(names(&signals), ["/binary/x", "/text/x"]);
+        assert_eq!(signals.events()[0].text, "text: started");
+        let stats = server.stats();
+        assert_eq!((stats.connected, stats.errors), (2, 0));
+    }
+}
@@ -0,0 +1,396 @@
+//! Encoding of the messages of the ingestion protocol, described in `docs/protocol.md`
+
+use regex::Regex;
+use thiserror::Error;
+
+use crate::PlotSignalSample;
+
+/// Sent at the start of a binary connection, or of a binary UDP datagram
+pub const WIRE_MAGIC: &[u8; 4] = b"RDI1";
+
+/// Type and length of a binary frame
+const FRAME_HEADER_LEN: usize = 5;
+const MAX_FRAME_LEN: usize = 16 * 1024 * 1024;
+const SAMPLE_LEN: usize = 16;
+
+const TYPE_HELLO: u8 = 0x01;
+const TYPE_SIGNAL: u8 = 0x02;
+const TYPE_SAMPLES: u8 = 0x03;
+const TYPE_EVENT: u8 = 0x04;
+const TYPE_KEEPALIVE: u8 = 0x05;
+
+/// Checks that `namespace` is made of letters, numbers and underscores, like `imu_2`
+pub fn is_valid_namespace(namespace: &str) -> bool {
+    Regex::new(r"^\w+$").unwrap().is_match(namespace)
+}
+
+#[derive(Debug, Error)]
+pub enum ProtocolError {
+    #[error("Unknown message type {0:#04x}")]
+    UnknownType(u8),
+    #[error("Unknown command '{0}'")]
+    UnknownCommand(String),
+    #[error("Frame of {0} bytes is too long")]
+    FrameTooLong(usize),
+    #[error("Malformed message: {0}")]
+    Malformed(String),
+}
+
+/// Message of the ingestion protocol, sent by a data producer to the viewer
+#[derive(Debug, Clone, PartialEq)]
+pub enum WireMessage {
+    /// Names the namespace of the connection, must be sent before anything else
+    Hello {
+        namespace: String,
+    },
+    /// Registers a signal, relative to the namespace, and the id its samples are sent with
+    Signal {
+        id: u32,
+        name: String,
+    },
+    Samples {
+        id: u32,
+        samples: Vec<PlotSignalSample>,
+    },
+    Event {
+        time: f64,
+        text: String,
+    },
+    /// Keeps an idle TCP connection open, as the viewer closes connections without data
+    Keepalive,
+}
+
+impl WireMessage {
+    /// Appends the binary frame of the message to `out`
+    pub fn encode(&self, out: &mut Vec<u8>) {
+        let start = out.len();
+        out.extend_from_slice(&[0; FRAME_HEADER_LEN]);
+        let ty = match self {
+            WireMessage::Hello { namespace } => {
+                out.extend_from_slice(namespace.as_bytes());
+                TYPE_HELLO
+            }
+            WireMessage::Signal { id, name } => {
+                out.extend_from_slice(&id.to_le_bytes());
+                out.extend_from_slice(name.as_bytes());
+                TYPE_SIGNAL
+            }
+            WireMessage::Samples { id, samples } => {
+                out.extend_from_slice(&id.to_le_bytes());
+                for sample in samples {
+                    out.extend_from_slice(&sample.time.to_le_bytes());
+                    out.extend_from_slice(&sample.value.to_le_bytes());
+                }
+                TYPE_SAMPLES
+            }
+            WireMessage::Event { time, text } => {
+                out.extend_from_slice(&time.to_le_bytes());
+                out.extend_from_slice(text.as_bytes());
+                TYPE_EVENT
+            }
+            WireMessage::Keepalive => TYPE_KEEPALIVE,
+        };
+        let len = (out.len() - start - FRAME_HEADER_LEN) as u32;
+        out[start] = ty;
+        out[start + 1..start + FRAME_HEADER_LEN].copy_from_slice(&len.to_le_bytes());
+    }
+
+    /// Decodes the binary frame at the start of `data`, returning the message and the length
+    /// of the frame, or `None` if the frame is incomplete.
+    ///
+    /// Frames with an invalid content still return their length, so that the following ones
+    /// can be decoded, except if they are too long to be trusted.
+    pub fn decode(data: &[u8]) -> Option<(Result<Self, ProtocolError>, usize)> {
+        let header = data.get(..FRAME_HEADER_LEN)?;
+        let len = u32::from_le_bytes(header[1..].try_into().unwrap()) as usize;
+        if len > MAX_FRAME_LEN {
+            return Some((Err(ProtocolError::FrameTooLong(len)), data.len()));
+        }
+        let payload = data.get(FRAME_HEADER_LEN..FRAME_HEADER_LEN + len)?;
+        Some((
+            Self::decode_payload(header[0], payload),
+            FRAME_HEADER_LEN + len,
+        ))
+    }
+
+    fn decode_payload(ty: u8, payload: &[u8]) -> Result<Self, ProtocolError> {
+        let text = |bytes: &[u8]| {
+            String::from_utf8(bytes.to_vec())
+                .map_err(|_| ProtocolError::Malformed("text is not valid UTF-8".to_string()))
+        };
+        let split = |n: usize| {
+            (payload.len() >= n)
+                .then(|| payload.split_at(n))
+                .ok_or_else(|| ProtocolError::Malformed("message is truncated".to_string()))
+        };
+
+        Ok(match ty {
+            TYPE_HELLO => WireMessage::Hello {
+                namespace: text(payload)?,
+            },
+            TYPE_SIGNAL => {
+                let (id, name) = split(4)?;
+                WireMessage::Signal {
+                    id: u32::from_le_bytes(id.try_into().unwrap()),
+                    name: text(name)?,
+                }
+            }
+            TYPE_SAMPLES => {
+                let (id, samples) = split(4)?;
+                if samples.len() % SAMPLE_LEN != 0 {
+                    return Err(ProtocolError::Malformed(
+                        "samples are truncated".to_string(),
+                    ));
+                }
+                WireMessage::Samples {
+                    id: u32::from_le_bytes(id.try_into().unwrap()),
+                    samples: samples
+                        .chunks_exact(SAMPLE_LEN)
+                        .map(|s| PlotSignalSample {
+                            time: f64::from_le_bytes(s[..8].try_into().unwrap()),
+                            value: f64::from_le_bytes(s[8..].try_into().unwrap()),
+                        })
+                        .collect(),
+                }
+            }
+            TYPE_EVENT => {
+                let (time, event) = split(8)?;
+                WireMessage::Event {
+                    time: f64::from_le_bytes(time.try_into().unwrap()),
+                    text: text(event)?,
+                }
+            }
+            TYPE_KEEPALIVE => WireMessage::Keepalive,
+            ty => return Err(ProtocolError::UnknownType(ty)),
+        })
+    }
+
+    /// Appends the message to `out` as lines of the text variant of the protocol
+    pub fn encode_text(&self, out: &mut String) {
+        use std::fmt::Write;
+
+        match self {
+            WireMessage::Hello { namespace } => writeln!(out, "hello {}", namespace),
+            WireMessage::Signal { id, name } => writeln!(out, "signal {} {}", id, name),
+            WireMessage::Samples { id, samples } => samples
+                .iter()
+                .try_for_each(|s| writeln!(out, "sample {} {} {}", id, s.time, s.value)),
+            WireMessage::Event { time, text } => {
+                writeln!(out, "event {} {}", time, text.replace(['\r', '\n'], " "))
+            }
+            WireMessage::Keepalive => writeln!(out, "keepalive"),
+        }
+        .unwrap();
+    }
+
+    /// Parses a line of the text variant of the protocol. Returns `None` for empty lines and
+    /// comments.
+    pub fn parse_line(line: &str) -> Result<Option<Self>, ProtocolError> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(None);
+        }
+
+        let (command, args) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let args = args.trim_start();
+        let mut tokens = args.split_whitespace();
+        let mut next = |what: &str| {
+            tokens
+                .next()
+                .ok_or_else(|| ProtocolError::Malformed(format!("missing {} in '{}'", what, line)))
+        };
+        let number = |token: &str| {
+            token
+                .parse::<f64>()
+                .map_err(|_| ProtocolError::Malformed(format!("invalid number '{}'", token)))
+        };
+        let id = |token: &str| {
+            token
+                .parse::<u32>()
+                .map_err(|_| ProtocolError::Malformed(format!("invalid signal id '{}'", token)))
+        };
+
+        let message = match command {
+            "hello" => WireMessage::Hello {
+                namespace: next("namespace")?.to_string(),
+            },
+            "signal" => WireMessage::Signal {
+                id: id(next("signal id")?)?,
+                name: next("signal name")?.to_string(),
+            },
+            "sample" => WireMessage::Samples {
+                id: id(next("signal id")?)?,
+                samples: vec![PlotSignalSample {
+                    time: number(next("time")?)?,
+                    value: number(next("value")?)?,
+                }],
+            },
+            "event" => {
+                let time = next("time")?;
+                WireMessage::Event {
+                    time: number(time)?,
+                    text: args[time.len()..].trim().to_string(),
+                }
+            }
+            "keepalive" => WireMessage::Keepalive,
+            command => return Err(ProtocolError::UnknownCommand(command.to_string())),
+        };
+        Ok(Some(message))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(time: f64, value: f64) -> PlotSignalSample {
+        PlotSignalSample { time, value }
+    }
+
+    fn messages() -> Vec<WireMessage> {
+        vec![
+            WireMessage::Hello {
+                namespace: "bench".to_string(),
+            },
+            WireMessage::Signal {
+                id: 7,
+                name: "motor/speed".to_string(),
+            },
+            WireMessage::Samples {
+                id: 7,
+                samples: vec![sample(0.0, 1200.0), sample(0.01, -1250.5)],
+            },
+            WireMessage::Samples {
+                id: 8,
+                samples: vec![],
+            },
+            WireMessage::Event {
+                time: 0.5,
+                text: "Throttle step".to_string(),
+            },
+            WireMessage::Keepalive,
+        ]
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let mut data = vec![];
+        for message in messages() {
+            message.encode(&mut data);
+        }
+
+        let mut decoded = vec![];
+        let mut pos = 0;
+        while let Some((message, len)) = WireMessage::decode(&data[pos..]) {
+            decoded.push(message.unwrap());
+            pos += len;
+        }
+        assert_eq!(pos, data.len());
+        assert_eq!(decoded, messages());
+    }
+
+    #[test]
+    fn incomplete_and_invalid_frames() {
+        let mut data = vec![];
+        messages()[1].encode(&mut data);
+        for len in 0..data.len() {
+            assert!(WireMessage::decode(&data[..len]).is_none());
+        }
+
+        // Unknown and malformed frames are skipped
+        let frame = [0x7f, 2, 0, 0, 0, 1, 2];
+        assert!(matches!(
+            WireMessage::decode(&frame),
+            Some((Err(ProtocolError::UnknownType(0x7f)), 7))
+        ));
+        let truncated_samples = [TYPE_SAMPLES, 6, 0, 0, 0, 1, 0, 0, 0, 1, 2];
+        assert!(matches!(
+            WireMessage::decode(&truncated_samples),
+            Some((Err(ProtocolError::Malformed(_)), 11))
+        ));
+
+        let mut too_long = vec![TYPE_SAMPLES];
+        too_long.extend_from_slice(&(MAX_FRAME_LEN as u32 + 1).to_le_bytes());
+        assert!(matches!(
+            WireMessage::decode(&too_long),
+            Some((Err(ProtocolError::FrameTooLong(_)), _))
+        ));
+    }
+
+    #[test]
+    fn text_round_trip() {
+        let mut text = String::new();
+        for message in messages() {
+            message.encode_text(&mut text);
+        }
+
+        let decoded: Vec<_> = text
+            .lines()
+            .map(|line| WireMessage::parse_line(line).unwrap().unwrap())
+            .collect();
+        // Samples are sent one per line
+        let expected = vec![
+            messages()[0].clone(),
+            messages()[1].clone(),
+            WireMessage::Samples {
+                id: 7,
+                samples: vec![sample(0.0, 1200.0)],
+            },
+            WireMessage::Samples {
+                id: 7,
+                samples: vec![sample(0.01, -1250.5)],
+            },
+            messages()[4].clone(),
+            messages()[5].clone(),
+        ];
+        assert_eq!(decoded, expected);
+    }
+
+    #[test]
+    fn parse_lines() {
+        assert_eq!(WireMessage::parse_line("").unwrap(), None);
+        assert_eq!(WireMessage::parse_line("  # comment").unwrap(), None);
+        assert_eq!(
+            WireMessage::parse_line("sample 3 1e-3 inf").unwrap(),
+            Some(WireMessage::Samples {
+                id: 3,
+                samples: vec![sample(0.001, f64::INFINITY)],
+            })
+        );
+        assert_eq!(
+            WireMessage::parse_line("event 2  Two  spaces ").unwrap(),
+            Some(WireMessage::Event {
+                time: 2.0,
+                text: "Two  spaces".to_string(),
+            })
+        );
+
+        assert!(matches!(
+            WireMessage::parse_line("bye"),
+            Err(ProtocolError::UnknownCommand(_))
+        ));
+        for line in [
+            "signal 1",
+            "signal x name",
+            "sample 1 2",
+            "sample 1 a 2",
+            "event",
+        ] {
+            assert!(matches!(
+                WireMessage::parse_line(line),
+                Err(ProtocolError::Malformed(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn event_text_is_a_single_line() {
+        let mut text = String::new();
+        WireMessage::Event {
+            time: 1.0,
+            text: "two\nlines".to_string(),
+        }
+        .encode_text(&mut text);
+        assert_eq!(text, "event 1 two lines\n");
+    }
+}
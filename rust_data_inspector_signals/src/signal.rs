@@ -1,16 +1,21 @@
 use std::collections::hash_map::DefaultHasher;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::Display;
 use std::hash::{Hash, Hasher};
+use std::path::Path;
 use std::sync::mpsc::{channel, Receiver, SendError, Sender};
+use std::sync::{Arc, Mutex, MutexGuard};
 
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use crate::recording::{Recorder, RecordingError, RecordingReader};
+
 pub struct PlotSignal {
     id: PlotSignalID,
     name: String,
+    unit: Option<String>,
 
     time: Vec<f64>,
     data: Vec<f64>,
@@ -21,6 +26,7 @@ impl PlotSignal {
         PlotSignal {
             id,
             name,
+            unit: None,
             time: vec![],
             data: vec![],
         }
@@ -34,6 +40,10 @@ impl PlotSignal {
         &self.name
     }
 
+    pub fn unit(&self) -> Option<&str> {
+        self.unit.as_deref()
+    }
+
     pub fn path_elements(&self) -> Vec<&str> {
         self.name.split('/').collect::<Vec<&str>>()
     }
@@ -46,10 +56,51 @@ impl PlotSignal {
         &self.data
     }
 
+    /// Returns the index of the last sample with a timestamp lower or equal to `t`.
+    pub fn index_at(&self, t: f64) -> Option<usize> {
+        self.time.partition_point(|&v| v <= t).checked_sub(1)
+    }
+
+    /// Returns the value of the signal at time `t`, linearly interpolating between the two closest samples.
+    /// Returns `None` if `t` is outside the time span of the signal.
+    pub fn value_at(&self, t: f64) -> Option<f64> {
+        let i = self.index_at(t)?;
+
+        if self.time[i] == t {
+            return Some(self.data[i]);
+        }
+
+        let (t0, t1) = (self.time[i], *self.time.get(i + 1)?);
+        let (y0, y1) = (self.data[i], self.data[i + 1]);
+
+        Some(y0 + (y1 - y0) * (t - t0) / (t1 - t0))
+    }
+
+    /// Returns the index of the sample closest in time to `t`.
+    pub fn nearest_index(&self, t: f64) -> Option<usize> {
+        let next = self.time.partition_point(|&v| v < t);
+
+        match (next.checked_sub(1), self.time.get(next)) {
+            (Some(prev), Some(&next_t)) if t - self.time[prev] <= next_t - t => Some(prev),
+            (_, Some(_)) => Some(next),
+            (prev, None) => prev,
+        }
+    }
+
     pub fn clear(&mut self) {
         self.time.clear();
         self.data.clear();
     }
+
+    fn push(&mut self, sample: PlotSignalSample) {
+        if let Some(&last) = self.time.last() {
+            if sample.time < last {
+                panic!("Received sample in the past!");
+            }
+        }
+        self.time.push(sample.time);
+        self.data.push(sample.value);
+    }
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
@@ -57,12 +108,19 @@ pub struct PlotSignalID {
     id: u64,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct PlotSignalSample {
     pub time: f64,
     pub value: f64,
 }
 
+/// Something that happened at a point in time, like a message logged by the source of the data
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlotEvent {
+    pub time: f64,
+    pub text: String,
+}
+
 impl Display for PlotSignalSample {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}, {}", self.time, self.value)
@@ -73,6 +131,80 @@ impl Display for PlotSignalSample {
 pub struct PlotSignals {
     signals: HashMap<PlotSignalID, PlotSignal>,
     receivers: HashMap<PlotSignalID, Receiver<PlotSignalSample>>,
+
+    frozen: bool,
+    /// Samples received while frozen, not yet added to the signals
+    held: HashMap<PlotSignalID, Vec<PlotSignalSample>>,
+
+    metadata: BTreeMap<String, String>,
+    events: Vec<PlotEvent>,
+    recorder: Option<Recorder>,
+
+    registry: Arc<Mutex<Registry>>,
+}
+
+/// State shared between [`PlotSignals`] and its [`SignalRegistry`]s
+#[derive(Debug, Default)]
+struct Registry {
+    /// Sender of each signal, by name, including the ones not yet added to the [`PlotSignals`]
+    senders: HashMap<String, PlotSampleSender>,
+    /// Signals registered from other threads, added to the [`PlotSignals`] on the next update
+    pending: Vec<(String, PlotSignalID, Receiver<PlotSignalSample>)>,
+    events: Vec<PlotEvent>,
+}
+
+impl Registry {
+    fn add_signal(
+        &mut self,
+        name: &str,
+    ) -> Result<(PlotSignalID, PlotSampleSender, Receiver<PlotSignalSample>), PlotSignalError> {
+        validate_signal_name(name, self.senders.keys())?;
+
+        let id = PlotSignalID {
+            id: PlotSignals::get_name_hash(name),
+        };
+        let (sender, receiver) = channel();
+        let sender = PlotSampleSender { sender, id };
+        self.senders.insert(name.to_string(), sender.clone());
+
+        Ok((id, sender, receiver))
+    }
+}
+
+/// Handle to add signals and events to [`PlotSignals`] from other threads, while they are owned
+/// by someone else, like the viewer. Signals are added on the next [`PlotSignals::update`].
+///
+/// Useful for sources whose signals are only known once data is received, like network streams.
+#[derive(Debug, Clone)]
+pub struct SignalRegistry {
+    registry: Arc<Mutex<Registry>>,
+}
+
+impl SignalRegistry {
+    /// Returns the sample producer of the signal `name`, creating the signal if needed.
+    /// Names follow the same rules as [`PlotSignals::add_signal`].
+    pub fn add_signal(&self, name: &str) -> Result<PlotSampleSender, PlotSignalError> {
+        let mut registry = lock(&self.registry);
+        if let Some(sender) = registry.senders.get(name) {
+            return Ok(sender.clone());
+        }
+
+        let (id, sender, receiver) = registry.add_signal(name)?;
+        registry.pending.push((name.to_string(), id, receiver));
+        Ok(sender)
+    }
+
+    /// Adds an event, like a message logged by the source of the data
+    pub fn add_event(&self, time: f64, text: &str) {
+        lock(&self.registry).events.push(PlotEvent {
+            time,
+            text: text.to_string(),
+        });
+    }
+}
+
+fn lock(registry: &Mutex<Registry>) -> MutexGuard<'_, Registry> {
+    registry.lock().unwrap_or_else(|e| e.into_inner())
 }
 
 impl PlotSignals {
@@ -106,13 +238,7 @@ impl PlotSignals {
         &mut self,
         name: &str,
     ) -> Result<(PlotSignalID, PlotSampleSender), PlotSignalError> {
-        self.validate_name(name)?;
-
-        let id = PlotSignalID {
-            id: Self::get_name_hash(name),
-        };
-
-        let (sender, receiver) = channel();
+        let (id, sender, receiver) = lock(&self.registry).add_signal(name)?;
 
         if self.signals.contains_key(&id) {
             panic!("Signal ID hash collision");
@@ -122,23 +248,217 @@ impl PlotSignals {
             .insert(id, PlotSignal::new(name.to_string(), id));
         self.receivers.insert(id, receiver);
 
-        Ok((id, PlotSampleSender { sender, id }))
+        if let Some(recorder) = &mut self.recorder {
+            recorder.add_signal(id, name);
+        }
+
+        Ok((id, sender))
+    }
+
+    /// Returns a handle to add signals and events from other threads
+    pub fn registry(&self) -> SignalRegistry {
+        SignalRegistry {
+            registry: self.registry.clone(),
+        }
     }
 
     pub fn update(&mut self) {
+        let (pending, events) = {
+            let mut registry = lock(&self.registry);
+            (
+                std::mem::take(&mut registry.pending),
+                std::mem::take(&mut registry.events),
+            )
+        };
+        for (name, id, receiver) in pending {
+            if self.signals.contains_key(&id) {
+                panic!("Signal ID hash collision");
+            }
+            self.signals.insert(id, PlotSignal::new(name.clone(), id));
+            self.receivers.insert(id, receiver);
+
+            if let Some(recorder) = &mut self.recorder {
+                recorder.add_signal(id, &name);
+            }
+        }
+        self.events.extend(events);
+
         for (id, signal) in self.signals.iter_mut() {
             let receiver = self.receivers.get(id).unwrap();
+            let samples: Vec<_> = receiver.try_iter().collect();
+
+            if let Some(recorder) = &mut self.recorder {
+                recorder.write_samples(*id, &samples);
+            }
 
-            while let Ok(sample) = receiver.try_recv() {
-                if let Some(&last) = signal.time.last() {
-                    if sample.time < last {
-                        panic!("Received sample in the past!");
-                    }
+            if self.frozen {
+                self.held.entry(*id).or_default().extend(samples);
+            } else {
+                for sample in samples {
+                    signal.push(sample);
                 }
-                signal.time.push(sample.time);
-                signal.data.push(sample.value);
             }
         }
+
+        if let Some(recorder) = &mut self.recorder {
+            recorder.end_chunk();
+        }
+    }
+
+    /// Stops adding received samples to the signals, so that they can be inspected while data
+    /// keeps being received. Samples are held until [`PlotSignals::unfreeze`] is called.
+    pub fn freeze(&mut self) {
+        self.frozen = true;
+    }
+
+    /// Adds the samples held while frozen to the signals and resumes updating them
+    pub fn unfreeze(&mut self) {
+        self.frozen = false;
+
+        for (id, samples) in self.held.drain() {
+            if let Some(signal) = self.signals.get_mut(&id) {
+                for sample in samples {
+                    signal.push(sample);
+                }
+            }
+        }
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
+    /// Number of samples received while frozen
+    pub fn held_samples(&self) -> usize {
+        self.held.values().map(|v| v.len()).sum()
+    }
+
+    /// Sets the unit of measurement of the signal, shown next to its values
+    pub fn set_signal_unit(&mut self, id: PlotSignalID, unit: &str) {
+        if let Some(signal) = self.signals.get_mut(&id) {
+            signal.unit = Some(unit.to_string());
+
+            if let Some(recorder) = &mut self.recorder {
+                recorder.set_unit(id, unit);
+            }
+        }
+    }
+
+    /// Sets a metadata entry describing the session, such as the source of the data
+    pub fn set_metadata(&mut self, key: &str, value: &str) {
+        self.metadata.insert(key.to_string(), value.to_string());
+
+        if let Some(recorder) = &mut self.recorder {
+            recorder.set_metadata(key, value);
+        }
+    }
+
+    pub fn metadata(&self) -> &BTreeMap<String, String> {
+        &self.metadata
+    }
+
+    /// Adds an event, like a message logged by the source of the data
+    pub fn add_event(&mut self, time: f64, text: &str) {
+        self.events.push(PlotEvent {
+            time,
+            text: text.to_string(),
+        });
+    }
+
+    pub fn events(&self) -> &[PlotEvent] {
+        &self.events
+    }
+
+    /// Starts writing all signals, metadata and samples to a recording file at `path`,
+    /// including the ones received before this call.
+    /// The recording can be opened with [`PlotSignals::from_recording`].
+    pub fn start_recording(&mut self, path: impl AsRef<Path>) -> Result<(), RecordingError> {
+        let mut recorder = Recorder::create(path)?;
+
+        for (key, value) in self.metadata.iter() {
+            recorder.set_metadata(key, value);
+        }
+
+        for (id, signal) in self.signals.iter() {
+            recorder.add_signal(*id, signal.name());
+            if let Some(unit) = signal.unit() {
+                recorder.set_unit(*id, unit);
+            }
+        }
+
+        // Previously received samples
+        for (id, signal) in self.signals.iter() {
+            let samples: Vec<_> = signal
+                .time
+                .iter()
+                .zip(signal.data.iter())
+                .map(|(&time, &value)| PlotSignalSample { time, value })
+                .collect();
+            recorder.write_samples(*id, &samples);
+        }
+        recorder.end_chunk();
+
+        for (id, samples) in self.held.iter() {
+            recorder.write_samples(*id, samples);
+        }
+        recorder.end_chunk();
+
+        if let Some(error) = recorder.error() {
+            return Err(RecordingError::Io(std::io::Error::other(error.to_string())));
+        }
+
+        self.recorder = Some(recorder);
+        Ok(())
+    }
+
+    /// Stops the recording, if any, flushing it to disk
+    pub fn stop_recording(&mut self) {
+        self.recorder = None;
+    }
+
+    pub fn recorder(&self) -> Option<&Recorder> {
+        self.recorder.as_ref()
+    }
+
+    /// Loads all the signals, metadata and samples stored in a recording.
+    /// Recordings truncated by a crash are loaded up to their last valid record.
+    pub fn from_recording(path: impl AsRef<Path>) -> Result<Self, RecordingError> {
+        let mut reader = RecordingReader::open(path)?;
+        let samples = reader.read_all()?;
+
+        let mut signals = PlotSignals::default();
+        for (key, value) in reader.metadata() {
+            signals.set_metadata(key, value);
+        }
+
+        for (recorded, samples) in reader.signals().iter().zip(samples) {
+            let (id, _) = signals.add_signal(&recorded.name)?;
+            if let Some(unit) = &recorded.unit {
+                signals.set_signal_unit(id, unit);
+            }
+
+            // Chunks of a recording may overlap, and a corrupted file may have any time
+            signals.push_samples(id, samples);
+        }
+
+        Ok(signals)
+    }
+
+    /// Adds a sample to a signal without going through its channel, for loaders
+    pub(crate) fn push_sample(&mut self, id: PlotSignalID, sample: PlotSignalSample) {
+        if let Some(signal) = self.signals.get_mut(&id) {
+            signal.push(sample);
+        }
+    }
+
+    /// Adds samples of a signal that may not be in order, sorting them by time
+    pub(crate) fn push_samples(&mut self, id: PlotSignalID, mut samples: Vec<PlotSignalSample>) {
+        if !samples.is_sorted_by(|a, b| a.time <= b.time) {
+            samples.sort_by(|a, b| a.time.total_cmp(&b.time));
+        }
+        for sample in samples {
+            self.push_sample(id, sample);
+        }
     }
 
     fn get_name_hash(name: &str) -> u64 {
@@ -147,56 +467,63 @@ impl PlotSignals {
         hasher.finish()
     }
 
+    /// Removes all the samples and events, including the samples received but not yet added to the signals
     pub fn clear_timeseries(&mut self) {
         for sig in self.signals.values_mut() {
             sig.clear();
         }
+        for receiver in self.receivers.values() {
+            receiver.try_iter().for_each(drop);
+        }
+        self.held.clear();
+        self.events.clear();
     }
-
 }
 
-impl PlotSignals {
-    fn validate_name(&self, name: &str) -> Result<(), PlotSignalError> {
-        if !name.starts_with('/') {
-            return Err(PlotSignalError::NameError {
-                name: name.to_string(),
-                msg: "Signal name must start with a `/`.".to_string(),
-            });
-        }
+/// Checks that `name` is a valid signal name, and that it doesn't conflict with the `existing`
+/// ones: a signal can't be a path prefix of another one.
+pub fn validate_signal_name<'a>(
+    name: &str,
+    existing: impl Iterator<Item = &'a String>,
+) -> Result<(), PlotSignalError> {
+    if !name.starts_with('/') {
+        return Err(PlotSignalError::NameError {
+            name: name.to_string(),
+            msg: "Signal name must start with a `/`.".to_string(),
+        });
+    }
 
-        if name.contains("//") {
-            return Err(PlotSignalError::NameError {
-                name: name.to_string(),
-                msg: "Signal name must not contain two or more consecutive `/`.".to_string(),
-            });
-        }
+    if name.contains("//") {
+        return Err(PlotSignalError::NameError {
+            name: name.to_string(),
+            msg: "Signal name must not contain two or more consecutive `/`.".to_string(),
+        });
+    }
 
-        let regex = Regex::new(r"^[\w\/]+$").unwrap();
+    let regex = Regex::new(r"^[\w\/]+$").unwrap();
 
-        if !regex.is_match(name) {
+    if !regex.is_match(name) {
+        return Err(PlotSignalError::NameError {
+            name: name.to_string(),
+            msg: "Signal name must contain only letters, numbers, underscore or `/`.".to_string(),
+        });
+    }
+
+    for sig_name in existing {
+        let is_subsignal = sig_name
+            .split("/")
+            .zip(name.split("/"))
+            .all(|(a, b)| a == b);
+
+        if is_subsignal {
             return Err(PlotSignalError::NameError {
                 name: name.to_string(),
-                msg: "Signal name must contain only letters, numbers, underscore or `/`."
-                    .to_string(),
+                msg: format!("Provided signal name is a subsignal of {sig_name}").to_string(),
             });
         }
-
-        for sig_name in self.signals.values().map(|sig| &sig.name) {
-            let is_subsignal = sig_name
-                .split("/")
-                .zip(name.split("/"))
-                .all(|(a, b)| a == b);
-
-            if is_subsignal {
-                return Err(PlotSignalError::NameError {
-                    name: name.to_string(),
-                    msg: format!("Provided signal name is a subsignal of {sig_name}").to_string(),
-                });
-            }
-        }
-
-        Ok(())
     }
+
+    Ok(())
 }
 
 #[derive(Debug, Error)]
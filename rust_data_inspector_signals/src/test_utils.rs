@@ -0,0 +1,84 @@
+//! Helpers shared by the tests of the crate
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{PlotSignalID, PlotSignalSample, PlotSignals};
+
+/// Path in the temporary directory, unique to each instance.
+/// The file or directory created at the path is removed on drop.
+pub struct TempPath {
+    path: PathBuf,
+}
+
+impl TempPath {
+    pub fn new(extension: &str) -> Self {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+        let count = COUNT.fetch_add(1, Ordering::Relaxed);
+        let name = format!("rdi_test_{}_{}.{}", std::process::id(), count, extension);
+        TempPath {
+            path: std::env::temp_dir().join(name),
+        }
+    }
+
+    /// Creates a file with the given content
+    pub fn with_data(extension: &str, data: &[u8]) -> Self {
+        let file = TempPath::new(extension);
+        std::fs::write(&file.path, data).unwrap();
+        file
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        let _ = if self.path.is_dir() {
+            std::fs::remove_dir_all(&self.path)
+        } else {
+            std::fs::remove_file(&self.path)
+        };
+    }
+}
+
+/// Signals with the given names and `(time, value)` samples, and their ids in the same order
+pub fn signals_with(data: &[(&str, &[(f64, f64)])]) -> (PlotSignals, Vec<PlotSignalID>) {
+    let mut signals = PlotSignals::default();
+    let mut ids = vec![];
+    for (name, samples) in data {
+        let (id, sender) = signals.add_signal(name).unwrap();
+        for &(time, value) in *samples {
+            sender.send(PlotSignalSample { time, value }).unwrap();
+        }
+        ids.push(id);
+    }
+    signals.update();
+    (signals, ids)
+}
+
+/// Waits for `condition` to be true, checking it every millisecond. Panics after 5 seconds.
+pub fn wait_until(mut condition: impl FnMut() -> bool) {
+    let start = Instant::now();
+    while !condition() {
+        assert!(start.elapsed() < Duration::from_secs(5), "timed out");
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+/// Samples of the signal `name` as `(time, value)`, if it exists
+pub fn samples_of(signals: &PlotSignals, name: &str) -> Option<Vec<(f64, f64)>> {
+    let signal = signals.get_signals().values().find(|s| s.name() == name)?;
+    Some(
+        signal
+            .time()
+            .iter()
+            .copied()
+            .zip(signal.data().iter().copied())
+            .collect(),
+    )
+}
@@ -0,0 +1,572 @@
+//! Import of PX4 ULog files, see https://docs.px4.io/main/en/dev_log/ulog_file_format.html
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use thiserror::Error;
+
+use crate::{PlotSignalError, PlotSignalID, PlotSignalSample, PlotSignals};
+
+const MAGIC: &[u8; 7] = b"ULog\x01\x12\x35";
+const HEADER_LEN: usize = 16;
+
+/// Data messages have a 16 bit size, so formats can not be larger
+const MAX_FORMAT_SIZE: usize = u16::MAX as usize;
+
+/// Incompatible flag bit signaling that data was appended to the log after it was closed
+const INCOMPAT_DATA_APPENDED: u8 = 1;
+
+#[derive(Debug, Error)]
+pub enum UlogError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Malformed ULog file: {0}")]
+    Malformed(String),
+    #[error("Unsupported ULog file: {0}")]
+    Unsupported(String),
+    #[error("Invalid signal: {0}")]
+    Signal(#[from] PlotSignalError),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Primitive {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    I64,
+    U64,
+    F32,
+    F64,
+    Bool,
+    Char,
+}
+
+impl Primitive {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "int8_t" => Primitive::I8,
+            "uint8_t" => Primitive::U8,
+            "int16_t" => Primitive::I16,
+            "uint16_t" => Primitive::U16,
+            "int32_t" => Primitive::I32,
+            "uint32_t" => Primitive::U32,
+            "int64_t" => Primitive::I64,
+            "uint64_t" => Primitive::U64,
+            "float" => Primitive::F32,
+            "double" => Primitive::F64,
+            "bool" => Primitive::Bool,
+            "char" => Primitive::Char,
+            _ => return None,
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Primitive::I8 | Primitive::U8 | Primitive::Bool | Primitive::Char => 1,
+            Primitive::I16 | Primitive::U16 => 2,
+            Primitive::I32 | Primitive::U32 | Primitive::F32 => 4,
+            Primitive::I64 | Primitive::U64 | Primitive::F64 => 8,
+        }
+    }
+
+    /// Reads a value from the start of `bytes`, which must be at least `self.size()` long
+    fn read(self, bytes: &[u8]) -> f64 {
+        match self {
+            Primitive::I8 => bytes[0] as i8 as f64,
+            Primitive::U8 | Primitive::Char => bytes[0] as f64,
+            Primitive::Bool => (bytes[0] != 0) as u8 as f64,
+            Primitive::I16 => i16::from_le_bytes(le(bytes)) as f64,
+            Primitive::U16 => u16::from_le_bytes(le(bytes)) as f64,
+            Primitive::I32 => i32::from_le_bytes(le(bytes)) as f64,
+            Primitive::U32 => u32::from_le_bytes(le(bytes)) as f64,
+            Primitive::I64 => i64::from_le_bytes(le(bytes)) as f64,
+            Primitive::U64 => u64::from_le_bytes(le(bytes)) as f64,
+            Primitive::F32 => f32::from_le_bytes(le(bytes)) as f64,
+            Primitive::F64 => f64::from_le_bytes(le(bytes)),
+        }
+    }
+}
+
+fn le<const N: usize>(bytes: &[u8]) -> [u8; N] {
+    bytes[..N].try_into().unwrap()
+}
+
+/// Field of a format, like `float[4] q`
+#[derive(Debug)]
+struct FormatField {
+    ty: String,
+    array: Option<usize>,
+    name: String,
+}
+
+/// Parses a field type and name, like `float[4] q` or `char[40] sys_name`
+fn parse_field(field: &str) -> Result<FormatField, UlogError> {
+    let (ty, name) = field
+        .trim()
+        .split_once(' ')
+        .ok_or_else(|| UlogError::Malformed(format!("invalid field '{}'", field)))?;
+    let (ty, array) = match ty.split_once('[') {
+        Some((ty, len)) => {
+            let len = len
+                .trim_end_matches(']')
+                .parse()
+                .map_err(|_| UlogError::Malformed(format!("invalid array size of '{}'", name)))?;
+            (ty, Some(len))
+        }
+        None => (ty, None),
+    };
+    Ok(FormatField {
+        ty: ty.to_string(),
+        array,
+        name: name.trim().to_string(),
+    })
+}
+
+/// Numeric value of a message, at `offset` bytes from the start of its data
+#[derive(Debug)]
+struct Leaf {
+    path: String,
+    offset: usize,
+    ty: Primitive,
+}
+
+/// Flattens the numeric fields of the format `name` into `out`, returning the size of the format
+fn flatten(
+    formats: &HashMap<String, Vec<FormatField>>,
+    name: &str,
+    prefix: &str,
+    offset: usize,
+    depth: usize,
+    out: &mut Vec<Leaf>,
+) -> Result<usize, UlogError> {
+    if depth > 16 {
+        return Err(UlogError::Malformed(format!(
+            "format '{}' is recursive",
+            name
+        )));
+    }
+    let fields = formats
+        .get(name)
+        .ok_or_else(|| UlogError::Malformed(format!("format '{}' not defined", name)))?;
+
+    let mut size = 0;
+    for field in fields {
+        let padding = field.name.starts_with("_padding");
+        let count = field.array.unwrap_or(1);
+        for i in 0..count {
+            let path = match field.array {
+                Some(_) => format!("{}/{}/{}", prefix, field.name, i),
+                None => format!("{}/{}", prefix, field.name),
+            };
+            let len = match Primitive::parse(&field.ty) {
+                Some(ty) => {
+                    // Strings and padding are not signals
+                    if !padding && !matches!(ty, Primitive::Char) {
+                        out.push(Leaf {
+                            path,
+                            offset: offset + size,
+                            ty,
+                        });
+                    }
+                    ty.size()
+                }
+                None => {
+                    let mut nested = vec![];
+                    let len = flatten(
+                        formats,
+                        &field.ty,
+                        &path,
+                        offset + size,
+                        depth + 1,
+                        &mut nested,
+                    )?;
+                    if !padding {
+                        out.extend(nested);
+                    }
+                    len
+                }
+            };
+            size += len;
+            if offset + size > MAX_FORMAT_SIZE {
+                return Err(UlogError::Malformed(format!(
+                    "format '{}' is too large",
+                    name
+                )));
+            }
+            // Elements of empty formats have no fields
+            if len == 0 {
+                break;
+            }
+        }
+    }
+    Ok(size)
+}
+
+/// Formats a value of an info or parameter message as text
+fn format_value(field: &FormatField, value: &[u8]) -> String {
+    match (Primitive::parse(&field.ty), field.array) {
+        (Some(Primitive::Char), _) => String::from_utf8_lossy(value)
+            .trim_end_matches('\0')
+            .to_string(),
+        (Some(ty), None) if value.len() >= ty.size() => ty.read(value).to_string(),
+        _ => format!("{:?}", value),
+    }
+}
+
+fn log_level_name(level: u8) -> &'static str {
+    match level {
+        b'0' => "EMERGENCY",
+        b'1' => "ALERT",
+        b'2' => "CRITICAL",
+        b'3' => "ERROR",
+        b'4' => "WARNING",
+        b'5' => "NOTICE",
+        b'6' => "INFO",
+        _ => "DEBUG",
+    }
+}
+
+/// Iterates over the type and content of the messages of a log
+#[derive(Clone)]
+struct Messages<'a> {
+    data: &'a [u8],
+    pos: usize,
+    /// Offsets of data appended to the log, which may follow a partially written message
+    appended: Vec<usize>,
+}
+
+impl<'a> Iterator for Messages<'a> {
+    type Item = (u8, &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            while self.appended.first().is_some_and(|&a| a <= self.pos) {
+                self.appended.remove(0);
+            }
+            let header_end = self.pos + 3;
+            if header_end > self.data.len() {
+                return None;
+            }
+            let size = u16::from_le_bytes([self.data[self.pos], self.data[self.pos + 1]]) as usize;
+            let msg_type = self.data[self.pos + 2];
+            let end = header_end + size;
+
+            // Skip to the appended data if the message overlaps it
+            if let Some(&appended) = self.appended.first() {
+                if end > appended {
+                    self.pos = appended;
+                    continue;
+                }
+            }
+            // The last message of logs that were not closed may be truncated
+            if end > self.data.len() {
+                return None;
+            }
+
+            self.pos = end;
+            return Some((msg_type, &self.data[header_end..end]));
+        }
+    }
+}
+
+/// Message subscribed with an `A` message
+struct Subscription {
+    /// Offset of the timestamp in the data
+    timestamp: usize,
+    leaves: Vec<(usize, Primitive, PlotSignalID)>,
+}
+
+impl PlotSignals {
+    /// Loads a PX4 ULog file. Each field of each logged topic becomes a signal like
+    /// `/vehicle_attitude/q/0`, with the multi-id after the topic for topics logged more than once,
+    /// like `/sensor_accel/1/x`. Samples are timestamped in seconds with microsecond resolution.
+    /// Logged text messages become events, and parameters and info messages become metadata,
+    /// like `param/MC_ROLL_P` and `info/sys_name`.
+    pub fn from_ulog(path: impl AsRef<Path>) -> Result<Self, UlogError> {
+        let data = std::fs::read(path)?;
+        if data.len() < HEADER_LEN || &data[..MAGIC.len()] != MAGIC {
+            return Err(UlogError::Malformed("not a ULog file".to_string()));
+        }
+
+        let mut messages = Messages {
+            data: &data,
+            pos: HEADER_LEN,
+            appended: vec![],
+        };
+
+        // The flag bits message, if present, must be the first one
+        if let Some((b'B', flags)) = messages.clone().next() {
+            if flags.len() < 40 {
+                return Err(UlogError::Malformed(
+                    "flag bits message is truncated".to_string(),
+                ));
+            }
+            let incompat = &flags[8..16];
+            if incompat[0] & !INCOMPAT_DATA_APPENDED != 0 || incompat[1..].iter().any(|&b| b != 0) {
+                return Err(UlogError::Unsupported(
+                    "unknown incompatible flags".to_string(),
+                ));
+            }
+            messages.appended = flags[16..40]
+                .chunks(8)
+                .map(|b| u64::from_le_bytes(b.try_into().unwrap()) as usize)
+                .filter(|&offset| offset != 0)
+                .collect();
+        }
+
+        // Topics logged with more than one multi-id get the id in their path
+        let mut formats = HashMap::new();
+        let mut multi_instance = HashSet::new();
+        for (msg_type, content) in messages.clone() {
+            match msg_type {
+                b'F' => {
+                    let format = String::from_utf8_lossy(content);
+                    let (name, fields) = format.split_once(':').ok_or_else(|| {
+                        UlogError::Malformed(format!("invalid format '{}'", format))
+                    })?;
+                    let fields = fields
+                        .split(';')
+                        .filter(|f| !f.trim().is_empty())
+                        .map(parse_field)
+                        .collect::<Result<Vec<_>, _>>()?;
+                    formats.insert(name.to_string(), fields);
+                }
+                b'A' if content.len() > 3 && content[0] != 0 => {
+                    multi_instance.insert(String::from_utf8_lossy(&content[3..]).to_string());
+                }
+                _ => {}
+            }
+        }
+
+        let mut signals = PlotSignals::default();
+        let mut subscriptions: HashMap<u16, Subscription> = HashMap::new();
+        let mut samples: HashMap<PlotSignalID, Vec<PlotSignalSample>> = HashMap::new();
+        let mut ids: HashMap<String, PlotSignalID> = HashMap::new();
+
+        for (msg_type, content) in messages {
+            match msg_type {
+                b'A' => {
+                    if content.len() < 3 {
+                        return Err(UlogError::Malformed(
+                            "subscription is truncated".to_string(),
+                        ));
+                    }
+                    let multi_id = content[0];
+                    let msg_id = u16::from_le_bytes([content[1], content[2]]);
+                    let name = String::from_utf8_lossy(&content[3..]).to_string();
+
+                    let topic = if multi_instance.contains(&name) {
+                        format!("/{}/{}", name, multi_id)
+                    } else {
+                        format!("/{}", name)
+                    };
+                    let mut leaves = vec![];
+                    flatten(&formats, &name, &topic, 0, 0, &mut leaves)?;
+
+                    // Topics without timestamp can not be plotted
+                    let timestamp_path = format!("{}/timestamp", topic);
+                    let Some(timestamp) = leaves.iter().position(|l| l.path == timestamp_path)
+                    else {
+                        continue;
+                    };
+                    let timestamp = leaves.remove(timestamp).offset;
+
+                    let mut subscription = Subscription {
+                        timestamp,
+                        leaves: vec![],
+                    };
+                    for leaf in leaves {
+                        let id = match ids.get(&leaf.path) {
+                            Some(&id) => id,
+                            None => {
+                                let (id, _) = signals.add_signal(&leaf.path)?;
+                                ids.insert(leaf.path, id);
+                                id
+                            }
+                        };
+                        subscription.leaves.push((leaf.offset, leaf.ty, id));
+                    }
+                    subscriptions.insert(msg_id, subscription);
+                }
+                b'R' if content.len() >= 2 => {
+                    subscriptions.remove(&u16::from_le_bytes([content[0], content[1]]));
+                }
+                b'D' if content.len() >= 2 => {
+                    let msg_id = u16::from_le_bytes([content[0], content[1]]);
+                    let Some(subscription) = subscriptions.get(&msg_id) else {
+                        continue;
+                    };
+                    let data = &content[2..];
+                    let size = |offset: usize, ty: Primitive| offset + ty.size();
+                    if data.len() < size(subscription.timestamp, Primitive::U64)
+                        || subscription
+                            .leaves
+                            .iter()
+                            .any(|&(offset, ty, _)| data.len() < size(offset, ty))
+                    {
+                        return Err(UlogError::Malformed(format!(
+                            "data of message {} is truncated",
+                            msg_id
+                        )));
+                    }
+
+                    let time = Primitive::U64.read(&data[subscription.timestamp..]) * 1e-6;
+                    for &(offset, ty, id) in &subscription.leaves {
+                        samples.entry(id).or_default().push(PlotSignalSample {
+                            time,
+                            value: ty.read(&data[offset..]),
+                        });
+                    }
+                }
+                b'L' | b'C' => {
+                    // Tagged messages have a tag after the level
+                    let start = if msg_type == b'C' { 3 } else { 1 };
+                    if content.len() < start + 8 {
+                        continue;
+                    }
+                    let time = Primitive::U64.read(&content[start..]) * 1e-6;
+                    let text = String::from_utf8_lossy(&content[start + 8..]);
+                    signals.add_event(
+                        time,
+                        &format!(
+                            "{}: {}",
+                            log_level_name(content[0]),
+                            text.trim_end_matches('\0')
+                        ),
+                    );
+                }
+                b'I' | b'P' if !content.is_empty() => {
+                    let key_len = content[0] as usize;
+                    if content.len() < 1 + key_len {
+                        continue;
+                    }
+                    let field = parse_field(&String::from_utf8_lossy(&content[1..1 + key_len]))?;
+                    let value = format_value(&field, &content[1 + key_len..]);
+                    let prefix = if msg_type == b'P' { "param" } else { "info" };
+                    signals.set_metadata(&format!("{}/{}", prefix, field.name), &value);
+                }
+                _ => {}
+            }
+        }
+
+        for (id, samples) in samples {
+            signals.push_samples(id, samples);
+        }
+        Ok(signals)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TempPath;
+    use crate::PlotEvent;
+
+    fn message(msg_type: u8, content: &[u8]) -> Vec<u8> {
+        let mut data = (content.len() as u16).to_le_bytes().to_vec();
+        data.push(msg_type);
+        data.extend_from_slice(content);
+        data
+    }
+
+    fn format(format: &str) -> Vec<u8> {
+        message(b'F', format.as_bytes())
+    }
+
+    fn subscription(msg_id: u16, name: &str) -> Vec<u8> {
+        let mut content = vec![0];
+        content.extend_from_slice(&msg_id.to_le_bytes());
+        content.extend_from_slice(name.as_bytes());
+        message(b'A', &content)
+    }
+
+    /// Writes a log with the given messages to a temporary file and loads it
+    fn load(messages: &[Vec<u8>]) -> Result<PlotSignals, UlogError> {
+        let mut data = MAGIC.to_vec();
+        data.push(1);
+        data.extend_from_slice(&0u64.to_le_bytes());
+        data.extend(messages.concat());
+
+        let file = TempPath::with_data("ulg", &data);
+        PlotSignals::from_ulog(file.path())
+    }
+
+    fn data(msg_id: u16, timestamp: u64, x: [f32; 2]) -> Vec<u8> {
+        let mut content = msg_id.to_le_bytes().to_vec();
+        content.extend_from_slice(&timestamp.to_le_bytes());
+        content.extend_from_slice(&x[0].to_le_bytes());
+        content.extend_from_slice(&[0; 3]);
+        content.extend_from_slice(&x[1].to_le_bytes());
+        message(b'D', &content)
+    }
+
+    #[test]
+    fn small_log() {
+        let mut log = vec![b'6'];
+        log.extend_from_slice(&1_500_000u64.to_le_bytes());
+        log.extend_from_slice(b"armed");
+        let mut param = vec![7];
+        param.extend_from_slice(b"float p");
+        param.extend_from_slice(&0.5f32.to_le_bytes());
+
+        let signals = load(&[
+            format("pair:float a;uint8_t[3] _padding0;float b"),
+            format("sensor:uint64_t timestamp;pair x"),
+            subscription(3, "sensor"),
+            data(3, 1_000_000, [1.0, 2.0]),
+            message(b'L', &log),
+            data(3, 2_000_000, [3.0, 4.0]),
+            message(b'P', &param),
+        ])
+        .unwrap();
+
+        let mut names: Vec<_> = signals.get_signals().values().map(|s| s.name()).collect();
+        names.sort();
+        assert_eq!(names, ["/sensor/x/a", "/sensor/x/b"]);
+        let b = signals
+            .get_signals()
+            .values()
+            .find(|s| s.name() == "/sensor/x/b")
+            .unwrap();
+        assert_eq!(*b.time(), [1.0, 2.0]);
+        assert_eq!(*b.data(), [2.0, 4.0]);
+
+        assert_eq!(
+            signals.events(),
+            [PlotEvent {
+                time: 1.5,
+                text: "INFO: armed".to_string()
+            }]
+        );
+        assert_eq!(signals.metadata()["param/p"], "0.5");
+    }
+
+    #[test]
+    fn oversized_formats() {
+        let large_array = [
+            format("sensor:uint64_t timestamp;float[4000000000] x"),
+            subscription(1, "sensor"),
+        ];
+        // Nested arrays multiply their lengths
+        let nested_arrays = [
+            format("inner:uint8_t[60000] v"),
+            format("outer:inner[60000] y"),
+            format("sensor:uint64_t timestamp;outer[60000] x"),
+            subscription(1, "sensor"),
+        ];
+        for (name, messages) in [("large", &large_array[..]), ("nested", &nested_arrays[..])] {
+            let result = load(messages);
+            assert!(matches!(result, Err(UlogError::Malformed(_))), "{}", name);
+        }
+
+        let empty_array = [
+            format("empty:"),
+            format("sensor:uint64_t timestamp;empty[4000000000] x"),
+            subscription(1, "sensor"),
+        ];
+        assert!(load(&empty_array).is_ok());
+    }
+}
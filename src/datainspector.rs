@@ -1,13 +1,26 @@
 use crate::framehistory::FrameHistory;
+use crate::layout::annotations::BookmarksUI;
+use crate::layout::export::{ExportDialog, ExportSource};
+use crate::layout::filedialog::{FileDialog, FileDialogKind};
+use crate::layout::layoutfile::load_layout;
+use crate::layout::replay::ReplayUI;
 use crate::layout::signallist::SignalListUI;
 use crate::layout::tabs::{Tab, TabViewer};
-use crate::state::{DataInspectorState, SignalData, TabState, XAxisMode};
+use crate::layout::template::TemplateEditor;
+use crate::state::{
+    Annotation, AnnotationKind, DataInspectorState, SignalData, TabState, XAxisMode,
+};
 use crate::utils::downsampling::DownsamplingMethod;
 use eframe::NativeOptions;
-use egui_dock::{DockArea, Style};
-use rust_data_inspector_signals::PlotSignals;
+use egui_dock::{DockArea, Node, Style};
+use rust_data_inspector_signals::{PlotSignals, ReplayHandle};
 
 use egui::Frame;
+use std::path::PathBuf;
+
+const FROZEN_COLOR: egui::Color32 = egui::Color32::from_rgb(100, 180, 255);
+const RECORDING_COLOR: egui::Color32 = egui::Color32::from_rgb(230, 60, 60);
+const EVENT_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 190, 70);
 
 pub struct DataInspector {
     signals: SignalData,
@@ -17,6 +30,23 @@ pub struct DataInspector {
     reset: bool,
     clear_timeseries: bool,
     custom_buttons: Option<Box<dyn FnMut(&mut egui::Ui, &mut DataInspectorAPI)>>,
+    file_dialog: Option<FileDialog>,
+    export_dialog: Option<ExportDialog>,
+    template_editor: TemplateEditor,
+    replay: Option<ReplayHandle>,
+    /// Number of events of the signals already shown as annotations
+    shown_events: usize,
+}
+
+/// Startup options of the data inspector
+#[derive(Debug, Default, Clone)]
+pub struct DataInspectorOptions {
+    /// Layout file to load at startup, replacing the stored one
+    pub layout: Option<PathBuf>,
+    /// Layout template to load at startup, replacing the stored one
+    pub template: Option<PathBuf>,
+    /// Replay feeding the signals, controlled from a timeline at the bottom of the window
+    pub replay: Option<ReplayHandle>,
 }
 
 #[derive(Debug, Default)]
@@ -35,6 +65,20 @@ impl DataInspector {
         app_name: &str,
         signals: PlotSignals,
         custom_buttons: Option<impl FnMut(&mut egui::Ui, &mut DataInspectorAPI) + 'static>,
+    ) -> Result<(), eframe::Error> {
+        Self::run_native_with_options(
+            app_name,
+            signals,
+            custom_buttons,
+            DataInspectorOptions::default(),
+        )
+    }
+
+    pub fn run_native_with_options(
+        app_name: &str,
+        signals: PlotSignals,
+        custom_buttons: Option<impl FnMut(&mut egui::Ui, &mut DataInspectorAPI) + 'static>,
+        options: DataInspectorOptions,
     ) -> Result<(), eframe::Error> {
         eframe::run_native(
             app_name,
@@ -43,9 +87,10 @@ impl DataInspector {
                 Ok(Box::new(DataInspector::run(
                     cc,
                     signals,
-                    custom_buttons.map(|f| -> Box<dyn FnMut(&mut egui::Ui, &mut DataInspectorAPI)> {
-                        Box::new(f)
-                    }),
+                    custom_buttons.map(
+                        |f| -> Box<dyn FnMut(&mut egui::Ui, &mut DataInspectorAPI)> { Box::new(f) },
+                    ),
+                    options,
                 )))
             }),
         )
@@ -57,9 +102,10 @@ impl DataInspector {
         cc: &eframe::CreationContext<'_>,
         signals: PlotSignals,
         custom_buttons: Option<Box<dyn FnMut(&mut egui::Ui, &mut DataInspectorAPI)>>,
+        options: DataInspectorOptions,
     ) -> Self {
         // Load from storage, if available
-        let (state, tab_state) = if let Some(storage) = cc.storage {
+        let (mut state, mut tab_state) = if let Some(storage) = cc.storage {
             let state = DataInspectorState::from_storage(storage, &signals);
             let tab_state = eframe::get_value::<TabState>(storage, "tab_state");
 
@@ -78,6 +124,29 @@ impl DataInspector {
             (state, tab_state)
         };
 
+        let mut file_dialog = None;
+        if let Some(path) = options.layout {
+            if let Err(e) = load_layout(&path, &signals, &mut state, &mut tab_state) {
+                // Let the user retry from the dialog
+                let mut dialog =
+                    FileDialog::new(FileDialogKind::LoadLayout, path.display().to_string());
+                dialog.error = Some(e.to_string());
+                file_dialog = Some(dialog);
+            }
+        }
+
+        let mut template_editor = TemplateEditor::default();
+        if let Some(path) = options.template {
+            match std::fs::read_to_string(&path) {
+                Ok(text) => {
+                    state.template.text = text;
+                    template_editor.error = state.template.validate().err().map(|e| e.to_string());
+                }
+                Err(e) => template_editor.error = Some(format!("{}: {}", path.display(), e)),
+            }
+            template_editor.open = template_editor.error.is_some();
+        }
+
         DataInspector {
             signals: SignalData::new(signals),
             state,
@@ -86,9 +155,53 @@ impl DataInspector {
             reset: false,
             clear_timeseries: false,
             custom_buttons,
+            file_dialog,
+            export_dialog: None,
+            template_editor,
+            replay: options.replay,
+            shown_events: 0,
         }
     }
 
+    /// Shows events of the signals, like logged messages, as annotations
+    fn show_new_events(&mut self) {
+        let events = self.signals.signals().events();
+        // Events may have been cleared along with the samples, for example by a replay
+        self.shown_events = self.shown_events.min(events.len());
+
+        for event in &events[self.shown_events..] {
+            // Annotations of events may have been stored with the layout
+            let exists =
+                self.state.annotations.iter().any(|a| {
+                    a.label == event.text && a.kind.time_range() == (event.time, event.time)
+                });
+            if !exists {
+                self.state.annotations.push(Annotation {
+                    label: event.text.clone(),
+                    kind: AnnotationKind::Line { time: event.time },
+                    color: EVENT_COLOR,
+                });
+            }
+        }
+        self.shown_events = events.len();
+    }
+
+    fn toggle_freeze(&mut self) {
+        let signals = self.signals.signals_mut();
+        if signals.is_frozen() {
+            signals.unfreeze();
+        } else {
+            signals.freeze();
+        }
+    }
+
+    fn layout_path(&self) -> String {
+        self.state
+            .layout_path
+            .clone()
+            .unwrap_or("layout.ron".to_string())
+    }
+
     fn reset(&mut self) {
         self.state = DataInspectorState::new(self.signals.signals());
         self.tab_state = TabState::default();
@@ -109,8 +222,14 @@ impl eframe::App for DataInspector {
             }
         }
 
+        if let Some(replay) = &self.replay {
+            // Seeking backwards replays the samples from the start
+            self.clear_timeseries |= replay.sync(self.signals.signals_mut());
+        }
+
         if self.clear_timeseries {
-            self.signals.signals_mut().clear_timeseries();
+            self.signals.clear_timeseries();
+            self.shown_events = 0;
 
             for (_, tab) in self.tab_state.tree.iter_all_tabs_mut() {
                 tab.clear_cache();
@@ -120,6 +239,21 @@ impl eframe::App for DataInspector {
         }
 
         self.signals.update();
+        self.state.add_new_signals(self.signals.signals());
+        self.show_new_events();
+        self.state.new_frame();
+
+        let untemplated = self
+            .signals
+            .signals()
+            .get_signals()
+            .values()
+            .any(|signal| !self.state.templated_signals.contains(signal.name()));
+        if untemplated {
+            let template = self.state.template.clone();
+            // Errors are shown in the template editor
+            let _ = template.apply(self.signals.signals(), &mut self.state, &mut self.tab_state);
+        }
 
         self.frame_history
             .on_new_frame(ctx.input(|i| i.time), frame.info().cpu_usage);
@@ -131,10 +265,53 @@ impl eframe::App for DataInspector {
 
         ctx.request_repaint();
 
+        // Space toggles the freeze, unless typing
+        if ctx.memory(|m| m.focused().is_none())
+            && ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::Space))
+        {
+            self.toggle_freeze();
+        }
+
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             ui.horizontal(|ui| {
                 egui::menu::bar(ui, |ui| {
                     ui.menu_button("File", |ui| {
+                        if ui.button("Save layout as…").clicked() {
+                            self.file_dialog = Some(FileDialog::new(
+                                FileDialogKind::SaveLayout,
+                                self.layout_path(),
+                            ));
+                            ui.close_menu();
+                        }
+                        if ui.button("Load layout…").clicked() {
+                            self.file_dialog = Some(FileDialog::new(
+                                FileDialogKind::LoadLayout,
+                                self.layout_path(),
+                            ));
+                            ui.close_menu();
+                        }
+                        if ui.button("Layout template…").clicked() {
+                            self.template_editor.open = true;
+                            ui.close_menu();
+                        }
+                        ui.separator();
+                        if ui.button("Export…").clicked() {
+                            self.export_dialog = Some(ExportDialog::new(ExportSource::All));
+                            ui.close_menu();
+                        }
+                        if self.signals.signals().recorder().is_some() {
+                            if ui.button("Stop recording").clicked() {
+                                self.signals.signals_mut().stop_recording();
+                                ui.close_menu();
+                            }
+                        } else if ui.button("Start recording…").clicked() {
+                            self.file_dialog = Some(FileDialog::new(
+                                FileDialogKind::Record,
+                                "recording.rdr".to_string(),
+                            ));
+                            ui.close_menu();
+                        }
+                        ui.separator();
                         if ui.button("Clear data").clicked() {
                             self.clear_timeseries = true;
                         }
@@ -147,6 +324,7 @@ impl eframe::App for DataInspector {
                     });
                     ui.menu_button("Options", |ui| {
                         ui.checkbox(&mut self.state.show_debug_info, "Show debug info");
+                        ui.checkbox(&mut self.state.hover_readout, "Show values on hover");
                         ui.menu_button("Downsampling Mode", |ui| {
                             ui.selectable_value(
                                 &mut self.state.downsample_mode,
@@ -169,6 +347,44 @@ impl eframe::App for DataInspector {
 
                     ui.toggle_value(&mut self.state.link_x, "Link X");
 
+                    let mut frozen = self.signals.signals().is_frozen();
+                    if ui
+                        .toggle_value(&mut frozen, "❄ Freeze")
+                        .on_hover_text("Freeze the plots while data keeps being received (Space)")
+                        .changed()
+                    {
+                        self.toggle_freeze();
+                    }
+                    if let Some(recorder) = self.signals.signals().recorder() {
+                        match recorder.error() {
+                            Some(error) => {
+                                ui.colored_label(
+                                    ui.visuals().error_fg_color,
+                                    format!("Recording failed: {}", error),
+                                );
+                            }
+                            None => {
+                                ui.colored_label(
+                                    RECORDING_COLOR,
+                                    format!(
+                                        "● REC {:.1} MB",
+                                        recorder.bytes_written() as f64 / 1e6
+                                    ),
+                                )
+                                .on_hover_text(recorder.path().display().to_string());
+                            }
+                        }
+                    }
+                    if frozen {
+                        ui.colored_label(
+                            FROZEN_COLOR,
+                            format!(
+                                "Frozen, {} samples held",
+                                self.signals.signals().held_samples()
+                            ),
+                        );
+                    }
+
                     if let Some(f) = &mut self.custom_buttons {
                         let mut api = DataInspectorAPI::default();
                         f(ui, &mut api);
@@ -179,7 +395,41 @@ impl eframe::App for DataInspector {
             });
         });
 
+        if self.template_editor.open {
+            self.template_editor.show(ctx, &mut self.state);
+        }
+
+        if let Some(dialog) = &mut self.file_dialog {
+            if dialog.show(
+                ctx,
+                self.signals.signals_mut(),
+                &mut self.state,
+                &mut self.tab_state,
+            ) {
+                self.file_dialog = None;
+            }
+        }
+
+        if let Some(dialog) = &mut self.export_dialog {
+            if dialog.show(ctx, self.signals.signals(), &self.state, &self.tab_state) {
+                self.export_dialog = None;
+            }
+        }
+
+        if let Some(replay) = &self.replay {
+            egui::TopBottomPanel::bottom("replay_panel")
+                .show(ctx, |ui| ReplayUI::new().ui(ui, replay));
+        }
+
         egui::SidePanel::left("side_panel").show(ctx, |ui| {
+            egui::TopBottomPanel::bottom("bookmarks_panel")
+                .resizable(true)
+                .show_inside(ui, |ui| {
+                    egui::CollapsingHeader::new("Bookmarks")
+                        .default_open(true)
+                        .show(ui, |ui| BookmarksUI::new().ui(ui, &mut self.state));
+                });
+
             SignalListUI::new().ui(ui, &self.signals, &mut self.state);
             if (self.state.show_debug_info) {
                 ui.with_layout(egui::Layout::bottom_up(egui::Align::LEFT), |ui| {
@@ -199,22 +449,68 @@ impl eframe::App for DataInspector {
         egui::CentralPanel::default()
             .frame(Frame::central_panel(&ctx.style()).inner_margin(0.))
             .show(ctx, |ui| {
+                let frozen = self.signals.signals().is_frozen();
                 let mut tabviewer = TabViewer::new(&mut self.state, &mut self.signals);
                 let show_close_button = self.tab_state.tree.iter_all_tabs().count() > 1;
                 DockArea::new(&mut self.tab_state.tree)
                     .show_add_buttons(true)
+                    .show_add_popup(true)
                     .show_close_buttons(show_close_button)
                     .style(Style::from_egui(ctx.style().as_ref()))
                     .show_inside(ui, &mut tabviewer);
 
-                for (surface, node) in tabviewer.added_nodes.drain(..) {
+                if frozen {
+                    ui.painter().rect_stroke(
+                        ui.max_rect().shrink(1.0),
+                        0.0,
+                        egui::Stroke::new(2.0, FROZEN_COLOR),
+                    );
+                }
+
+                for (surface, node, kind) in tabviewer.added_nodes.drain(..) {
                     self.tab_state
                         .tree
                         .set_focused_node_and_surface((surface, node));
                     self.tab_state
                         .tree
-                        .push_to_focused_leaf(Tab::new(self.tab_state.tab_counter));
+                        .push_to_focused_leaf(Tab::with_kind(self.tab_state.tab_counter, kind));
+                    self.tab_state.tab_counter += 1;
+                }
+
+                if let Some(pane_id) = tabviewer.export_request {
+                    self.export_dialog = Some(ExportDialog::new(ExportSource::Tab(pane_id)));
+                }
+                if let Some(pane_id) = tabviewer.figure_request {
+                    self.file_dialog = Some(FileDialog::new(
+                        FileDialogKind::Figure(pane_id),
+                        "figure.svg".to_string(),
+                    ));
+                }
+
+                for (pane_id, split, dropped) in tabviewer.split_drops.drain(..) {
+                    let Some((surface, node, _)) = self
+                        .tab_state
+                        .tree
+                        .find_tab_from(|tab| tab.pane_id == pane_id)
+                    else {
+                        continue;
+                    };
+
+                    let new_id = self.tab_state.tab_counter;
                     self.tab_state.tab_counter += 1;
+                    self.tab_state.tree.split(
+                        (surface, node),
+                        split,
+                        0.5,
+                        Node::leaf(Tab::new(new_id)),
+                    );
+
+                    for id in dropped {
+                        if let Some(signal_state) = self.state.signal_state.get_mut(&id) {
+                            signal_state.used_by_tile.insert(new_id);
+                        }
+                    }
+                    self.state.selected_pane = new_id;
                 }
             });
     }
@@ -1,6 +1,15 @@
 use thiserror::Error;
 
 #[derive(Debug, Error)]
-enum DataInspectorError {
-    
-}
\ No newline at end of file
+pub enum DataInspectorError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Error reading layout: {0}")]
+    LayoutRead(#[from] ron::error::SpannedError),
+    #[error("Error writing layout: {0}")]
+    LayoutWrite(#[from] ron::Error),
+    #[error("Template error at line {line}: {msg}")]
+    Template { line: usize, msg: String },
+    #[error("No tab titled '{0}' in the layout")]
+    TabNotFound(String),
+}
@@ -0,0 +1,181 @@
+use egui::{Align2, Color32, RichText};
+use egui_plot::{MarkerShape, PlotPoint, PlotUi, Points, Polygon, Text, VLine};
+
+use crate::state::{Annotation, AnnotationKind, DataInspectorState, XAxisMode};
+
+const REGION_FILL_ALPHA: f32 = 0.15;
+const GO_TO_MARGIN_PC: f64 = 0.1;
+
+pub fn draw_annotations(plot_ui: &mut PlotUi, annotations: &[Annotation]) {
+    let bounds = plot_ui.plot_bounds();
+    let (bottom, top) = (bounds.min()[1], bounds.max()[1]);
+
+    for annotation in annotations {
+        let color = annotation.color;
+        let label_pos = match annotation.kind {
+            AnnotationKind::Point { time, value } => {
+                plot_ui.points(
+                    Points::new([time, value])
+                        .color(color)
+                        .shape(MarkerShape::Diamond)
+                        .radius(5.0),
+                );
+                PlotPoint::new(time, value)
+            }
+            AnnotationKind::Line { time } => {
+                plot_ui.vline(VLine::new(time).color(color).width(1.5));
+                PlotPoint::new(time, top)
+            }
+            AnnotationKind::Region { start, end } => {
+                plot_ui.polygon(
+                    Polygon::new(vec![
+                        [start, bottom],
+                        [end, bottom],
+                        [end, top],
+                        [start, top],
+                    ])
+                    .fill_color(color.gamma_multiply(REGION_FILL_ALPHA))
+                    .stroke(egui::Stroke::new(1.0, color)),
+                );
+                PlotPoint::new(start, top)
+            }
+        };
+
+        plot_ui.text(
+            Text::new(label_pos, format!(" {}", annotation.label))
+                .color(color)
+                .anchor(Align2::LEFT_TOP),
+        );
+    }
+}
+
+/// Lists the annotations, allowing to navigate to them or delete them
+pub struct BookmarksUI {}
+
+impl BookmarksUI {
+    pub fn new() -> BookmarksUI {
+        BookmarksUI {}
+    }
+
+    pub fn ui(&self, ui: &mut egui::Ui, state: &mut DataInspectorState) {
+        if state.annotations.is_empty() {
+            ui.weak("Right click on a plot to add an annotation");
+            return;
+        }
+
+        let mut removed = None;
+        egui::ScrollArea::vertical()
+            .id_salt("bookmarks")
+            .show(ui, |ui| {
+                for (i, annotation) in state.annotations.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.color_edit_button_srgba(&mut annotation.color);
+
+                        let (min, max) = annotation.kind.time_range();
+                        let text = RichText::new(&annotation.label).color(annotation.color);
+                        if ui
+                            .button(text)
+                            .on_hover_text(format!("t = {}", min))
+                            .clicked()
+                        {
+                            let width = if max > min {
+                                (max - min) * (1.0 + 2.0 * GO_TO_MARGIN_PC)
+                            } else {
+                                state.x_range.map(|[min, max]| max - min).unwrap_or(1.0)
+                            };
+                            let center = (min + max) / 2.0;
+
+                            state.x_range_request =
+                                Some([center - width / 2.0, center + width / 2.0]);
+                        }
+
+                        if ui.small_button("🗑").clicked() {
+                            removed = Some(i);
+                        }
+                    });
+                }
+            });
+
+        if let Some(i) = removed {
+            state.annotations.remove(i);
+        }
+
+        // Navigating in fit or follow modes would be immediately overridden
+        if state.x_range_request.is_some() {
+            state.x_axis_mode = XAxisMode::Free;
+        }
+    }
+}
+
+/// Plot context menu entries to add new annotations at the right-clicked point
+pub fn annotation_menu(
+    ui: &mut egui::Ui,
+    clicked: PlotPoint,
+    cursors: Option<[f64; 2]>,
+    label: &mut String,
+    pending_region: &mut Option<f64>,
+    state: &mut DataInspectorState,
+) {
+    ui.horizontal(|ui| {
+        ui.label("Label:");
+        ui.text_edit_singleline(label);
+    });
+
+    let mut add = |kind| {
+        let color = Color32::LIGHT_BLUE;
+        state.annotations.push(Annotation {
+            label: if label.is_empty() {
+                format!("Annotation {}", state.annotations.len() + 1)
+            } else {
+                std::mem::take(label)
+            },
+            kind,
+            color,
+        });
+    };
+
+    if ui.button("Add point").clicked() {
+        add(AnnotationKind::Point {
+            time: clicked.x,
+            value: clicked.y,
+        });
+        ui.close_menu();
+    }
+    if ui.button("Add vertical line").clicked() {
+        add(AnnotationKind::Line { time: clicked.x });
+        ui.close_menu();
+    }
+
+    if let Some([a, b]) = cursors {
+        if ui.button("Add region between cursors").clicked() {
+            add(AnnotationKind::Region {
+                start: a.min(b),
+                end: a.max(b),
+            });
+            ui.close_menu();
+        }
+    }
+
+    match *pending_region {
+        None => {
+            if ui.button("Start region here").clicked() {
+                *pending_region = Some(clicked.x);
+                ui.close_menu();
+            }
+        }
+        Some(start) => {
+            if ui.button("End region here").clicked() {
+                add(AnnotationKind::Region {
+                    start: start.min(clicked.x),
+                    end: start.max(clicked.x),
+                });
+                *pending_region = None;
+                ui.close_menu();
+            }
+            if ui.button("Cancel region").clicked() {
+                *pending_region = None;
+                ui.close_menu();
+            }
+        }
+    }
+}
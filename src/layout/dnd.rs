@@ -0,0 +1,109 @@
+use egui::{DragAndDrop, Id, Rect, Response, Sense, Stroke};
+use egui_dock::Split;
+use rust_data_inspector_signals::PlotSignalID;
+
+/// Fraction of the tab size, from each edge, where dropping signals splits the tab
+const SPLIT_EDGE_PC: f32 = 0.2;
+const DROP_FILL_ALPHA: f32 = 0.2;
+
+/// Signals being dragged from the signal list
+#[derive(Debug, Clone)]
+pub struct SignalDragPayload {
+    pub signals: Vec<PlotSignalID>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DropZone {
+    /// Add the signals to the tab
+    Center,
+    /// Split the tab, creating a new one with the signals
+    Split(Split),
+}
+
+impl DropZone {
+    fn at(rect: Rect, pos: egui::Pos2) -> Self {
+        let rel = (pos - rect.min) / rect.size();
+        let edges = [
+            (rel.x, Split::Left),
+            (1.0 - rel.x, Split::Right),
+            (rel.y, Split::Above),
+            (1.0 - rel.y, Split::Below),
+        ];
+
+        edges
+            .into_iter()
+            .filter(|(d, _)| *d < SPLIT_EDGE_PC)
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map_or(DropZone::Center, |(_, split)| DropZone::Split(split))
+    }
+
+    /// Area of the tab that will be occupied by the signals
+    fn preview_rect(&self, rect: Rect) -> Rect {
+        let center = rect.center();
+        match self {
+            DropZone::Center => rect,
+            DropZone::Split(Split::Left) => rect.with_max_x(center.x),
+            DropZone::Split(Split::Right) => rect.with_min_x(center.x),
+            DropZone::Split(Split::Above) => rect.with_max_y(center.y),
+            DropZone::Split(Split::Below) => rect.with_min_y(center.y),
+        }
+    }
+}
+
+/// Makes `response` draggable, carrying the signals returned by `signals`
+pub fn signal_drag_source(
+    ui: &egui::Ui,
+    response: &Response,
+    signals: impl FnOnce() -> Vec<PlotSignalID>,
+) {
+    let drag = ui.interact(response.rect, response.id.with("drag"), Sense::drag());
+
+    if drag.drag_started() {
+        drag.dnd_set_drag_payload(SignalDragPayload { signals: signals() });
+    }
+
+    if !drag.dragged() {
+        return;
+    }
+
+    if let Some(payload) = DragAndDrop::payload::<SignalDragPayload>(ui.ctx()) {
+        ui.ctx().set_cursor_icon(egui::CursorIcon::Grabbing);
+        egui::show_tooltip_at_pointer(
+            ui.ctx(),
+            ui.layer_id(),
+            Id::new("signal_drag_tooltip"),
+            |ui| ui.label(format!("{} signal(s)", payload.signals.len())),
+        );
+    }
+}
+
+/// Highlights the drop zone under the pointer while signals are dragged over `rect`.
+/// Returns the dropped signals once released.
+pub fn signal_drop_target(ui: &egui::Ui, rect: Rect) -> Option<(DropZone, Vec<PlotSignalID>)> {
+    if !DragAndDrop::has_payload_of_type::<SignalDragPayload>(ui.ctx()) {
+        return None;
+    }
+
+    let pos = ui.ctx().pointer_hover_pos().filter(|p| rect.contains(*p))?;
+    let zone = DropZone::at(rect, pos);
+
+    let color = ui.visuals().selection.bg_fill;
+    ui.ctx()
+        .layer_painter(egui::LayerId::new(
+            egui::Order::Foreground,
+            Id::new("signal_drop_preview"),
+        ))
+        .rect(
+            zone.preview_rect(rect).shrink(2.0),
+            4.0,
+            color.gamma_multiply(DROP_FILL_ALPHA),
+            Stroke::new(2.0, color),
+        );
+
+    if ui.input(|i| i.pointer.any_released()) {
+        DragAndDrop::take_payload::<SignalDragPayload>(ui.ctx())
+            .map(|payload| (zone, payload.signals.clone()))
+    } else {
+        None
+    }
+}
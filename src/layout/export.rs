@@ -0,0 +1,281 @@
+use rust_data_inspector_signals::{CsvExportOptions, CsvLayout, PlotSignalID, PlotSignals};
+
+use crate::state::{DataInspectorState, TabState};
+
+/// Highest resampling rate, in Hz
+const MAX_RATE: f64 = 1e6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportSource {
+    /// Signals plotted in any tab
+    All,
+    /// Signals plotted in the tab with the provided pane id
+    Tab(u64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Csv,
+    #[cfg(feature = "parquet")]
+    Parquet,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LayoutKind {
+    Union,
+    Resampled,
+    PerSignal,
+}
+
+/// Window to export signals to CSV, or Parquet if enabled
+#[derive(Debug)]
+pub struct ExportDialog {
+    source: ExportSource,
+    path: String,
+    /// Include signals not plotted in any tab, when exporting all tabs
+    include_hidden: bool,
+    visible_range: bool,
+    format: Format,
+    layout: LayoutKind,
+    rate: f64,
+    separator: String,
+    precision: Option<usize>,
+    result: Option<Result<String, String>>,
+}
+
+impl ExportDialog {
+    pub fn new(source: ExportSource) -> Self {
+        ExportDialog {
+            source,
+            path: "export.csv".to_string(),
+            include_hidden: false,
+            visible_range: false,
+            format: Format::Csv,
+            layout: LayoutKind::Union,
+            rate: 100.0,
+            separator: ",".to_string(),
+            precision: None,
+            result: None,
+        }
+    }
+
+    fn selected_signals(
+        &self,
+        signals: &PlotSignals,
+        state: &DataInspectorState,
+    ) -> Vec<PlotSignalID> {
+        let mut selected: Vec<_> = signals
+            .get_signals()
+            .values()
+            .filter(|signal| {
+                let Some(signal_state) = state.signal_state.get(&signal.id()) else {
+                    return false;
+                };
+                match self.source {
+                    ExportSource::All => {
+                        self.include_hidden || !signal_state.used_by_tile.is_empty()
+                    }
+                    ExportSource::Tab(pane_id) => signal_state.used_by_tile.contains(&pane_id),
+                }
+            })
+            .collect();
+        selected.sort_by_key(|s| s.name());
+
+        selected.into_iter().map(|s| s.id()).collect()
+    }
+
+    /// Time range shown by the plots of the source
+    fn shown_range(&self, state: &DataInspectorState, tab_state: &TabState) -> Option<[f64; 2]> {
+        match self.source {
+            ExportSource::All => state.x_range,
+            ExportSource::Tab(pane_id) => tab_state
+                .tree
+                .iter_all_tabs()
+                .find(|(_, tab)| tab.pane_id == pane_id)
+                .and_then(|(_, tab)| tab.view)
+                .map(|[x, _]| x),
+        }
+    }
+
+    /// Shows the dialog. Returns true if it should be closed.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        signals: &PlotSignals,
+        state: &DataInspectorState,
+        tab_state: &TabState,
+    ) -> bool {
+        let title = match self.source {
+            ExportSource::All => "Export…",
+            ExportSource::Tab(_) => "Export tab…",
+        };
+
+        let mut open = true;
+        let mut done = false;
+        egui::Window::new(title)
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let selected = self.selected_signals(signals, state);
+                let shown_range = self.shown_range(state, tab_state);
+
+                egui::Grid::new("export_grid")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Signals:");
+                        ui.horizontal(|ui| {
+                            ui.label(format!("{}", selected.len()));
+                            if self.source == ExportSource::All {
+                                ui.checkbox(
+                                    &mut self.include_hidden,
+                                    "Include signals not plotted",
+                                );
+                            }
+                        });
+                        ui.end_row();
+
+                        ui.label("Range:");
+                        ui.horizontal(|ui| {
+                            ui.selectable_value(&mut self.visible_range, false, "Full");
+                            ui.add_enabled_ui(shown_range.is_some(), |ui| {
+                                ui.selectable_value(&mut self.visible_range, true, "Visible");
+                            });
+                        });
+                        ui.end_row();
+
+                        #[cfg(feature = "parquet")]
+                        {
+                            ui.label("Format:");
+                            ui.horizontal(|ui| {
+                                ui.selectable_value(&mut self.format, Format::Csv, "CSV");
+                                ui.selectable_value(&mut self.format, Format::Parquet, "Parquet")
+                                    .on_hover_text(
+                                        "A single file with a row for each timestamp of any signal",
+                                    );
+                            });
+                            ui.end_row();
+                        }
+
+                        if self.format == Format::Csv {
+                            ui.label("Layout:");
+                            ui.horizontal(|ui| {
+                                ui.selectable_value(&mut self.layout, LayoutKind::Union, "Union")
+                                    .on_hover_text("A row for each timestamp of any signal");
+                                ui.selectable_value(
+                                    &mut self.layout,
+                                    LayoutKind::Resampled,
+                                    "Resampled",
+                                )
+                                .on_hover_text("Rows at a fixed rate, interpolating the signals");
+                                ui.selectable_value(
+                                    &mut self.layout,
+                                    LayoutKind::PerSignal,
+                                    "One file per signal",
+                                )
+                                .on_hover_text("Files are written in the provided directory");
+                                if self.layout == LayoutKind::Resampled {
+                                    ui.add(
+                                        egui::DragValue::new(&mut self.rate)
+                                            .range(0.001..=MAX_RATE)
+                                            .suffix(" Hz"),
+                                    );
+                                }
+                            });
+                            ui.end_row();
+
+                            ui.label("Separator:");
+                            ui.add(
+                                egui::TextEdit::singleline(&mut self.separator).desired_width(30.0),
+                            );
+                            ui.end_row();
+
+                            ui.label("Precision:");
+                            ui.horizontal(|ui| {
+                                let mut fixed = self.precision.is_some();
+                                ui.checkbox(&mut fixed, "Fixed");
+                                match (&mut self.precision, fixed) {
+                                    (Some(precision), true) => {
+                                        ui.add(
+                                            egui::DragValue::new(precision)
+                                                .range(0..=17)
+                                                .suffix(" digits"),
+                                        );
+                                    }
+                                    (None, true) => self.precision = Some(6),
+                                    (_, false) => self.precision = None,
+                                }
+                            });
+                            ui.end_row();
+                        }
+
+                        ui.label(
+                            if self.format == Format::Csv && self.layout == LayoutKind::PerSignal {
+                                "Directory:"
+                            } else {
+                                "File:"
+                            },
+                        );
+                        ui.add(egui::TextEdit::singleline(&mut self.path).desired_width(300.0));
+                        ui.end_row();
+                    });
+
+                match &self.result {
+                    Some(Ok(msg)) => {
+                        ui.label(msg);
+                    }
+                    Some(Err(error)) => {
+                        ui.colored_label(ui.visuals().error_fg_color, error);
+                    }
+                    None => {}
+                }
+
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(!selected.is_empty(), egui::Button::new("Export"))
+                        .clicked()
+                    {
+                        self.result = Some(self.export(signals, &selected, shown_range));
+                    }
+                    if ui.button("Close").clicked() {
+                        done = true;
+                    }
+                });
+            });
+
+        done || !open
+    }
+
+    fn export(
+        &self,
+        signals: &PlotSignals,
+        selected: &[PlotSignalID],
+        shown_range: Option<[f64; 2]>,
+    ) -> Result<String, String> {
+        let range = shown_range.filter(|_| self.visible_range);
+        match self.format {
+            Format::Csv => {
+                let options = CsvExportOptions {
+                    layout: match self.layout {
+                        LayoutKind::Union => CsvLayout::Union,
+                        LayoutKind::Resampled => CsvLayout::Resampled { rate: self.rate },
+                        LayoutKind::PerSignal => CsvLayout::PerSignal,
+                    },
+                    separator: self.separator.clone(),
+                    precision: self.precision,
+                    range,
+                };
+
+                signals
+                    .export_csv(selected, &self.path, &options)
+                    .map(|files| format!("Written {} file(s)", files.len()))
+                    .map_err(|e| e.to_string())
+            }
+            #[cfg(feature = "parquet")]
+            Format::Parquet => signals
+                .export_parquet(selected, &self.path, range)
+                .map(|_| format!("Written {}", self.path))
+                .map_err(|e| e.to_string()),
+        }
+    }
+}
@@ -0,0 +1,1002 @@
+use std::{
+    collections::HashSet,
+    fmt::Write as _,
+    path::{Path, PathBuf},
+};
+
+use egui::Color32;
+use rust_data_inspector_signals::PlotSignals;
+
+use crate::{
+    errors::DataInspectorError,
+    layout::{layoutfile::load_layout, tabs::TabKind, tabsettings::LegendPosition},
+    state::{Annotation, AnnotationKind, DataInspectorState, LineDash, TabState},
+};
+
+pub const FIGURE_FONT_SIZE: f32 = 12.0;
+/// Approximate width of a character relative to the font size, used to lay out text
+const CHAR_WIDTH: f32 = 0.55;
+/// Fills are drawn as lighter, opaque colors, since PDF transparency requires extra resources
+const FILL_LIGHTEN: f32 = 0.8;
+const TICK_LENGTH: f32 = 4.0;
+const TARGET_TICKS: usize = 8;
+const GRID_COLOR: Color32 = Color32::from_gray(220);
+const AXIS_COLOR: Color32 = Color32::from_gray(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FigureFormat {
+    Svg,
+    Pdf,
+}
+
+impl FigureFormat {
+    /// Format matching the extension of `path`, SVG if unknown
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("pdf") => FigureFormat::Pdf,
+            _ => FigureFormat::Svg,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            FigureFormat::Svg => "svg",
+            FigureFormat::Pdf => "pdf",
+        }
+    }
+}
+
+/// Time range shown in a figure
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FigureRange {
+    /// Last view of the tab, or the full range if it was never shown
+    View,
+    /// Whole time span of the signals in the tab
+    Full,
+    Custom([f64; 2]),
+}
+
+/// Options to render the tabs of a layout to figures, without a window
+#[derive(Debug, Clone)]
+pub struct FigureOptions {
+    pub width: f32,
+    pub height: f32,
+    pub format: FigureFormat,
+    pub range: FigureRange,
+    /// Titles of the tabs to render, or all time plots if empty
+    pub tabs: Vec<String>,
+    /// Directory where figures are written, named after the title of their tab
+    pub output: PathBuf,
+}
+
+impl Default for FigureOptions {
+    fn default() -> Self {
+        FigureOptions {
+            width: 800.0,
+            height: 500.0,
+            format: FigureFormat::Svg,
+            range: FigureRange::View,
+            tabs: vec![],
+            output: PathBuf::from("."),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextAnchor {
+    Start,
+    Middle,
+    End,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FigureStroke {
+    pub width: f32,
+    pub color: Color32,
+    pub dash: LineDash,
+}
+
+impl FigureStroke {
+    pub fn solid(width: f32, color: Color32) -> Self {
+        FigureStroke {
+            width,
+            color,
+            dash: LineDash::Solid,
+        }
+    }
+
+    fn dash_pattern(&self) -> Option<[f32; 2]> {
+        match self.dash {
+            LineDash::Solid => None,
+            LineDash::Dashed => Some([10.0, 5.0]),
+            LineDash::Dotted => Some([self.width, self.width * 2.0]),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Shape {
+    Path {
+        points: Vec<[f32; 2]>,
+        closed: bool,
+        stroke: Option<FigureStroke>,
+        fill: Option<Color32>,
+    },
+    Circle {
+        center: [f32; 2],
+        radius: f32,
+        color: Color32,
+    },
+    /// Text with its baseline at `pos`. Vertical text is rotated counterclockwise.
+    Text {
+        pos: [f32; 2],
+        text: String,
+        size: f32,
+        color: Color32,
+        anchor: TextAnchor,
+        vertical: bool,
+    },
+}
+
+impl Shape {
+    pub fn line(points: Vec<[f32; 2]>, stroke: FigureStroke) -> Self {
+        Shape::Path {
+            points,
+            closed: false,
+            stroke: Some(stroke),
+            fill: None,
+        }
+    }
+
+    pub fn text(
+        pos: [f32; 2],
+        text: impl Into<String>,
+        color: Color32,
+        anchor: TextAnchor,
+    ) -> Self {
+        Shape::Text {
+            pos,
+            text: text.into(),
+            size: FIGURE_FONT_SIZE,
+            color,
+            anchor,
+            vertical: false,
+        }
+    }
+}
+
+/// Maps plot coordinates to the pixels of the plot area of a figure
+#[derive(Debug, Clone, Copy)]
+pub struct FigureAxes {
+    pub x: [f64; 2],
+    pub y: [f64; 2],
+    /// Plot area: left, top, width, height
+    pub rect: [f32; 4],
+}
+
+impl FigureAxes {
+    pub fn screen_pos(&self, [x, y]: [f64; 2]) -> [f32; 2] {
+        let [left, top, width, height] = self.rect;
+        [
+            left + ((x - self.x[0]) / (self.x[1] - self.x[0])) as f32 * width,
+            top + ((self.y[1] - y) / (self.y[1] - self.y[0])) as f32 * height,
+        ]
+    }
+
+    fn left(&self) -> f32 {
+        self.rect[0]
+    }
+
+    fn right(&self) -> f32 {
+        self.rect[0] + self.rect[2]
+    }
+
+    fn top(&self) -> f32 {
+        self.rect[1]
+    }
+
+    fn bottom(&self) -> f32 {
+        self.rect[1] + self.rect[3]
+    }
+}
+
+/// Vector drawing of a plot, in pixels with the origin at the top left.
+/// Shapes in `plot` are clipped to the plot area, the ones in `overlay` are drawn on top.
+#[derive(Debug, Clone)]
+pub struct Figure {
+    pub width: f32,
+    pub height: f32,
+    pub axes: FigureAxes,
+    pub plot: Vec<Shape>,
+    pub overlay: Vec<Shape>,
+}
+
+impl Figure {
+    /// Creates a figure with room for the title, the axis labels and the tick labels
+    pub fn new(
+        width: f32,
+        height: f32,
+        x: [f64; 2],
+        y: [f64; 2],
+        has_y_label: bool,
+        has_right_axis: bool,
+    ) -> Self {
+        let left = if has_y_label { 90.0 } else { 70.0 };
+        let right = if has_right_axis { 70.0 } else { 20.0 };
+        let (top, bottom) = (30.0, 45.0);
+
+        Figure {
+            width,
+            height,
+            axes: FigureAxes {
+                x,
+                y,
+                rect: [
+                    left,
+                    top,
+                    (width - left - right).max(1.0),
+                    (height - top - bottom).max(1.0),
+                ],
+            },
+            plot: vec![],
+            overlay: vec![],
+        }
+    }
+
+    /// Draws the frame, grid, ticks and labels of the plot.
+    /// Y tick labels are omitted if `y_ticks` is false, and drawn on the right for `right`, if provided.
+    pub fn draw_axes(
+        &mut self,
+        title: &str,
+        x_label: &str,
+        y_label: &str,
+        show_grid: bool,
+        y_ticks: bool,
+        right: Option<[f64; 2]>,
+    ) {
+        let axes = self.axes;
+        let axis = FigureStroke::solid(1.0, AXIS_COLOR);
+        let grid = FigureStroke::solid(1.0, GRID_COLOR);
+
+        let (x_ticks, x_decimals) = ticks(axes.x, TARGET_TICKS);
+        for t in x_ticks {
+            let [px, _] = axes.screen_pos([t, axes.y[0]]);
+            if show_grid {
+                self.plot.push(Shape::line(
+                    vec![[px, axes.top()], [px, axes.bottom()]],
+                    grid,
+                ));
+            }
+            self.overlay.push(Shape::line(
+                vec![[px, axes.bottom()], [px, axes.bottom() + TICK_LENGTH]],
+                axis,
+            ));
+            self.overlay.push(Shape::text(
+                [px, axes.bottom() + TICK_LENGTH + FIGURE_FONT_SIZE],
+                format!("{:.*}", x_decimals, t),
+                AXIS_COLOR,
+                TextAnchor::Middle,
+            ));
+        }
+
+        let y_target = TARGET_TICKS / 2 + 1;
+        let (y_marks, y_decimals) = ticks(axes.y, y_target);
+        for y in y_marks {
+            let [_, py] = axes.screen_pos([axes.x[0], y]);
+            if show_grid {
+                self.plot.push(Shape::line(
+                    vec![[axes.left(), py], [axes.right(), py]],
+                    grid,
+                ));
+            }
+            if !y_ticks {
+                continue;
+            }
+
+            let baseline = py + FIGURE_FONT_SIZE * 0.35;
+            self.overlay.push(Shape::line(
+                vec![[axes.left() - TICK_LENGTH, py], [axes.left(), py]],
+                axis,
+            ));
+            self.overlay.push(Shape::text(
+                [axes.left() - TICK_LENGTH - 2.0, baseline],
+                format!("{:.*}", y_decimals, y),
+                AXIS_COLOR,
+                TextAnchor::End,
+            ));
+
+            if let Some(right) = right {
+                // Same marks as the left axis, labeled with the values of the right one
+                let scale = (right[1] - right[0]) / (axes.y[1] - axes.y[0]);
+                let value = right[0] + (y - axes.y[0]) * scale;
+                let step = tick_step(axes.y, y_target).unwrap_or(1.0) * scale.abs();
+
+                self.overlay.push(Shape::line(
+                    vec![[axes.right(), py], [axes.right() + TICK_LENGTH, py]],
+                    axis,
+                ));
+                self.overlay.push(Shape::text(
+                    [axes.right() + TICK_LENGTH + 2.0, baseline],
+                    format!("{:.*}", decimals(step), value),
+                    AXIS_COLOR,
+                    TextAnchor::Start,
+                ));
+            }
+        }
+
+        let [left, top, width, height] = axes.rect;
+        self.overlay.push(Shape::Path {
+            points: vec![
+                [left, top],
+                [left + width, top],
+                [left + width, top + height],
+                [left, top + height],
+            ],
+            closed: true,
+            stroke: Some(axis),
+            fill: None,
+        });
+
+        self.overlay.push(Shape::text(
+            [self.width / 2.0, 20.0],
+            title,
+            Color32::BLACK,
+            TextAnchor::Middle,
+        ));
+        if !x_label.is_empty() {
+            self.overlay.push(Shape::text(
+                [left + width / 2.0, self.height - 8.0],
+                x_label,
+                AXIS_COLOR,
+                TextAnchor::Middle,
+            ));
+        }
+        if !y_label.is_empty() {
+            self.overlay.push(Shape::Text {
+                pos: [FIGURE_FONT_SIZE + 4.0, top + height / 2.0],
+                text: y_label.to_string(),
+                size: FIGURE_FONT_SIZE,
+                color: AXIS_COLOR,
+                anchor: TextAnchor::Middle,
+                vertical: true,
+            });
+        }
+    }
+
+    /// Draws the annotations the same way they are drawn in the plots
+    pub fn draw_annotations(&mut self, annotations: &[Annotation]) {
+        let axes = self.axes;
+        for annotation in annotations {
+            let color = annotation.color;
+            let stroke = FigureStroke::solid(1.5, color);
+
+            let label_pos = match annotation.kind {
+                AnnotationKind::Point { time, value } => {
+                    let [x, y] = axes.screen_pos([time, value]);
+                    let r = 5.0;
+                    self.plot.push(Shape::Path {
+                        points: vec![[x, y - r], [x + r, y], [x, y + r], [x - r, y]],
+                        closed: true,
+                        stroke: None,
+                        fill: Some(color),
+                    });
+                    [x, y]
+                }
+                AnnotationKind::Line { time } => {
+                    let [x, _] = axes.screen_pos([time, axes.y[0]]);
+                    self.plot.push(Shape::line(
+                        vec![[x, axes.top()], [x, axes.bottom()]],
+                        stroke,
+                    ));
+                    [x, axes.top()]
+                }
+                AnnotationKind::Region { start, end } => {
+                    let [x0, _] = axes.screen_pos([start, axes.y[0]]);
+                    let [x1, _] = axes.screen_pos([end, axes.y[0]]);
+                    self.plot.push(Shape::Path {
+                        points: vec![
+                            [x0, axes.top()],
+                            [x1, axes.top()],
+                            [x1, axes.bottom()],
+                            [x0, axes.bottom()],
+                        ],
+                        closed: true,
+                        stroke: Some(FigureStroke::solid(1.0, color)),
+                        fill: Some(lighten(color)),
+                    });
+                    [x0, axes.top()]
+                }
+            };
+
+            self.plot.push(Shape::text(
+                [label_pos[0] + 3.0, label_pos[1] + FIGURE_FONT_SIZE],
+                annotation.label.clone(),
+                color,
+                TextAnchor::Start,
+            ));
+        }
+    }
+
+    /// Draws the legend in a corner of the plot area
+    pub fn draw_legend(
+        &mut self,
+        entries: &[(String, Color32, LineDash)],
+        position: LegendPosition,
+    ) {
+        if entries.is_empty() || position == LegendPosition::Hidden {
+            return;
+        }
+
+        let (sample, pad, row) = (20.0, 6.0, FIGURE_FONT_SIZE + 4.0);
+        let text_width = entries
+            .iter()
+            .map(|(name, ..)| text_width(name, FIGURE_FONT_SIZE))
+            .fold(0.0, f32::max);
+        let (width, height) = (
+            sample + text_width + pad * 3.0,
+            row * entries.len() as f32 + pad * 2.0,
+        );
+
+        let axes = self.axes;
+        let left = match position {
+            LegendPosition::TopLeft | LegendPosition::BottomLeft => axes.left() + pad,
+            _ => axes.right() - pad - width,
+        };
+        let top = match position {
+            LegendPosition::TopLeft | LegendPosition::TopRight => axes.top() + pad,
+            _ => axes.bottom() - pad - height,
+        };
+
+        self.overlay.push(Shape::Path {
+            points: vec![
+                [left, top],
+                [left + width, top],
+                [left + width, top + height],
+                [left, top + height],
+            ],
+            closed: true,
+            stroke: Some(FigureStroke::solid(1.0, GRID_COLOR)),
+            fill: Some(Color32::WHITE),
+        });
+
+        for (i, (name, color, dash)) in entries.iter().enumerate() {
+            let y = top + pad + row * (i as f32 + 0.5);
+            self.overlay.push(Shape::line(
+                vec![[left + pad, y], [left + pad + sample, y]],
+                FigureStroke {
+                    width: 2.0,
+                    color: *color,
+                    dash: *dash,
+                },
+            ));
+            self.overlay.push(Shape::text(
+                [left + pad * 2.0 + sample, y + FIGURE_FONT_SIZE * 0.35],
+                name.clone(),
+                Color32::BLACK,
+                TextAnchor::Start,
+            ));
+        }
+    }
+
+    pub fn write(&self, path: &Path, format: FigureFormat) -> std::io::Result<()> {
+        match format {
+            FigureFormat::Svg => std::fs::write(path, self.to_svg()),
+            FigureFormat::Pdf => std::fs::write(path, self.to_pdf()),
+        }
+    }
+
+    pub fn to_svg(&self) -> String {
+        let mut svg = String::new();
+        let [left, top, width, height] = self.axes.rect;
+
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="Helvetica, Arial, sans-serif">"#,
+            w = self.width,
+            h = self.height
+        );
+        let _ = writeln!(
+            svg,
+            r#"<defs><clipPath id="plot"><rect x="{left}" y="{top}" width="{width}" height="{height}"/></clipPath></defs>"#
+        );
+        let _ = writeln!(svg, r#"<rect width="100%" height="100%" fill="white"/>"#);
+
+        svg.push_str("<g clip-path=\"url(#plot)\">\n");
+        for shape in &self.plot {
+            svg_shape(&mut svg, shape);
+        }
+        svg.push_str("</g>\n");
+        for shape in &self.overlay {
+            svg_shape(&mut svg, shape);
+        }
+        svg.push_str("</svg>\n");
+
+        svg
+    }
+
+    pub fn to_pdf(&self) -> Vec<u8> {
+        let [left, top, width, height] = self.axes.rect;
+
+        let mut content = String::new();
+        let _ = writeln!(content, "1 1 1 rg 0 0 {} {} re f", self.width, self.height);
+        let _ = writeln!(
+            content,
+            "q {:.2} {:.2} {:.2} {:.2} re W n",
+            left,
+            self.height - top - height,
+            width,
+            height
+        );
+        for shape in &self.plot {
+            self.pdf_shape(&mut content, shape);
+        }
+        content.push_str("Q\n");
+        for shape in &self.overlay {
+            self.pdf_shape(&mut content, shape);
+        }
+
+        let objects = [
+            "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_string(),
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /Font << /F1 4 0 R >> >> /Contents 5 0 R >>",
+                self.width, self.height
+            ),
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+                .to_string(),
+            format!(
+                "<< /Length {} >>\nstream\n{}endstream",
+                content.len(),
+                content
+            ),
+        ];
+
+        let mut pdf = String::from("%PDF-1.4\n");
+        let mut offsets = vec![];
+        for (i, object) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            let _ = write!(pdf, "{} 0 obj\n{}\nendobj\n", i + 1, object);
+        }
+
+        let xref = pdf.len();
+        let _ = write!(pdf, "xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+        for offset in offsets {
+            let _ = writeln!(pdf, "{:010} 00000 n ", offset);
+        }
+        let _ = write!(
+            pdf,
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref
+        );
+
+        pdf.into_bytes()
+    }
+
+    fn pdf_shape(&self, out: &mut String, shape: &Shape) {
+        // PDF has the origin at the bottom left
+        let flip = |[x, y]: [f32; 2]| [x, self.height - y];
+
+        match shape {
+            Shape::Path {
+                points,
+                closed,
+                stroke,
+                fill,
+            } => {
+                let Some((first, rest)) = points.split_first() else {
+                    return;
+                };
+
+                if let Some(fill) = fill {
+                    let _ = write!(out, "{} rg ", pdf_color(*fill));
+                }
+                if let Some(stroke) = stroke {
+                    let _ = write!(
+                        out,
+                        "{} RG {:.2} w 1 J 1 j ",
+                        pdf_color(stroke.color),
+                        stroke.width
+                    );
+                    match stroke.dash_pattern() {
+                        Some([on, off]) => {
+                            let _ = write!(out, "[{:.2} {:.2}] 0 d ", on, off);
+                        }
+                        None => out.push_str("[] 0 d "),
+                    }
+                }
+
+                let [x, y] = flip(*first);
+                let _ = write!(out, "{:.2} {:.2} m ", x, y);
+                for &p in rest {
+                    let [x, y] = flip(p);
+                    let _ = write!(out, "{:.2} {:.2} l ", x, y);
+                }
+
+                let op = match (fill.is_some(), stroke.is_some(), closed) {
+                    (true, true, _) => "b",
+                    (true, false, _) => "f",
+                    (false, true, true) => "s",
+                    (false, true, false) => "S",
+                    (false, false, _) => "n",
+                };
+                out.push_str(op);
+                out.push('\n');
+            }
+            Shape::Circle {
+                center,
+                radius,
+                color,
+            } => {
+                // Circle approximated by four cubic Bézier curves
+                let [cx, cy] = flip(*center);
+                let (r, k) = (*radius, radius * 0.5523);
+                let _ = writeln!(
+                    out,
+                    "{c} rg {x0:.2} {cy:.2} m \
+                     {x0:.2} {a:.2} {b:.2} {y1:.2} {cx:.2} {y1:.2} c \
+                     {d:.2} {y1:.2} {x1:.2} {a:.2} {x1:.2} {cy:.2} c \
+                     {x1:.2} {e:.2} {d:.2} {y0:.2} {cx:.2} {y0:.2} c \
+                     {b:.2} {y0:.2} {x0:.2} {e:.2} {x0:.2} {cy:.2} c f",
+                    c = pdf_color(*color),
+                    x0 = cx - r,
+                    x1 = cx + r,
+                    y0 = cy - r,
+                    y1 = cy + r,
+                    a = cy + k,
+                    b = cx - k,
+                    d = cx + k,
+                    e = cy - k,
+                );
+            }
+            Shape::Text {
+                pos,
+                text,
+                size,
+                color,
+                anchor,
+                vertical,
+            } => {
+                let offset = match anchor {
+                    TextAnchor::Start => 0.0,
+                    TextAnchor::Middle => -text_width(text, *size) / 2.0,
+                    TextAnchor::End => -text_width(text, *size),
+                };
+                let [x, y] = flip(*pos);
+                let matrix = if *vertical {
+                    format!("0 1 -1 0 {:.2} {:.2}", x, y + offset)
+                } else {
+                    format!("1 0 0 1 {:.2} {:.2}", x + offset, y)
+                };
+
+                let escaped: String = text
+                    .chars()
+                    .map(|c| match c {
+                        '(' | ')' | '\\' => format!("\\{}", c),
+                        ' '..='~' => c.to_string(),
+                        _ => "?".to_string(),
+                    })
+                    .collect();
+
+                let _ = writeln!(
+                    out,
+                    "{} rg BT /F1 {:.1} Tf {} Tm ({}) Tj ET",
+                    pdf_color(*color),
+                    size,
+                    matrix,
+                    escaped
+                );
+            }
+        }
+    }
+}
+
+fn svg_shape(out: &mut String, shape: &Shape) {
+    match shape {
+        Shape::Path {
+            points,
+            closed,
+            stroke,
+            fill,
+        } => {
+            let mut d = String::new();
+            for (i, [x, y]) in points.iter().enumerate() {
+                let _ = write!(d, "{}{:.2},{:.2}", if i == 0 { "M" } else { " L" }, x, y);
+            }
+            if *closed {
+                d.push_str(" Z");
+            }
+
+            let fill = fill.map_or("none".to_string(), svg_color);
+            let stroke = match stroke {
+                Some(stroke) => {
+                    let dash = stroke.dash_pattern().map_or(String::new(), |[on, off]| {
+                        format!(r#" stroke-dasharray="{:.2} {:.2}""#, on, off)
+                    });
+                    format!(
+                        r#" stroke="{}" stroke-width="{:.2}" stroke-linejoin="round" stroke-linecap="round"{}"#,
+                        svg_color(stroke.color),
+                        stroke.width,
+                        dash
+                    )
+                }
+                None => String::new(),
+            };
+            let _ = writeln!(out, r#"<path d="{}" fill="{}"{}/>"#, d, fill, stroke);
+        }
+        Shape::Circle {
+            center: [x, y],
+            radius,
+            color,
+        } => {
+            let _ = writeln!(
+                out,
+                r#"<circle cx="{:.2}" cy="{:.2}" r="{:.2}" fill="{}"/>"#,
+                x,
+                y,
+                radius,
+                svg_color(*color)
+            );
+        }
+        Shape::Text {
+            pos: [x, y],
+            text,
+            size,
+            color,
+            anchor,
+            vertical,
+        } => {
+            let anchor = match anchor {
+                TextAnchor::Start => "start",
+                TextAnchor::Middle => "middle",
+                TextAnchor::End => "end",
+            };
+            let rotate = if *vertical {
+                format!(r#" transform="rotate(-90 {:.2} {:.2})""#, x, y)
+            } else {
+                String::new()
+            };
+            let escaped = text
+                .replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;");
+
+            let _ = writeln!(
+                out,
+                r#"<text x="{:.2}" y="{:.2}" font-size="{:.1}" fill="{}" text-anchor="{}"{}>{}</text>"#,
+                x,
+                y,
+                size,
+                svg_color(*color),
+                anchor,
+                rotate,
+                escaped
+            );
+        }
+    }
+}
+
+fn svg_color(color: Color32) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r(), color.g(), color.b())
+}
+
+fn pdf_color(color: Color32) -> String {
+    format!(
+        "{:.3} {:.3} {:.3}",
+        color.r() as f32 / 255.0,
+        color.g() as f32 / 255.0,
+        color.b() as f32 / 255.0
+    )
+}
+
+/// Opaque lighter version of `color`, used for fills
+pub fn lighten(color: Color32) -> Color32 {
+    let mix = |c: u8| (c as f32 + (255.0 - c as f32) * FILL_LIGHTEN) as u8;
+    Color32::from_rgb(mix(color.r()), mix(color.g()), mix(color.b()))
+}
+
+pub fn text_width(text: &str, size: f32) -> f32 {
+    text.chars().count() as f32 * size * CHAR_WIDTH
+}
+
+/// Distance between ticks covering `range` with about `target` ticks, rounded to 1, 2 or 5 times a power of 10
+fn tick_step(range: [f64; 2], target: usize) -> Option<f64> {
+    let raw = (range[1] - range[0]).abs() / target.max(1) as f64;
+    let magnitude = 10f64.powf(raw.log10().floor());
+    let step = [1.0, 2.0, 5.0, 10.0]
+        .into_iter()
+        .map(|m| m * magnitude)
+        .find(|&s| s >= raw)?;
+
+    (step.is_finite() && step > 0.0).then_some(step)
+}
+
+fn decimals(step: f64) -> usize {
+    (-step.log10()).ceil().max(0.0) as usize
+}
+
+/// Returns the position of the ticks within `range` and the number of decimals needed to show them
+fn ticks(range: [f64; 2], target: usize) -> (Vec<f64>, usize) {
+    let Some(step) = tick_step(range, target) else {
+        return (vec![], 0);
+    };
+
+    let first = (range[0] / step).ceil() as i64;
+    let last = (range[1] / step).floor() as i64;
+
+    (
+        (first..=last).map(|i| i as f64 * step).collect(),
+        decimals(step),
+    )
+}
+
+/// Renders the tabs of the layout at `layout` to figures, without opening a window.
+/// Returns the paths of the written files.
+pub fn export_figures(
+    signals: &PlotSignals,
+    layout: &Path,
+    options: &FigureOptions,
+) -> Result<Vec<PathBuf>, DataInspectorError> {
+    let mut state = DataInspectorState::new(signals);
+    let mut tab_state = TabState::default();
+    load_layout(layout, signals, &mut state, &mut tab_state)?;
+
+    for title in &options.tabs {
+        if !tab_state
+            .tree
+            .iter_all_tabs()
+            .any(|(_, tab)| &tab.settings.title(|| tab.default_title()) == title)
+        {
+            return Err(DataInspectorError::TabNotFound(title.clone()));
+        }
+    }
+
+    std::fs::create_dir_all(&options.output)?;
+
+    let mut paths = vec![];
+    let mut used = HashSet::new();
+    for (_, tab) in tab_state.tree.iter_all_tabs_mut() {
+        let title = tab.settings.title(|| tab.default_title());
+        let selected = options.tabs.is_empty() || options.tabs.contains(&title);
+        if !selected || !matches!(tab.kind, TabKind::Time) {
+            continue;
+        }
+
+        let figure = tab.figure(
+            signals,
+            &state,
+            options.range,
+            options.width,
+            options.height,
+        );
+
+        let name: String = title
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        // Tabs with the same title, or titles differing only by the replaced characters or by
+        // case, are written to distinct files
+        let mut unique = name.clone();
+        let mut n = 1;
+        while !used.insert(unique.to_lowercase()) {
+            n += 1;
+            unique = format!("{}_{}", name, n);
+        }
+        let path = options
+            .output
+            .join(format!("{}.{}", unique, options.format.extension()));
+        figure.write(&path, options.format)?;
+        paths.push(path);
+    }
+
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn figure() -> Figure {
+        let mut figure = Figure::new(200.0, 100.0, [0.0, 10.0], [-1.0, 1.0], false, false);
+        figure.plot.push(Shape::line(
+            vec![[70.0, 55.0], [180.0, 30.5]],
+            FigureStroke {
+                width: 2.0,
+                color: Color32::RED,
+                dash: LineDash::Dashed,
+            },
+        ));
+        figure.plot.push(Shape::Circle {
+            center: [100.0, 40.0],
+            radius: 3.0,
+            color: Color32::BLUE,
+        });
+        figure.overlay.push(Shape::text(
+            [100.0, 20.0],
+            "a < b & (c)",
+            Color32::BLACK,
+            TextAnchor::Middle,
+        ));
+        figure.overlay.push(Shape::Text {
+            pos: [16.0, 50.0],
+            text: "y".to_string(),
+            size: FIGURE_FONT_SIZE,
+            color: AXIS_COLOR,
+            anchor: TextAnchor::Middle,
+            vertical: true,
+        });
+        figure
+    }
+
+    #[test]
+    fn tick_step_rounds_to_1_2_5() {
+        assert_eq!(tick_step([0.0, 10.0], 5), Some(2.0));
+        assert_eq!(tick_step([0.0, 1.0], 8), Some(0.2));
+        assert_eq!(tick_step([0.0, 300.0], 8), Some(50.0));
+        assert_eq!(tick_step([10.0, 0.0], 10), Some(1.0));
+        assert_eq!(tick_step([3.0, 3.0], 8), None);
+        assert_eq!(tick_step([0.0, f64::INFINITY], 8), None);
+        assert_eq!(tick_step([0.0, f64::NAN], 8), None);
+    }
+
+    #[test]
+    fn ticks_within_range() {
+        assert_eq!(ticks([-1.0, 1.0], 4), (vec![-1.0, -0.5, 0.0, 0.5, 1.0], 1));
+        assert_eq!(ticks([0.5, 2.5], 2), (vec![1.0, 2.0], 0));
+        assert_eq!(
+            ticks([0.0, 0.05], 5),
+            (vec![0.0, 0.01, 0.02, 0.03, 0.04, 0.05], 2)
+        );
+        assert_eq!(ticks([3.0, 3.0], 8), (vec![], 0));
+    }
+
+    #[test]
+    fn svg_golden() {
+        let expected = r##"<svg xmlns="http://www.w3.org/2000/svg" width="200" height="100" viewBox="0 0 200 100" font-family="Helvetica, Arial, sans-serif">
+<defs><clipPath id="plot"><rect x="70" y="30" width="110" height="25"/></clipPath></defs>
+<rect width="100%" height="100%" fill="white"/>
+<g clip-path="url(#plot)">
+<path d="M70.00,55.00 L180.00,30.50" fill="none" stroke="#ff0000" stroke-width="2.00" stroke-linejoin="round" stroke-linecap="round" stroke-dasharray="10.00 5.00"/>
+<circle cx="100.00" cy="40.00" r="3.00" fill="#0000ff"/>
+</g>
+<text x="100.00" y="20.00" font-size="12.0" fill="#000000" text-anchor="middle">a &lt; b &amp; (c)</text>
+<text x="16.00" y="50.00" font-size="12.0" fill="#3c3c3c" text-anchor="middle" transform="rotate(-90 16.00 50.00)">y</text>
+</svg>
+"##;
+        assert_eq!(figure().to_svg(), expected);
+    }
+
+    #[test]
+    fn pdf_xref_offsets() {
+        let pdf = String::from_utf8(figure().to_pdf()).unwrap();
+        assert!(pdf.starts_with("%PDF-1.4\n"));
+        assert!(pdf.ends_with("%%EOF\n"));
+
+        // startxref points to the xref table
+        let (_, startxref) = pdf.trim_end_matches("\n%%EOF\n").rsplit_once('\n').unwrap();
+        let xref: usize = startxref.parse().unwrap();
+        let table = &pdf[xref..];
+        assert!(table.starts_with("xref\n0 6\n0000000000 65535 f \n"));
+
+        // Each entry is 20 bytes and points to its object
+        let entries = &table["xref\n0 6\n".len()..];
+        for i in 1..6 {
+            let entry = &entries[i * 20..(i + 1) * 20];
+            assert!(entry.ends_with(" 00000 n \n"), "{:?}", entry);
+            let offset: usize = entry[..10].parse().unwrap();
+            assert!(pdf[offset..].starts_with(&format!("{} 0 obj\n", i)));
+        }
+
+        // The length of the content stream matches its data
+        let (_, stream) = pdf.split_once("<< /Length ").unwrap();
+        let (length, stream) = stream.split_once(" >>\nstream\n").unwrap();
+        let length: usize = length.parse().unwrap();
+        assert!(stream[length..].starts_with("endstream"));
+    }
+}
@@ -0,0 +1,125 @@
+use std::path::Path;
+
+use rust_data_inspector_signals::PlotSignals;
+
+use crate::{
+    layout::{
+        figure::{FigureFormat, FigureOptions, FigureRange},
+        layoutfile::{load_layout, save_layout},
+    },
+    state::{DataInspectorState, TabState},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileDialogKind {
+    SaveLayout,
+    LoadLayout,
+    Record,
+    /// Render the view of the tab with the provided pane id to SVG or PDF
+    Figure(u64),
+}
+
+/// Asks for the path of a file to read or write
+#[derive(Debug)]
+pub struct FileDialog {
+    pub kind: FileDialogKind,
+    pub path: String,
+    pub error: Option<String>,
+}
+
+impl FileDialog {
+    pub fn new(kind: FileDialogKind, path: String) -> Self {
+        FileDialog {
+            kind,
+            path,
+            error: None,
+        }
+    }
+
+    /// Shows the dialog. Returns true if it should be closed.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        signals: &mut PlotSignals,
+        state: &mut DataInspectorState,
+        tab_state: &mut TabState,
+    ) -> bool {
+        let (title, confirm, hint) = match self.kind {
+            FileDialogKind::SaveLayout => ("Save layout as…", "Save", "layout.ron"),
+            FileDialogKind::LoadLayout => ("Load layout…", "Load", "layout.ron"),
+            FileDialogKind::Record => ("Start recording…", "Record", "recording.rdr"),
+            FileDialogKind::Figure(_) => ("Export figure…", "Export", "figure.svg or figure.pdf"),
+        };
+
+        let mut open = true;
+        let mut done = false;
+        egui::Window::new(title)
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("File:");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.path)
+                            .hint_text(hint)
+                            .desired_width(300.0),
+                    );
+                });
+
+                if let Some(error) = &self.error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+
+                if ui.button(confirm).clicked() {
+                    let path = Path::new(&self.path);
+                    let res = match self.kind {
+                        FileDialogKind::SaveLayout => {
+                            save_layout(path, signals, state, tab_state).map_err(|e| e.to_string())
+                        }
+                        FileDialogKind::LoadLayout => {
+                            load_layout(path, signals, state, tab_state).map_err(|e| e.to_string())
+                        }
+                        FileDialogKind::Record => {
+                            signals.start_recording(path).map_err(|e| e.to_string())
+                        }
+                        FileDialogKind::Figure(pane_id) => {
+                            let options = FigureOptions::default();
+                            match tab_state
+                                .tree
+                                .iter_all_tabs_mut()
+                                .find(|(_, tab)| tab.pane_id == pane_id)
+                            {
+                                Some((_, tab)) => tab
+                                    .figure(
+                                        signals,
+                                        state,
+                                        FigureRange::View,
+                                        options.width,
+                                        options.height,
+                                    )
+                                    .write(path, FigureFormat::from_path(path))
+                                    .map_err(|e| e.to_string()),
+                                None => Err("The tab was closed".to_string()),
+                            }
+                        }
+                    };
+
+                    match res {
+                        Ok(()) => {
+                            if matches!(
+                                self.kind,
+                                FileDialogKind::SaveLayout | FileDialogKind::LoadLayout
+                            ) {
+                                state.layout_path = Some(self.path.clone());
+                            }
+                            done = true;
+                        }
+                        Err(e) => self.error = Some(e),
+                    }
+                }
+            });
+
+        done || !open
+    }
+}
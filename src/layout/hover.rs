@@ -0,0 +1,80 @@
+use egui::{Color32, RichText};
+use egui_plot::{MarkerShape, Plot, PlotPoint, PlotUi, Points};
+use rust_data_inspector_signals::PlotSignal;
+
+use crate::layout::yaxes::YTransform;
+
+const TOOLTIP_OFFSET: f32 = 16.0;
+
+/// Hides the coordinates shown next to the pointer if the values are shown by the hover readout instead
+pub fn hide_pointer_label(plot: Plot<'_>, hover_readout: bool) -> Plot<'_> {
+    if hover_readout {
+        plot.label_formatter(|_, _| String::new())
+    } else {
+        plot
+    }
+}
+
+/// Shows the values of the plotted signals at time `t`, snapping to the closest sample of each signal.
+/// If `hovered` is false, the readout is placed at the top of the plot instead of next to the pointer.
+pub fn hover_readout(
+    plot_ui: &mut PlotUi,
+    pane_id: u64,
+    t: f64,
+    hovered: bool,
+    plotted: &[(&PlotSignal, Color32, YTransform)],
+) {
+    let samples: Vec<_> = plotted
+        .iter()
+        .filter_map(|(signal, color, transform)| {
+            signal.nearest_index(t).map(|i| {
+                (
+                    *signal,
+                    *color,
+                    *transform,
+                    signal.time()[i],
+                    signal.data()[i],
+                )
+            })
+        })
+        .collect();
+
+    for (_, color, transform, time, value) in samples.iter() {
+        plot_ui.points(
+            Points::new([*time, transform.apply(*value)])
+                .color(*color)
+                .shape(MarkerShape::Circle)
+                .radius(4.0),
+        );
+    }
+
+    let pos = if hovered {
+        plot_ui.response().hover_pos()
+    } else {
+        let top = plot_ui.plot_bounds().max()[1];
+        Some(plot_ui.screen_from_plot(PlotPoint::new(t, top)))
+    };
+
+    let Some(pos) = pos else {
+        return;
+    };
+
+    let response = plot_ui.response();
+    egui::show_tooltip_at(
+        plot_ui.ctx(),
+        response.layer_id,
+        response.id.with(("hover_readout", pane_id)),
+        pos + egui::vec2(TOOLTIP_OFFSET, TOOLTIP_OFFSET),
+        |ui| {
+            ui.label(format!("t = {}", t));
+            egui::Grid::new(("hover_readout_grid", pane_id)).show(ui, |ui| {
+                for (signal, color, _, _, value) in samples {
+                    ui.label(RichText::new(signal.name()).color(color));
+                    ui.label(RichText::new(format!("{}", value)).color(color));
+                    ui.label(signal.unit().unwrap_or_default());
+                    ui.end_row();
+                }
+            });
+        },
+    );
+}
@@ -0,0 +1,170 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    path::Path,
+};
+
+use egui::Color32;
+use egui_dock::DockState;
+use rust_data_inspector_signals::{PlotSignalID, PlotSignals};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    errors::DataInspectorError,
+    layout::tabs::{Tab, TabKind},
+    state::{Annotation, DataInspectorState, SignalStyle, TabState, XAxisMode},
+};
+
+/// Layout saved to a human readable file, to be shared between sessions and users.
+/// Signals are stored by name, so that layouts can be loaded with a different set of signals.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LayoutFile {
+    pub x_axis_mode: XAxisMode,
+    pub link_x: bool,
+    pub signals: BTreeMap<String, LayoutSignal>,
+    pub tab_counter: u64,
+    pub tree: DockState<LayoutTab>,
+    #[serde(default)]
+    pub annotations: Vec<Annotation>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LayoutSignal {
+    pub color: Color32,
+    /// Tabs the signal is plotted in
+    pub tabs: BTreeSet<u64>,
+    #[serde(default)]
+    pub style: SignalStyle,
+    #[serde(default)]
+    pub style_overrides: BTreeMap<u64, SignalStyle>,
+}
+
+/// Tab of a layout file. The signals it references are stored by name next to it, and
+/// removed from the tab itself.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LayoutTab {
+    pub tab: Tab,
+    /// Signals plotted against the right axis
+    #[serde(default)]
+    pub right_axis: BTreeSet<String>,
+    /// X signal of an XY plot
+    #[serde(default)]
+    pub x_signal: Option<String>,
+}
+
+/// Writes the current layout to `path`
+pub fn save_layout(
+    path: &Path,
+    signals: &PlotSignals,
+    state: &DataInspectorState,
+    tab_state: &TabState,
+) -> Result<(), DataInspectorError> {
+    let names: HashMap<PlotSignalID, &str> = signals
+        .get_signals()
+        .iter()
+        .map(|(id, signal)| (*id, signal.name()))
+        .collect();
+    let name = |id: PlotSignalID| names.get(&id).map(|name| name.to_string());
+
+    let layout = LayoutFile {
+        x_axis_mode: state.x_axis_mode,
+        link_x: state.link_x,
+        signals: signals
+            .get_signals()
+            .iter()
+            .filter_map(|(id, signal)| {
+                let signal_state = state.signal_state.get(id)?;
+                Some((
+                    signal.name().to_string(),
+                    LayoutSignal {
+                        color: signal_state.color,
+                        tabs: signal_state.used_by_tile.clone(),
+                        style: signal_state.style,
+                        style_overrides: signal_state.style_overrides.clone(),
+                    },
+                ))
+            })
+            .collect(),
+        tab_counter: tab_state.tab_counter,
+        tree: tab_state.tree.map_tabs(|tab| {
+            let mut tab = tab.clone();
+            let right_axis = tab.y_axes.right.drain().filter_map(name).collect();
+            let x_signal = match &mut tab.kind {
+                TabKind::XY(xy) => xy.x_signal.take().and_then(name),
+                _ => None,
+            };
+            LayoutTab {
+                tab,
+                right_axis,
+                x_signal,
+            }
+        }),
+        annotations: state.annotations.clone(),
+    };
+
+    let text = ron::ser::to_string_pretty(&layout, ron::ser::PrettyConfig::default())?;
+    std::fs::write(path, text)?;
+
+    Ok(())
+}
+
+/// Reads a layout from `path`, applying it to the current signals.
+/// Signals not present in the layout are not assigned to any tab.
+pub fn load_layout(
+    path: &Path,
+    signals: &PlotSignals,
+    state: &mut DataInspectorState,
+    tab_state: &mut TabState,
+) -> Result<(), DataInspectorError> {
+    let text = std::fs::read_to_string(path)?;
+    let layout: LayoutFile = ron::from_str(&text)?;
+
+    let ids_by_name: HashMap<&str, PlotSignalID> = signals
+        .get_signals()
+        .iter()
+        .map(|(id, signal)| (signal.name(), *id))
+        .collect();
+
+    for (&name, id) in ids_by_name.iter() {
+        let Some(signal_state) = state.signal_state.get_mut(id) else {
+            continue;
+        };
+
+        match layout.signals.get(name) {
+            Some(signal) => {
+                signal_state.color = signal.color;
+                signal_state.used_by_tile = signal.tabs.clone();
+                signal_state.style = signal.style;
+                signal_state.style_overrides = signal.style_overrides.clone();
+            }
+            None => signal_state.used_by_tile.clear(),
+        }
+    }
+
+    // Signals of the tabs are mapped back to the ids of the current signals
+    let id = |name: &String| ids_by_name.get(name.as_str()).copied();
+    let tree = layout.tree.map_tabs(|layout_tab| {
+        let mut tab = layout_tab.tab.clone();
+        tab.y_axes.right = layout_tab.right_axis.iter().filter_map(id).collect();
+        if let TabKind::XY(xy) = &mut tab.kind {
+            xy.x_signal = layout_tab.x_signal.as_ref().and_then(id);
+        }
+        tab
+    });
+
+    state.x_axis_mode = layout.x_axis_mode;
+    state.link_x = layout.link_x;
+    if !layout.annotations.is_empty() {
+        state.annotations = layout.annotations;
+    }
+    state.selected_pane = tree
+        .iter_all_tabs()
+        .next()
+        .map_or(state.selected_pane, |(_, tab)| tab.pane_id);
+
+    *tab_state = TabState {
+        tree,
+        tab_counter: layout.tab_counter,
+    };
+
+    Ok(())
+}
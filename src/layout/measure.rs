@@ -0,0 +1,146 @@
+use egui::{Align2, Color32, CursorIcon, RichText};
+use egui_plot::{PlotBounds, PlotPoint, PlotUi, Text, VLine};
+use rust_data_inspector_signals::PlotSignal;
+use serde::{Deserialize, Serialize};
+
+use crate::state::{DataInspectorState, SignalData};
+
+const CURSOR_COLOR: Color32 = Color32::GOLD;
+const CURSOR_GRAB_DISTANCE: f32 = 6.0;
+const CURSOR_NAMES: [&str; 2] = ["A", "B"];
+
+/// A pair of draggable vertical cursors used to measure time and value differences
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MeasureCursors {
+    pub positions: [f64; 2],
+
+    #[serde(skip)]
+    dragged: Option<usize>,
+}
+
+impl MeasureCursors {
+    /// Places the cursors at one and two thirds of the visible range
+    pub fn new(bounds: &PlotBounds) -> Self {
+        let third = bounds.width() / 3.0;
+        MeasureCursors {
+            positions: [bounds.min()[0] + third, bounds.max()[0] - third],
+            dragged: None,
+        }
+    }
+
+    /// Keeps the position of the cursors in sync with the ones of other linked plots. Until
+    /// cursors are placed or moved, each plot keeps the positions it was restored with.
+    pub fn sync(&mut self, linked: Option<[f64; 2]>) {
+        if let Some(positions) = linked {
+            self.positions = positions;
+        }
+    }
+
+    /// Draws the cursors and moves them if dragged.
+    /// Returns true if a cursor is being dragged.
+    pub fn plot_ui(&mut self, plot_ui: &mut PlotUi) -> bool {
+        let response = plot_ui.response().clone();
+        let pointer = response.hover_pos().or(response.interact_pointer_pos());
+
+        let hovered = pointer.and_then(|pointer| {
+            self.positions
+                .iter()
+                .map(|&x| (plot_ui.screen_from_plot(PlotPoint::new(x, 0.0)).x - pointer.x).abs())
+                .enumerate()
+                .filter(|(_, d)| *d < CURSOR_GRAB_DISTANCE)
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(i, _)| i)
+        });
+
+        if response.drag_started() {
+            self.dragged = hovered;
+        }
+        if response.drag_stopped() {
+            self.dragged = None;
+        }
+
+        if let Some(i) = self.dragged {
+            if let Some(coord) = plot_ui.pointer_coordinate() {
+                self.positions[i] = coord.x;
+            }
+        }
+
+        if hovered.is_some() || self.dragged.is_some() {
+            plot_ui.ctx().set_cursor_icon(CursorIcon::ResizeHorizontal);
+        }
+
+        let top = plot_ui.plot_bounds().max()[1];
+        for (x, name) in self.positions.iter().zip(CURSOR_NAMES) {
+            plot_ui.vline(VLine::new(*x).color(CURSOR_COLOR).width(1.5));
+            plot_ui.text(
+                Text::new(PlotPoint::new(*x, top), format!(" {name}"))
+                    .color(CURSOR_COLOR)
+                    .anchor(Align2::LEFT_TOP),
+            );
+        }
+
+        self.dragged.is_some()
+    }
+
+    pub fn readout_ui(
+        &self,
+        ui: &mut egui::Ui,
+        pane_id: u64,
+        state: &DataInspectorState,
+        signals: &SignalData,
+    ) {
+        let [a, b] = self.positions;
+        let dt = b - a;
+
+        let mut plotted: Vec<&PlotSignal> = signals
+            .signals()
+            .get_signals()
+            .iter()
+            .filter(|(id, _)| {
+                state
+                    .signal_state
+                    .get(id)
+                    .is_some_and(|s| s.used_by_tile.contains(&pane_id))
+            })
+            .map(|(_, s)| s)
+            .collect();
+        plotted.sort_by_key(|s| s.name());
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            egui::Grid::new(format!("measure_readout_{}", pane_id))
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label("");
+                    ui.strong(CURSOR_NAMES[0]);
+                    ui.strong(CURSOR_NAMES[1]);
+                    ui.strong("Δ");
+                    ui.end_row();
+
+                    ui.label("t");
+                    ui.label(format_value(Some(a)));
+                    ui.label(format_value(Some(b)));
+                    ui.label(format!(
+                        "{} (1/Δt: {})",
+                        format_value(Some(dt)),
+                        format_value((dt != 0.0).then(|| 1.0 / dt))
+                    ));
+                    ui.end_row();
+
+                    for signal in plotted {
+                        let color = state.signal_state[&signal.id()].color;
+                        let (va, vb) = (signal.value_at(a), signal.value_at(b));
+
+                        ui.label(RichText::new(signal.name()).color(color));
+                        ui.label(format_value(va));
+                        ui.label(format_value(vb));
+                        ui.label(format_value(va.zip(vb).map(|(va, vb)| vb - va)));
+                        ui.end_row();
+                    }
+                });
+        });
+    }
+}
+
+fn format_value(v: Option<f64>) -> String {
+    v.map(|v| format!("{:.6}", v)).unwrap_or("-".to_string())
+}
@@ -1,2 +1,16 @@
+pub(crate) mod annotations;
+pub(crate) mod dnd;
+pub(crate) mod export;
+pub(crate) mod figure;
+pub(crate) mod filedialog;
+pub(crate) mod hover;
+pub(crate) mod layoutfile;
+pub(crate) mod measure;
+pub(crate) mod replay;
 pub(crate) mod signallist;
-pub(crate) mod tabs;
\ No newline at end of file
+pub(crate) mod table;
+pub(crate) mod tabs;
+pub(crate) mod tabsettings;
+pub(crate) mod template;
+pub(crate) mod xyplot;
+pub(crate) mod yaxes;
@@ -0,0 +1,56 @@
+use rust_data_inspector_signals::{ReplayHandle, MAX_REPLAY_SPEED, MIN_REPLAY_SPEED};
+
+/// Timeline to control the replay of a recording
+pub struct ReplayUI {}
+
+impl ReplayUI {
+    pub fn new() -> ReplayUI {
+        ReplayUI {}
+    }
+
+    pub fn ui(&self, ui: &mut egui::Ui, replay: &ReplayHandle) {
+        let [start, end] = replay.time_span();
+
+        ui.horizontal(|ui| {
+            if replay.is_playing() {
+                if ui.button("⏸").on_hover_text("Pause").clicked() {
+                    replay.pause();
+                }
+            } else if ui.button("▶").on_hover_text("Play").clicked() {
+                replay.play();
+            }
+            if ui
+                .button("⏭")
+                .on_hover_text("Step to the next sample")
+                .clicked()
+            {
+                replay.step();
+            }
+
+            let mut speed = replay.speed();
+            if ui
+                .add(
+                    egui::Slider::new(&mut speed, MIN_REPLAY_SPEED..=MAX_REPLAY_SPEED)
+                        .logarithmic(true)
+                        .suffix("×"),
+                )
+                .on_hover_text("Replay speed")
+                .changed()
+            {
+                replay.set_speed(speed);
+            }
+
+            ui.separator();
+            ui.label(format!("{:.3} / {:.3} s", replay.position(), end));
+
+            let mut position = replay.position();
+            ui.spacing_mut().slider_width = ui.available_width();
+            if ui
+                .add(egui::Slider::new(&mut position, start..=end).show_value(false))
+                .changed()
+            {
+                replay.seek(position);
+            }
+        });
+    }
+}
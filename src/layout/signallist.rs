@@ -1,8 +1,21 @@
-use egui::{CollapsingHeader, Color32};
+use std::ops::Range;
+
+use egui::{
+    collapsing_header::CollapsingState, text::LayoutJob, Checkbox, Color32, TextFormat, TextStyle,
+};
+
+use rust_data_inspector_signals::PlotSignalID;
 
 use crate::{
-    state::{DataInspectorState, SignalData, SignalNode},
-    utils::VecTree,
+    layout::dnd::signal_drag_source,
+    state::{
+        DataInspectorState, Interpolation, LineDash, SignalData, SignalNode, SignalState,
+        SignalStyle,
+    },
+    utils::{
+        pattern::{Pattern, PatternKind},
+        VecTree,
+    },
 };
 
 pub struct SignalListUI {}
@@ -13,32 +26,127 @@ impl SignalListUI {
     }
 
     pub fn ui(&self, ui: &mut egui::Ui, signals: &SignalData, state: &mut DataInspectorState) {
+        let pattern = Self::search_ui(ui, signals, state);
+
         egui::ScrollArea::vertical().show(ui, |ui| {
-            Self::ui_impl(ui, signals.signal_tree(), state, true)
+            Self::ui_impl(ui, signals.signal_tree(), state, pattern.as_ref(), true)
+        });
+    }
+
+    /// Shows the search bar, returning the pattern to filter the signals with, if any
+    fn search_ui(
+        ui: &mut egui::Ui,
+        signals: &SignalData,
+        state: &mut DataInspectorState,
+    ) -> Option<Pattern> {
+        let filter = &mut state.signal_filter;
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("signal_filter_kind")
+                .width(70.0)
+                .selected_text(format!("{:?}", filter.kind))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut filter.kind, PatternKind::Substring, "Substring");
+                    ui.selectable_value(&mut filter.kind, PatternKind::Glob, "Glob")
+                        .on_hover_text("`*` matches within a path segment, `**` across segments");
+                    ui.selectable_value(&mut filter.kind, PatternKind::Regex, "Regex");
+                });
+            ui.add(
+                egui::TextEdit::singleline(&mut filter.query)
+                    .hint_text("Search signals")
+                    .desired_width(f32::INFINITY),
+            );
+        });
+
+        if filter.query.is_empty() {
+            return None;
+        }
+
+        let pattern = match Pattern::new(filter.kind, &filter.query) {
+            Ok(pattern) => pattern,
+            Err(err) => {
+                ui.colored_label(ui.visuals().error_fg_color, err.to_string());
+                return None;
+            }
+        };
+
+        let matches: Vec<_> = signals
+            .signals()
+            .get_signals()
+            .iter()
+            .filter(|(_, signal)| pattern.is_match(signal.name()))
+            .map(|(id, _)| *id)
+            .collect();
+
+        ui.horizontal(|ui| {
+            ui.label(format!("{} matches", matches.len()));
+
+            let pane = state.selected_pane;
+            if ui
+                .button("Add all")
+                .on_hover_text(format!("Add all matches to Tab {}", pane))
+                .clicked()
+            {
+                for id in matches.iter() {
+                    if let Some(signal_state) = state.signal_state.get_mut(id) {
+                        signal_state.used_by_tile.insert(pane);
+                    }
+                }
+            }
+            if ui
+                .button("Remove all")
+                .on_hover_text(format!("Remove all matches from Tab {}", pane))
+                .clicked()
+            {
+                for id in matches.iter() {
+                    if let Some(signal_state) = state.signal_state.get_mut(id) {
+                        signal_state.used_by_tile.remove(&pane);
+                    }
+                }
+            }
         });
+        ui.separator();
+
+        Some(pattern)
     }
 
     fn ui_impl(
         ui: &mut egui::Ui,
         node: &VecTree<SignalNode>,
         state: &mut DataInspectorState,
+        pattern: Option<&Pattern>,
         is_root: bool,
     ) {
+        // Ranges of the node path to highlight
+        let highlights = match pattern {
+            Some(pattern) => match subtree_matches(node, pattern) {
+                Some(ranges) => ranges,
+                None => return,
+            },
+            None => vec![],
+        };
+        let label = highlighted_label(ui, &node.value, &highlights);
+
         if node.children.is_empty() {
             let id = node.value.signal.unwrap();
             let signal_state = state.signal_state.get_mut(&id).unwrap();
 
-            let col = &mut signal_state.color;
+            let col = signal_state.color;
             let mut srgb = [col.r(), col.g(), col.b()];
 
             let selected = signal_state.used_by_tile.contains(&state.selected_pane);
             let mut selected_mut = selected;
             ui.horizontal(|ui| {
                 ui.color_edit_button_srgb(&mut srgb);
-                ui.toggle_value(&mut selected_mut, node.value.name.clone());
+                ui.menu_button("✏", |ui| {
+                    Self::style_ui(ui, signal_state, state.selected_pane);
+                })
+                .response
+                .on_hover_text("Line style");
+                let response = ui.toggle_value(&mut selected_mut, label);
+                signal_drag_source(ui, &response, || vec![id]);
             });
 
-            *col = Color32::from_rgb(srgb[0], srgb[1], srgb[2]);
+            signal_state.color = Color32::from_rgb(srgb[0], srgb[1], srgb[2]);
             if selected_mut != selected {
                 // Value was changed
                 if selected_mut {
@@ -49,17 +157,200 @@ impl SignalListUI {
             }
         } else if is_root {
             for child in node.children.iter() {
-                Self::ui_impl(ui, child, state, false);
+                Self::ui_impl(ui, child, state, pattern, false);
             }
         } else {
-            CollapsingHeader::new(node.value.name.clone())
-                .id_source(node.value.path.clone())
-                .default_open(true)
-                .show(ui, |ui| {
-                    for child in node.children.iter() {
-                        Self::ui_impl(ui, child, state, false);
+            let pane = state.selected_pane;
+            let subtree = subtree_signals(node);
+            let in_pane = subtree
+                .iter()
+                .filter(|id| {
+                    state
+                        .signal_state
+                        .get(id)
+                        .is_some_and(|s| s.used_by_tile.contains(&pane))
+                })
+                .count();
+
+            let id = ui.make_persistent_id(&node.value.path);
+            let mut collapsing = CollapsingState::load_with_default_open(ui.ctx(), id, true);
+            if pattern.is_some() {
+                // Show all matches while searching
+                collapsing.set_open(true);
+            }
+
+            let mut all = in_pane == subtree.len();
+            let some = in_pane > 0 && !all;
+            let mut label_clicked = false;
+            let mut header = collapsing.show_header(ui, |ui| {
+                let changed = ui
+                    .add(Checkbox::without_text(&mut all).indeterminate(some))
+                    .on_hover_text(format!("{}/{} in Tab {}", in_pane, subtree.len(), pane))
+                    .changed();
+
+                let response = ui.add(
+                    egui::Label::new(label)
+                        .selectable(false)
+                        .sense(egui::Sense::click()),
+                );
+                label_clicked = response.clicked();
+                signal_drag_source(ui, &response, || subtree.clone());
+
+                changed
+            });
+            if label_clicked {
+                header.toggle();
+            }
+            let (_, changed, _) = header.body(|ui| {
+                for child in node.children.iter() {
+                    Self::ui_impl(ui, child, state, pattern, false);
+                }
+            });
+
+            if changed.inner {
+                for id in subtree.iter() {
+                    if let Some(signal_state) = state.signal_state.get_mut(id) {
+                        if all {
+                            signal_state.used_by_tile.insert(pane);
+                        } else {
+                            signal_state.used_by_tile.remove(&pane);
+                        }
                     }
-                });
+                }
+            }
         }
     }
+
+    fn style_ui(ui: &mut egui::Ui, signal_state: &mut SignalState, pane: u64) {
+        let mut overridden = signal_state.style_overrides.contains_key(&pane);
+        if ui
+            .checkbox(&mut overridden, format!("Override for Tab {}", pane))
+            .changed()
+        {
+            if overridden {
+                signal_state
+                    .style_overrides
+                    .insert(pane, signal_state.style);
+            } else {
+                signal_state.style_overrides.remove(&pane);
+            }
+        }
+        ui.separator();
+
+        let style = match signal_state.style_overrides.get_mut(&pane) {
+            Some(style) => style,
+            None => &mut signal_state.style,
+        };
+
+        egui::Grid::new("signal_style").show(ui, |ui| {
+            ui.label("Width");
+            ui.add(egui::Slider::new(&mut style.width, 0.5..=8.0));
+            ui.end_row();
+
+            ui.label("Line");
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut style.dash, LineDash::Solid, "Solid");
+                ui.selectable_value(&mut style.dash, LineDash::Dashed, "Dashed");
+                ui.selectable_value(&mut style.dash, LineDash::Dotted, "Dotted");
+            });
+            ui.end_row();
+
+            ui.label("Interpolation");
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut style.interpolation, Interpolation::Linear, "Linear");
+                ui.selectable_value(
+                    &mut style.interpolation,
+                    Interpolation::StepBefore,
+                    "Step before",
+                );
+                ui.selectable_value(
+                    &mut style.interpolation,
+                    Interpolation::StepAfter,
+                    "Step after",
+                );
+            });
+            ui.end_row();
+        });
+
+        ui.checkbox(&mut style.markers, "Show markers");
+        ui.checkbox(&mut style.points_only, "Points only");
+        ui.checkbox(&mut style.fill, "Fill to zero");
+
+        if ui.button("Reset").clicked() {
+            *style = SignalStyle::default();
+        }
+    }
+}
+
+/// Returns all the signals in the subtree of `node`
+pub fn subtree_signals(node: &VecTree<SignalNode>) -> Vec<PlotSignalID> {
+    let mut signals: Vec<_> = node.value.signal.into_iter().collect();
+    for child in node.children.iter() {
+        signals.extend(subtree_signals(child));
+    }
+    signals
+}
+
+/// Returns the ranges of the signal paths matched by `pattern` in the subtree of `node`,
+/// or `None` if no signal matches.
+/// Since the path of a node is a prefix of the ones of its children, ranges are valid for all of them.
+fn subtree_matches(node: &VecTree<SignalNode>, pattern: &Pattern) -> Option<Vec<Range<usize>>> {
+    if node.children.is_empty() {
+        let path = &node.value.path;
+        return pattern.is_match(path).then(|| pattern.match_ranges(path));
+    }
+
+    node.children
+        .iter()
+        .filter_map(|child| subtree_matches(child, pattern))
+        .reduce(|mut a, b| {
+            a.extend(b);
+            a
+        })
+}
+
+/// Name of the node, highlighting the parts overlapping `ranges`
+fn highlighted_label(
+    ui: &egui::Ui,
+    node: &SignalNode,
+    ranges: &[Range<usize>],
+) -> egui::WidgetText {
+    if ranges.is_empty() {
+        return node.name.clone().into();
+    }
+
+    // Position of the node name in its path
+    let start = node.path.trim_end_matches('/').len() - node.name.len();
+    let mut highlighted = vec![false; node.name.len()];
+    for range in ranges {
+        let from = range.start.max(start).min(start + node.name.len()) - start;
+        let to = range.end.max(start).min(start + node.name.len()) - start;
+        highlighted[from..to].fill(true);
+    }
+
+    let style = ui.style();
+    let normal = TextFormat {
+        font_id: TextStyle::Button.resolve(style),
+        color: style.visuals.text_color(),
+        ..Default::default()
+    };
+    let strong = TextFormat {
+        background: style.visuals.selection.bg_fill,
+        color: style.visuals.strong_text_color(),
+        ..normal.clone()
+    };
+
+    let mut job = LayoutJob::default();
+    let mut i = 0;
+    while i < highlighted.len() {
+        let end = highlighted[i..]
+            .iter()
+            .position(|h| *h != highlighted[i])
+            .map_or(highlighted.len(), |n| i + n);
+        let format = if highlighted[i] { &strong } else { &normal };
+        job.append(&node.name[i..end], 0.0, format.clone());
+        i = end;
+    }
+
+    job.into()
 }
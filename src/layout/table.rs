@@ -0,0 +1,245 @@
+use std::fmt::Write;
+
+use egui::{Align2, Event, Sense, TextStyle};
+use rust_data_inspector_signals::{PlotSignal, PlotSignalID};
+use serde::{Deserialize, Serialize};
+
+use crate::state::{DataInspectorState, SignalData};
+
+const COLUMN_WIDTH: f32 = 140.0;
+
+/// Lists the samples of the signals assigned to the tab, one row per distinct timestamp.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct SampleTable {
+    #[serde(skip)]
+    rows: TableRows,
+
+    /// Anchor and last selected row
+    #[serde(skip)]
+    selection: Option<(usize, usize)>,
+
+    #[serde(skip)]
+    scroll_to: Option<usize>,
+}
+
+/// Union of the timestamps of the displayed signals, updated incrementally as new samples arrive
+#[derive(Debug, Default, Clone)]
+struct TableRows {
+    signals: Vec<PlotSignalID>,
+    lengths: Vec<usize>,
+    time: Vec<f64>,
+}
+
+impl TableRows {
+    fn update(&mut self, signals: Vec<&PlotSignal>) {
+        let ids: Vec<_> = signals.iter().map(|s| s.id()).collect();
+        let lengths: Vec<_> = signals.iter().map(|s| s.time().len()).collect();
+
+        let rebuild = ids != self.signals
+            || lengths
+                .iter()
+                .zip(&self.lengths)
+                .any(|(new, old)| new < old);
+
+        if rebuild {
+            self.signals = ids;
+            self.lengths = vec![0; signals.len()];
+            self.time.clear();
+        }
+
+        let mut new_times: Vec<f64> = signals
+            .iter()
+            .zip(&self.lengths)
+            .flat_map(|(s, &len)| s.time()[len..].iter().copied())
+            .collect();
+        self.lengths = lengths;
+
+        if new_times.is_empty() {
+            return;
+        }
+
+        new_times.sort_unstable_by(f64::total_cmp);
+
+        // Only merge the tail of the rows that overlaps with the new samples
+        let start = self.time.partition_point(|&t| t < new_times[0]);
+        let mut merged = self.time.split_off(start);
+        merged.append(&mut new_times);
+        merged.sort_unstable_by(f64::total_cmp);
+        merged.dedup();
+
+        self.time.append(&mut merged);
+    }
+}
+
+impl SampleTable {
+    pub fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        pane_id: u64,
+        state: &mut DataInspectorState,
+        signals: &mut SignalData,
+    ) {
+        let mut columns: Vec<&PlotSignal> = signals
+            .signals()
+            .get_signals()
+            .iter()
+            .filter(|(id, _)| {
+                state
+                    .signal_state
+                    .get(id)
+                    .is_some_and(|s| s.used_by_tile.contains(&pane_id))
+            })
+            .map(|(_, s)| s)
+            .collect();
+        columns.sort_by_key(|s| s.name());
+
+        self.rows.update(columns.clone());
+
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(
+                    state.pointer_time.is_some(),
+                    egui::Button::new("Jump to cursor"),
+                )
+                .on_hover_text("Scroll to the last time hovered in a time plot")
+                .clicked()
+            {
+                if let Some(t) = state.pointer_time {
+                    let row = self.rows.time.partition_point(|&v| v < t);
+                    self.scroll_to = Some(row.min(self.rows.time.len().saturating_sub(1)));
+                }
+            }
+
+            if ui
+                .add_enabled(self.selection.is_some(), egui::Button::new("Copy CSV"))
+                .clicked()
+            {
+                self.copy_selection(ui.ctx(), &columns);
+            }
+
+            ui.label(format!("{} rows", self.rows.time.len()));
+        });
+
+        let text_style = TextStyle::Monospace;
+        let font_id = text_style.resolve(ui.style());
+        let row_height = ui.text_style_height(&text_style);
+        let row_width = COLUMN_WIDTH * (columns.len() + 1) as f32;
+
+        let visible_range = state.x_range;
+        let text_color = ui.visuals().text_color();
+        let highlight_color = ui.visuals().faint_bg_color;
+        let selection_color = ui.visuals().selection.bg_fill;
+        let scroll_to = self.scroll_to.take();
+
+        egui::ScrollArea::horizontal()
+            .id_salt(format!("table_h_{}", pane_id))
+            .auto_shrink(false)
+            .show(ui, |ui| {
+                // Header
+                let (rect, _) =
+                    ui.allocate_exact_size(egui::vec2(row_width, row_height), Sense::hover());
+                for (i, name) in std::iter::once("time")
+                    .chain(columns.iter().map(|s| s.name()))
+                    .enumerate()
+                {
+                    ui.painter().text(
+                        rect.left_center() + egui::vec2(COLUMN_WIDTH * i as f32, 0.0),
+                        Align2::LEFT_CENTER,
+                        name,
+                        font_id.clone(),
+                        ui.visuals().strong_text_color(),
+                    );
+                }
+                ui.separator();
+
+                let mut scroll_area = egui::ScrollArea::vertical()
+                    .id_salt(format!("table_v_{}", pane_id))
+                    .auto_shrink(false);
+
+                if let Some(row) = scroll_to {
+                    scroll_area = scroll_area.vertical_scroll_offset(
+                        row as f32 * (row_height + ui.spacing().item_spacing.y),
+                    );
+                }
+
+                scroll_area.show_rows(ui, row_height, self.rows.time.len(), |ui, row_range| {
+                    let shift = ui.input(|i| i.modifiers.shift);
+
+                    for row in row_range {
+                        let t = self.rows.time[row];
+                        let (rect, response) = ui
+                            .allocate_exact_size(egui::vec2(row_width, row_height), Sense::click());
+
+                        if response.clicked() {
+                            self.selection = match self.selection {
+                                Some((anchor, _)) if shift => Some((anchor, row)),
+                                _ => Some((row, row)),
+                            };
+                        }
+
+                        let selected = self
+                            .selection
+                            .is_some_and(|(a, b)| (a.min(b)..=a.max(b)).contains(&row));
+
+                        if selected {
+                            ui.painter().rect_filled(rect, 0.0, selection_color);
+                        } else if visible_range.is_some_and(|[min, max]| t >= min && t <= max) {
+                            ui.painter().rect_filled(rect, 0.0, highlight_color);
+                        }
+
+                        let cells =
+                            std::iter::once(Some(t)).chain(columns.iter().map(|s| sample_at(s, t)));
+                        for (i, value) in cells.enumerate() {
+                            if let Some(value) = value {
+                                ui.painter().text(
+                                    rect.left_center() + egui::vec2(COLUMN_WIDTH * i as f32, 0.0),
+                                    Align2::LEFT_CENTER,
+                                    value.to_string(),
+                                    font_id.clone(),
+                                    text_color,
+                                );
+                            }
+                        }
+                    }
+                });
+            });
+
+        let copy = ui.input(|i| i.events.iter().any(|e| matches!(e, Event::Copy)));
+        if copy && ui.ui_contains_pointer() && self.selection.is_some() {
+            self.copy_selection(ui.ctx(), &columns);
+        }
+    }
+
+    fn copy_selection(&self, ctx: &egui::Context, columns: &[&PlotSignal]) {
+        let Some((a, b)) = self.selection else {
+            return;
+        };
+
+        let mut csv = std::iter::once("time")
+            .chain(columns.iter().map(|s| s.name()))
+            .collect::<Vec<_>>()
+            .join(",");
+        csv.push('\n');
+
+        for &t in self.rows.time.get(a.min(b)..=a.max(b)).unwrap_or_default() {
+            let _ = write!(csv, "{}", t);
+            for signal in columns {
+                csv.push(',');
+                if let Some(v) = sample_at(signal, t) {
+                    let _ = write!(csv, "{}", v);
+                }
+            }
+            csv.push('\n');
+        }
+
+        ctx.copy_text(csv);
+    }
+}
+
+/// Returns the value of the sample with exactly time `t`, if any
+fn sample_at(signal: &PlotSignal, t: f64) -> Option<f64> {
+    signal
+        .index_at(t)
+        .filter(|&i| signal.time()[i] == t)
+        .map(|i| signal.data()[i])
+}
@@ -1,38 +1,143 @@
 use std::{collections::HashMap, ops::Range};
 
 use downsample_rs::lttb_with_x;
-use egui::{Event, Vec2, Vec2b};
-use egui_dock::{NodeIndex, SurfaceIndex};
-use egui_plot::{Legend, Line, PlotBounds, PlotPoints};
-use rust_data_inspector_signals::{PlotSignal, PlotSignalID};
+use egui::{Color32, Event, Vec2, Vec2b};
+use egui_dock::{NodeIndex, Split, SurfaceIndex};
+use egui_plot::{Line, LineStyle, PlotBounds, PlotPoint, PlotPoints, Points, VLine};
+use rust_data_inspector_signals::{PlotSignal, PlotSignalID, PlotSignals};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    state::{DataInspectorState, SignalData, XAxisMode},
+    layout::{
+        annotations::{annotation_menu, draw_annotations},
+        dnd::{signal_drop_target, DropZone},
+        figure::{lighten, Figure, FigureRange, FigureStroke, Shape},
+        hover::{hide_pointer_label, hover_readout},
+        measure::MeasureCursors,
+        table::SampleTable,
+        tabsettings::TabSettings,
+        xyplot::XYPlot,
+        yaxes::{YAxes, YAxisLayout, YTransform},
+    },
+    state::{DataInspectorState, Interpolation, LineDash, SignalData, SignalStyle, XAxisMode},
     utils::downsampling::{decimate, DownsamplingMethod},
 };
 
 const DEFAULT_PLOT_WIDTH: f64 = 30.0;
 const PLOT_MARGIN_PC: f64 = 0.01;
+const MARKER_RADIUS_EXTRA: f32 = 1.0;
+/// Number of samples summarized by each cached extent of a signal
+const EXTENT_BLOCK_LEN: usize = 1024;
 
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct Tab {
     pub pane_id: u64,
+    #[serde(default)]
+    pub kind: TabKind,
+    #[serde(default)]
+    pub measure: Option<MeasureCursors>,
+    #[serde(default)]
+    pub y_axes: YAxes,
+    #[serde(default)]
+    pub settings: TabSettings,
+    /// Last x and y range shown by a time plot
+    #[serde(default)]
+    pub view: Option<[[f64; 2]; 2]>,
 
     #[serde(skip)]
     cache: HashMap<PlotSignalID, SignalPlotCache>,
+
+    /// Point where the context menu was opened
+    #[serde(skip)]
+    context_point: Option<PlotPoint>,
+    #[serde(skip)]
+    annotation_label: String,
+    #[serde(skip)]
+    pending_region: Option<f64>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub enum TabKind {
+    #[default]
+    Time,
+    XY(XYPlot),
+    Table(SampleTable),
 }
 
 #[derive(Debug, Default, Clone)]
 struct SignalPlotCache {
     last_visible_range: Range<usize>,
+    /// Minimum and maximum value of each full block of `EXTENT_BLOCK_LEN` samples
+    blocks: Vec<Option<[f64; 2]>>,
+}
+
+impl SignalPlotCache {
+    /// Updates the visible range, returning the extent of the data within it.
+    /// The extent of full blocks of samples is cached, so that only the samples of the partial
+    /// blocks at both ends of the range are scanned, including when it slides as in follow mode.
+    fn update(&mut self, signal: &PlotSignal, range: Range<usize>) -> Option<[f64; 2]> {
+        let data = signal.data();
+        while (self.blocks.len() + 1) * EXTENT_BLOCK_LEN <= data.len() {
+            let start = self.blocks.len() * EXTENT_BLOCK_LEN;
+            let block = extent(&data[start..start + EXTENT_BLOCK_LEN]);
+            self.blocks.push(block);
+        }
+
+        let first_block = range.start.div_ceil(EXTENT_BLOCK_LEN);
+        let last_block = range.end / EXTENT_BLOCK_LEN;
+        let extent = if first_block < last_block {
+            [
+                extent(&data[range.start..first_block * EXTENT_BLOCK_LEN]),
+                extent(&data[last_block * EXTENT_BLOCK_LEN..range.end]),
+            ]
+            .into_iter()
+            .chain(self.blocks[first_block..last_block].iter().copied())
+            .flatten()
+            .reduce(|a, b| [a[0].min(b[0]), a[1].max(b[1])])
+        } else {
+            extent(&data[range.clone()])
+        };
+
+        self.last_visible_range = range;
+        extent
+    }
+}
+
+/// Minimum and maximum of the finite values of `data`
+fn extent(data: &[f64]) -> Option<[f64; 2]> {
+    data.iter()
+        .filter(|v| v.is_finite())
+        .fold(None, |extent, &v| match extent {
+            Some([min, max]) => Some([f64::min(min, v), f64::max(max, v)]),
+            None => Some([v, v]),
+        })
 }
 
 impl Tab {
     pub fn new(tab_id: u64) -> Self {
+        Self::with_kind(tab_id, TabKind::Time)
+    }
+
+    pub fn with_kind(tab_id: u64, kind: TabKind) -> Self {
         Tab {
             pane_id: tab_id,
+            kind,
+            measure: None,
+            y_axes: YAxes::default(),
+            settings: TabSettings::default(),
+            view: None,
             cache: HashMap::new(),
+            context_point: None,
+            annotation_label: String::new(),
+            pending_region: None,
+        }
+    }
+
+    pub fn default_title(&self) -> String {
+        match self.kind {
+            TabKind::Time => format!("Tab {}", self.pane_id),
+            TabKind::XY(_) => format!("XY {}", self.pane_id),
+            TabKind::Table(_) => format!("Table {}", self.pane_id),
         }
     }
 
@@ -46,6 +151,20 @@ impl Tab {
         state: &mut DataInspectorState,
         signals: &mut SignalData,
         link_x_translated: &mut bool,
+    ) {
+        match self.kind {
+            TabKind::Time => self.time_plot_ui(ui, state, signals, link_x_translated),
+            TabKind::XY(ref mut xy) => xy.ui(ui, self.pane_id, &self.settings, state, signals),
+            TabKind::Table(ref mut table) => table.ui(ui, self.pane_id, state, signals),
+        }
+    }
+
+    fn time_plot_ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        state: &mut DataInspectorState,
+        signals: &mut SignalData,
+        link_x_translated: &mut bool,
     ) {
         let (scroll, pointer_down, modifiers) = ui.input(|i| {
             let scroll = i.events.iter().find_map(|e| match e {
@@ -59,7 +178,19 @@ impl Tab {
             (scroll, i.pointer.primary_down(), i.modifiers)
         });
 
-        egui_plot::Plot::new(format!("plot_{}", self.pane_id))
+        if let Some(measure) = &mut self.measure {
+            if state.link_x {
+                measure.sync(state.linked_cursors);
+            }
+
+            egui::TopBottomPanel::bottom(format!("measure_{}", self.pane_id))
+                .resizable(true)
+                .show_inside(ui, |ui| {
+                    measure.readout_ui(ui, self.pane_id, state, signals)
+                });
+        }
+
+        let plot = egui_plot::Plot::new(format!("plot_{}", self.pane_id))
             .allow_drag(false)
             .allow_zoom(false)
             .allow_scroll(false)
@@ -71,7 +202,9 @@ impl Tab {
                 false),
             )
             .auto_bounds(Vec2b::FALSE)
-            .legend(Legend::default())
+            .custom_y_axes(self.y_axes.axis_hints());
+
+        let plot_response = hide_pointer_label(self.settings.apply(plot), state.hover_readout)
             .show(ui, |plot_ui| {
                 let plot_rect_width = plot_ui
                     .screen_from_plot(plot_ui.plot_bounds().max().into())
@@ -80,6 +213,7 @@ impl Tab {
                         .screen_from_plot(plot_ui.plot_bounds().min().into())
                         .x as usize;
 
+                let mut visible = vec![];
                 for (id, signal) in signals.signals().get_signals() {
                     if let Some(sig_state) = state.signal_state.get(id) {
                         if sig_state.used_by_tile.contains(&self.pane_id) {
@@ -93,29 +227,51 @@ impl Tab {
                                 self.cache.get(id),
                             );
 
-                            if let Some(range) = range {
-                                self.cache.insert(
-                                    *id,
-                                    SignalPlotCache {
-                                        last_visible_range: range.clone(),
-                                    },
-                                );
-                                let time = signal.time().get(range.clone()).unwrap();
-                                let data = signal.data().get(range.clone()).unwrap();
-                                let points = Self::downsample(
-                                    time,
-                                    data,
-                                    plot_rect_width,
-                                    state.downsample_mode,
-                                );
-
-                                plot_ui.line(
-                                    Line::new(points).color(sig_state.color).name(signal.name()),
-                                );
-                            }
+                            let extent = range.clone().and_then(|range| {
+                                let cache = self.cache.entry(*id).or_default();
+                                cache.update(signal, range)
+                            });
+
+                            visible.push((signal, sig_state.color, range, extent));
                         }
                     }
                 }
+                visible.sort_by_key(|(signal, ..)| signal.name());
+
+                let extents: Vec<_> = visible.iter().map(|(s, _, _, e)| (*s, *e)).collect();
+
+                let bounds = plot_ui.plot_bounds();
+                if let Some([min, max]) = self
+                    .y_axes
+                    .y_bounds([bounds.min()[1], bounds.max()[1]], &extents)
+                {
+                    plot_ui.set_plot_bounds(PlotBounds::from_min_max(
+                        [bounds.min()[0], min],
+                        [bounds.max()[0], max],
+                    ));
+                }
+
+                let transforms = self.y_axes.transforms(&extents, &plot_ui.plot_bounds());
+
+                let mut plotted = vec![];
+                for ((signal, color, range, _), transform) in visible.into_iter().zip(transforms) {
+                    plotted.push((signal, color, transform));
+
+                    if let Some(range) = range {
+                        let time = signal.time().get(range.clone()).unwrap();
+                        let data = signal.data().get(range.clone()).unwrap();
+                        let style = *state.signal_state[&signal.id()].style_for(self.pane_id);
+                        let points = Self::downsample(
+                            time,
+                            data,
+                            plot_rect_width,
+                            state.downsample_mode,
+                            transform,
+                        );
+
+                        Self::plot_signal(plot_ui, signal.name(), color, &style, points, transform);
+                    }
+                }
 
                 let time_span = signals.time_span();
                 // Plot mode transformations
@@ -157,8 +313,59 @@ impl Tab {
                     XAxisMode::Free => {}
                 }
 
+                if let Some([min, max]) = state.x_range_request {
+                    let bounds = plot_ui.plot_bounds();
+                    plot_ui.set_plot_bounds(PlotBounds::from_min_max(
+                        [min, bounds.min()[1]],
+                        [max, bounds.max()[1]],
+                    ));
+                }
+
+                draw_annotations(plot_ui, &state.annotations);
+                if let Some(start) = self.pending_region {
+                    plot_ui.vline(VLine::new(start).style(LineStyle::dashed_dense()));
+                }
+
+                let bounds = plot_ui.plot_bounds();
+                state.x_range = Some([bounds.min()[0], bounds.max()[0]]);
+                self.view = Some([
+                    [bounds.min()[0], bounds.max()[0]],
+                    [bounds.min()[1], bounds.max()[1]],
+                ]);
+
+                if state.hover_readout {
+                    let pointer = plot_ui
+                        .pointer_coordinate()
+                        .filter(|_| plot_ui.response().hovered());
+
+                    if let Some(pointer) = pointer {
+                        state.next_hover_time = Some((self.pane_id, pointer.x));
+                        hover_readout(plot_ui, self.pane_id, pointer.x, true, &plotted);
+                    } else if let Some((pane_id, t)) = state.hover_time {
+                        if pane_id != self.pane_id {
+                            hover_readout(plot_ui, self.pane_id, t, false, &plotted);
+                        }
+                    }
+                }
+
+                let mut dragging_cursor = false;
+                if let Some(measure) = &mut self.measure {
+                    dragging_cursor = measure.plot_ui(plot_ui);
+                    if dragging_cursor && state.link_x {
+                        state.linked_cursors = Some(measure.positions);
+                    }
+                }
+
+                if plot_ui.response().secondary_clicked() {
+                    self.context_point = plot_ui.pointer_coordinate();
+                }
+
                 // User interaction transformations
                 if plot_ui.response().hovered() {
+                    if let Some(pointer) = plot_ui.pointer_coordinate() {
+                        state.pointer_time = Some(pointer.x);
+                    }
+
                     if let Some(mut scroll) = scroll {
                         scroll = Vec2::splat(scroll.x + scroll.y);
                         let mut zoom_factor =
@@ -185,7 +392,7 @@ impl Tab {
                         }
                     }
 
-                    if pointer_down {
+                    if pointer_down && !dragging_cursor {
                         let mut pointer_translate = -plot_ui.pointer_coordinate_drag_delta();
                         if state.x_axis_mode != XAxisMode::Free {
                             pointer_translate.x = 0.0;
@@ -195,6 +402,34 @@ impl Tab {
                     }
                 }
             });
+
+        plot_response.response.context_menu(|ui| {
+            if self.measure.is_none() {
+                if ui.button("Place measurement cursors").clicked() {
+                    let measure = MeasureCursors::new(plot_response.transform.bounds());
+                    if state.link_x {
+                        state.linked_cursors = Some(measure.positions);
+                    }
+                    self.measure = Some(measure);
+                    ui.close_menu();
+                }
+            } else if ui.button("Remove measurement cursors").clicked() {
+                self.measure = None;
+                ui.close_menu();
+            }
+
+            if let Some(point) = self.context_point {
+                ui.separator();
+                annotation_menu(
+                    ui,
+                    point,
+                    self.measure.as_ref().map(|m| m.positions),
+                    &mut self.annotation_label,
+                    &mut self.pending_region,
+                    state,
+                );
+            }
+        });
     }
 
     fn downsample(
@@ -202,7 +437,8 @@ impl Tab {
         data: &[f64],
         rec_width: usize,
         mode: DownsamplingMethod,
-    ) -> PlotPoints {
+        transform: YTransform,
+    ) -> Vec<[f64; 2]> {
         let indices = match mode {
             DownsamplingMethod::Decimation => decimate(time, rec_width * 2),
             DownsamplingMethod::Lttb => lttb_with_x(time, data, rec_width * 2),
@@ -210,8 +446,230 @@ impl Tab {
 
         indices
             .into_iter()
-            .map(|i| [time[i], data[i]])
-            .collect::<PlotPoints>()
+            .map(|i| [time[i], transform.apply(data[i])])
+            .collect()
+    }
+
+    /// Draws the samples of a signal with the provided style
+    fn plot_signal(
+        plot_ui: &mut egui_plot::PlotUi,
+        name: &str,
+        color: Color32,
+        style: &SignalStyle,
+        points: Vec<[f64; 2]>,
+        transform: YTransform,
+    ) {
+        if style.points_only {
+            plot_ui.points(
+                Points::new(points)
+                    .color(color)
+                    .radius(style.width + MARKER_RADIUS_EXTRA)
+                    .name(name),
+            );
+            return;
+        }
+
+        let line_points = match style.interpolation {
+            Interpolation::Linear => points.clone(),
+            Interpolation::StepAfter => step_points(&points, |prev, next| [next[0], prev[1]]),
+            Interpolation::StepBefore => step_points(&points, |prev, next| [prev[0], next[1]]),
+        };
+
+        let mut line = Line::new(PlotPoints::from(line_points))
+            .color(color)
+            .width(style.width)
+            .name(name);
+
+        line = match style.dash {
+            LineDash::Solid => line,
+            LineDash::Dashed => line.style(LineStyle::dashed_loose()),
+            LineDash::Dotted => line.style(LineStyle::dotted_dense()),
+        };
+
+        if style.fill {
+            line = line.fill(transform.apply(0.0) as f32);
+        }
+
+        plot_ui.line(line);
+
+        if style.markers {
+            plot_ui.points(
+                Points::new(points)
+                    .color(color)
+                    .radius(style.width + MARKER_RADIUS_EXTRA),
+            );
+        }
+    }
+
+    /// Renders the view of a time plot to a figure, with the same downsampling and styles as the plot
+    pub fn figure(
+        &mut self,
+        signals: &PlotSignals,
+        state: &DataInspectorState,
+        range: FigureRange,
+        width: f32,
+        height: f32,
+    ) -> Figure {
+        let mut plotted: Vec<&PlotSignal> = signals
+            .get_signals()
+            .values()
+            .filter(|s| {
+                state
+                    .signal_state
+                    .get(&s.id())
+                    .is_some_and(|st| st.used_by_tile.contains(&self.pane_id))
+            })
+            .collect();
+        plotted.sort_by_key(|s| s.name());
+
+        let full = plotted
+            .iter()
+            .filter_map(|s| Some([*s.time().first()?, *s.time().last()?]))
+            .reduce(|a, b| [a[0].min(b[0]), a[1].max(b[1])])
+            .unwrap_or([0.0, DEFAULT_PLOT_WIDTH]);
+        let (mut x, view_y) = match (range, self.view) {
+            (FigureRange::View, Some([x, y])) => (x, Some(y)),
+            (FigureRange::Custom(x), _) => (x, None),
+            _ => (full, None),
+        };
+        if x[1] <= x[0] {
+            x = [x[0] - 0.5, x[0] + 0.5];
+        }
+
+        let current_y = view_y.unwrap_or([0.0, 1.0]);
+        let bounds = PlotBounds::from_min_max([x[0], current_y[0]], [x[1], current_y[1]]);
+        let visible: Vec<_> = plotted
+            .into_iter()
+            .map(|signal| {
+                let range = Self::find_visible_range(signal, &bounds, None);
+                let extent = range
+                    .clone()
+                    .and_then(|range| SignalPlotCache::default().update(signal, range));
+                (signal, range, extent)
+            })
+            .collect();
+        let extents: Vec<_> = visible.iter().map(|(s, _, e)| (*s, *e)).collect();
+
+        // Without a view to reproduce, manual axes fit the data
+        let mut y = self
+            .y_axes
+            .y_bounds(current_y, &extents)
+            .or(view_y)
+            .or_else(|| self.y_axes.fit_bounds(&extents))
+            .unwrap_or([0.0, 1.0]);
+        if y[1] <= y[0] {
+            y = [y[0] - 0.5, y[0] + 0.5];
+        }
+
+        let bounds = PlotBounds::from_min_max([x[0], y[0]], [x[1], y[1]]);
+        let transforms = self.y_axes.transforms(&extents, &bounds);
+        let right = match self.y_axes.layout {
+            YAxisLayout::Dual => self.y_axes.right_range,
+            _ => None,
+        };
+        let y_ticks = matches!(self.y_axes.layout, YAxisLayout::Shared | YAxisLayout::Dual);
+
+        let settings = &self.settings;
+        let mut figure = Figure::new(
+            width,
+            height,
+            x,
+            y,
+            !settings.y_label.is_empty(),
+            right.is_some(),
+        );
+        figure.draw_axes(
+            &settings.title(|| self.default_title()),
+            &settings.x_label,
+            &settings.y_label,
+            settings.show_grid,
+            y_ticks,
+            right,
+        );
+
+        let plot_rect_width = figure.axes.rect[2] as usize;
+        let mut legend = vec![];
+        for ((signal, range, _), transform) in visible.into_iter().zip(transforms) {
+            let color = state.signal_state[&signal.id()].color;
+            let style = *state.signal_state[&signal.id()].style_for(self.pane_id);
+            legend.push((signal.name().to_string(), color, style.dash));
+
+            if let Some(range) = range {
+                let points = Self::downsample(
+                    &signal.time()[range.clone()],
+                    &signal.data()[range],
+                    plot_rect_width,
+                    state.downsample_mode,
+                    transform,
+                );
+                Self::figure_signal(&mut figure, color, &style, &points, transform);
+            }
+        }
+
+        figure.draw_annotations(&state.annotations);
+        figure.draw_legend(&legend, settings.legend);
+
+        figure
+    }
+
+    /// Draws the samples of a signal to a figure, as `plot_signal` does in the plot
+    fn figure_signal(
+        figure: &mut Figure,
+        color: Color32,
+        style: &SignalStyle,
+        points: &[[f64; 2]],
+        transform: YTransform,
+    ) {
+        let axes = figure.axes;
+        let markers = |figure: &mut Figure| {
+            for &p in points {
+                figure.plot.push(Shape::Circle {
+                    center: axes.screen_pos(p),
+                    radius: style.width + MARKER_RADIUS_EXTRA,
+                    color,
+                });
+            }
+        };
+
+        if style.points_only {
+            markers(figure);
+            return;
+        }
+
+        let line_points = match style.interpolation {
+            Interpolation::Linear => points.to_vec(),
+            Interpolation::StepAfter => step_points(points, |prev, next| [next[0], prev[1]]),
+            Interpolation::StepBefore => step_points(points, |prev, next| [prev[0], next[1]]),
+        };
+        let line: Vec<[f32; 2]> = line_points.iter().map(|&p| axes.screen_pos(p)).collect();
+
+        if style.fill {
+            if let (Some(first), Some(last)) = (line_points.first(), line_points.last()) {
+                let zero = transform.apply(0.0);
+                let mut area = line.clone();
+                area.push(axes.screen_pos([last[0], zero]));
+                area.push(axes.screen_pos([first[0], zero]));
+                figure.plot.push(Shape::Path {
+                    points: area,
+                    closed: true,
+                    stroke: None,
+                    fill: Some(lighten(color)),
+                });
+            }
+        }
+
+        figure.plot.push(Shape::line(
+            line,
+            FigureStroke {
+                width: style.width,
+                color,
+                dash: style.dash,
+            },
+        ));
+
+        if style.markers {
+            markers(figure);
+        }
     }
 
     fn find_visible_range(
@@ -287,7 +745,13 @@ pub struct TabViewer<'a> {
 
     link_x_translated: bool,
 
-    pub added_nodes: Vec<(SurfaceIndex, NodeIndex)>,
+    pub added_nodes: Vec<(SurfaceIndex, NodeIndex, TabKind)>,
+    /// Signals dropped on the edge of a tab, to be shown in a new tab split from it
+    pub split_drops: Vec<(u64, Split, Vec<PlotSignalID>)>,
+    /// Tab whose signals were requested to be exported
+    pub export_request: Option<u64>,
+    /// Tab whose view was requested to be exported as a figure
+    pub figure_request: Option<u64>,
 }
 
 impl<'a> TabViewer<'a> {
@@ -297,6 +761,9 @@ impl<'a> TabViewer<'a> {
             signals,
             link_x_translated: false,
             added_nodes: vec![],
+            split_drops: vec![],
+            export_request: None,
+            figure_request: None,
         }
     }
 }
@@ -305,14 +772,105 @@ impl<'a> egui_dock::TabViewer for TabViewer<'a> {
     type Tab = Tab;
 
     fn title(&mut self, tab: &mut Self::Tab) -> egui::WidgetText {
-        format!("Tab {}", tab.pane_id).into()
+        tab.settings.title(|| tab.default_title()).into()
+    }
+
+    fn id(&mut self, tab: &mut Self::Tab) -> egui::Id {
+        // Titles are editable, so they can't be used as id
+        egui::Id::new(("tab", tab.pane_id))
     }
 
     fn ui(&mut self, ui: &mut egui::Ui, tab: &mut Self::Tab) {
+        let rect = ui.max_rect();
         tab.ui(ui, self.state, self.signals, &mut self.link_x_translated);
+
+        match signal_drop_target(ui, rect) {
+            Some((DropZone::Center, dropped)) => {
+                for id in dropped {
+                    if let Some(signal_state) = self.state.signal_state.get_mut(&id) {
+                        signal_state.used_by_tile.insert(tab.pane_id);
+                    }
+                }
+            }
+            Some((DropZone::Split(split), dropped)) => {
+                self.split_drops.push((tab.pane_id, split, dropped));
+            }
+            None => {}
+        }
     }
 
-    fn on_add(&mut self, surface: egui_dock::SurfaceIndex, node: egui_dock::NodeIndex) {
-        self.added_nodes.push((surface, node));
+    fn context_menu(
+        &mut self,
+        ui: &mut egui::Ui,
+        tab: &mut Self::Tab,
+        _surface: SurfaceIndex,
+        _node: NodeIndex,
+    ) {
+        let default_title = tab.default_title();
+        let is_plot = !matches!(tab.kind, TabKind::Table(_));
+        tab.settings.settings_ui(ui, &default_title, is_plot);
+
+        if let TabKind::Time = tab.kind {
+            ui.separator();
+            let mut plotted: Vec<&PlotSignal> = self
+                .signals
+                .signals()
+                .get_signals()
+                .iter()
+                .filter(|(id, _)| {
+                    self.state
+                        .signal_state
+                        .get(id)
+                        .is_some_and(|s| s.used_by_tile.contains(&tab.pane_id))
+                })
+                .map(|(_, s)| s)
+                .collect();
+            plotted.sort_by_key(|s| s.name());
+
+            tab.y_axes.settings_ui(ui, &plotted);
+        }
+
+        ui.separator();
+        if ui.button("Export…").clicked() {
+            self.export_request = Some(tab.pane_id);
+            ui.close_menu();
+        }
+        if let TabKind::Time = tab.kind {
+            if ui.button("Export figure…").clicked() {
+                self.figure_request = Some(tab.pane_id);
+                ui.close_menu();
+            }
+        }
+    }
+
+    fn add_popup(&mut self, ui: &mut egui::Ui, surface: SurfaceIndex, node: NodeIndex) {
+        ui.set_min_width(100.0);
+        if ui.button("Time plot").clicked() {
+            self.added_nodes.push((surface, node, TabKind::Time));
+        }
+        if ui.button("XY plot").clicked() {
+            self.added_nodes
+                .push((surface, node, TabKind::XY(XYPlot::default())));
+        }
+        if ui.button("Table").clicked() {
+            self.added_nodes
+                .push((surface, node, TabKind::Table(SampleTable::default())));
+        }
+    }
+}
+
+/// Inserts an intermediate point between consecutive samples to draw a step.
+/// `corner` returns the intermediate point given the previous and next sample.
+fn step_points(
+    points: &[[f64; 2]],
+    corner: impl Fn([f64; 2], [f64; 2]) -> [f64; 2],
+) -> Vec<[f64; 2]> {
+    let mut out = Vec::with_capacity(points.len() * 2);
+    for (i, &p) in points.iter().enumerate() {
+        if i > 0 {
+            out.push(corner(points[i - 1], p));
+        }
+        out.push(p);
     }
+    out
 }
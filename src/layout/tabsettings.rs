@@ -0,0 +1,121 @@
+use egui_plot::{Corner, Legend, Plot};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LegendPosition {
+    Hidden,
+    TopLeft,
+    #[default]
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl LegendPosition {
+    const ALL: [(LegendPosition, &'static str); 5] = [
+        (LegendPosition::Hidden, "Hidden"),
+        (LegendPosition::TopLeft, "Top left"),
+        (LegendPosition::TopRight, "Top right"),
+        (LegendPosition::BottomLeft, "Bottom left"),
+        (LegendPosition::BottomRight, "Bottom right"),
+    ];
+
+    fn corner(&self) -> Option<Corner> {
+        match self {
+            LegendPosition::Hidden => None,
+            LegendPosition::TopLeft => Some(Corner::LeftTop),
+            LegendPosition::TopRight => Some(Corner::RightTop),
+            LegendPosition::BottomLeft => Some(Corner::LeftBottom),
+            LegendPosition::BottomRight => Some(Corner::RightBottom),
+        }
+    }
+}
+
+/// User settings of a tab, edited from the tab context menu
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TabSettings {
+    /// Custom title of the tab. The default one is used if empty.
+    pub title: String,
+    pub legend: LegendPosition,
+    pub show_grid: bool,
+    pub x_label: String,
+    pub y_label: String,
+}
+
+impl Default for TabSettings {
+    fn default() -> Self {
+        TabSettings {
+            title: String::new(),
+            legend: LegendPosition::default(),
+            show_grid: true,
+            x_label: String::new(),
+            y_label: String::new(),
+        }
+    }
+}
+
+impl TabSettings {
+    pub fn title(&self, default: impl FnOnce() -> String) -> String {
+        if self.title.is_empty() {
+            default()
+        } else {
+            self.title.clone()
+        }
+    }
+
+    /// Applies the settings to a plot. Must be called after setting custom axes, if any.
+    pub fn apply<'a>(&self, mut plot: Plot<'a>) -> Plot<'a> {
+        if let Some(corner) = self.legend.corner() {
+            plot = plot.legend(Legend::default().position(corner));
+        }
+        if !self.x_label.is_empty() {
+            plot = plot.x_axis_label(self.x_label.clone());
+        }
+        if !self.y_label.is_empty() {
+            plot = plot.y_axis_label(self.y_label.clone());
+        }
+
+        plot.show_grid(self.show_grid)
+    }
+
+    /// Shows the settings. Plot specific ones are hidden if `is_plot` is false.
+    pub fn settings_ui(&mut self, ui: &mut egui::Ui, default_title: &str, is_plot: bool) {
+        egui::Grid::new("tab_settings").show(ui, |ui| {
+            ui.label("Title");
+            ui.add(egui::TextEdit::singleline(&mut self.title).hint_text(default_title));
+            ui.end_row();
+
+            if !is_plot {
+                return;
+            }
+
+            ui.label("Legend");
+            egui::ComboBox::from_id_salt("tab_settings_legend")
+                .selected_text(
+                    LegendPosition::ALL
+                        .iter()
+                        .find(|(pos, _)| *pos == self.legend)
+                        .map_or("", |(_, name)| *name),
+                )
+                .show_ui(ui, |ui| {
+                    for (pos, name) in LegendPosition::ALL {
+                        ui.selectable_value(&mut self.legend, pos, name);
+                    }
+                });
+            ui.end_row();
+
+            ui.label("Grid");
+            ui.checkbox(&mut self.show_grid, "Show");
+            ui.end_row();
+
+            ui.label("X axis label");
+            ui.text_edit_singleline(&mut self.x_label);
+            ui.end_row();
+
+            ui.label("Y axis label");
+            ui.text_edit_singleline(&mut self.y_label);
+            ui.end_row();
+        });
+    }
+}
@@ -0,0 +1,162 @@
+use rust_data_inspector_signals::PlotSignals;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    errors::DataInspectorError,
+    layout::tabs::Tab,
+    state::{DataInspectorState, TabState},
+    utils::{
+        name_color,
+        pattern::{Pattern, PatternKind},
+    },
+};
+
+const REGEX_PREFIX: &str = "re:";
+
+pub const TEMPLATE_HINT: &str = "# One rule per line: <tab title>: <glob>
+# Prefix the pattern with re: to use a regex
+IMU: /*/imu/**
+Battery: re:^/\\w+/battery/(voltage|current)$";
+
+/// Rules assigning signals to tabs based on their path, applied to signals as they appear
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct LayoutTemplate {
+    /// Source of the template, in the format described by `TEMPLATE_HINT`
+    pub text: String,
+    /// Color signals based on their name, so that they look the same in every session
+    pub consistent_colors: bool,
+}
+
+struct TemplateRule {
+    tab: String,
+    pattern: Pattern,
+}
+
+impl LayoutTemplate {
+    fn parse(&self) -> Result<Vec<TemplateRule>, DataInspectorError> {
+        let mut rules = vec![];
+        for (i, line) in self.text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let error = |msg: String| DataInspectorError::Template { line: i + 1, msg };
+
+            let (tab, pattern) = line
+                .split_once(':')
+                .ok_or_else(|| error("expected `<tab title>: <pattern>`".to_string()))?;
+            let (tab, pattern) = (tab.trim(), pattern.trim());
+
+            let pattern = match pattern.strip_prefix(REGEX_PREFIX) {
+                Some(regex) => Pattern::new(PatternKind::Regex, regex.trim()),
+                None => Pattern::new(PatternKind::Glob, pattern),
+            }
+            .map_err(|e| error(e.to_string()))?;
+
+            rules.push(TemplateRule {
+                tab: tab.to_string(),
+                pattern,
+            });
+        }
+
+        Ok(rules)
+    }
+
+    /// Checks that the template is valid
+    pub fn validate(&self) -> Result<(), DataInspectorError> {
+        self.parse().map(|_| ())
+    }
+
+    /// Assigns the signals not assigned yet to the tabs matching their path, creating the tabs if needed
+    pub fn apply(
+        &self,
+        signals: &PlotSignals,
+        state: &mut DataInspectorState,
+        tab_state: &mut TabState,
+    ) -> Result<(), DataInspectorError> {
+        let rules = self.parse()?;
+
+        for (id, signal) in signals.get_signals() {
+            if !state.templated_signals.insert(signal.name().to_string()) {
+                continue;
+            }
+
+            let Some(signal_state) = state.signal_state.get_mut(id) else {
+                continue;
+            };
+
+            if self.consistent_colors {
+                signal_state.color = name_color(signal.name());
+            }
+
+            for rule in rules.iter().filter(|r| r.pattern.is_match(signal.name())) {
+                let existing = tab_state
+                    .tree
+                    .iter_all_tabs()
+                    .find(|(_, tab)| tab.settings.title == rule.tab)
+                    .map(|(_, tab)| tab.pane_id);
+
+                let pane_id = existing.unwrap_or_else(|| {
+                    let mut tab = Tab::new(tab_state.tab_counter);
+                    tab.settings.title = rule.tab.clone();
+                    tab_state.tab_counter += 1;
+
+                    let pane_id = tab.pane_id;
+                    tab_state.tree.push_to_focused_leaf(tab);
+                    pane_id
+                });
+
+                signal_state.used_by_tile.insert(pane_id);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Window to edit the layout template
+#[derive(Debug, Default)]
+pub struct TemplateEditor {
+    pub open: bool,
+    pub error: Option<String>,
+}
+
+impl TemplateEditor {
+    pub fn show(&mut self, ctx: &egui::Context, state: &mut DataInspectorState) {
+        let mut open = self.open;
+        egui::Window::new("Layout template")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.label("Signals are assigned to the tabs whose rules match their path.");
+
+                let template = &mut state.template;
+                let changed = ui
+                    .add(
+                        egui::TextEdit::multiline(&mut template.text)
+                            .code_editor()
+                            .hint_text(TEMPLATE_HINT)
+                            .desired_rows(8)
+                            .desired_width(f32::INFINITY),
+                    )
+                    .changed();
+                ui.checkbox(&mut template.consistent_colors, "Color signals by name");
+
+                if changed {
+                    self.error = template.validate().err().map(|e| e.to_string());
+                }
+                if let Some(error) = &self.error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+
+                if ui
+                    .button("Apply to all signals")
+                    .on_hover_text("By default, rules are only applied to new signals")
+                    .clicked()
+                {
+                    state.templated_signals.clear();
+                }
+            });
+        self.open = open;
+    }
+}
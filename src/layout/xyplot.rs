@@ -0,0 +1,145 @@
+use egui::Color32;
+use egui_plot::{Line, PlotPoints};
+use rust_data_inspector_signals::{PlotSignal, PlotSignalID};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    layout::tabsettings::TabSettings,
+    state::{DataInspectorState, SignalData},
+    utils::downsampling::decimate,
+};
+
+const MAX_XY_POINTS: usize = 10_000;
+const TIME_COLOR_SEGMENTS: usize = 32;
+
+/// Plots one or more signals against another signal instead of time.
+/// Y signals are the ones assigned to the tab, the X signal is selected in the tab itself.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct XYPlot {
+    pub x_signal: Option<PlotSignalID>,
+    pub link_time: bool,
+    pub color_by_time: bool,
+    pub equal_aspect: bool,
+}
+
+impl XYPlot {
+    pub fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        pane_id: u64,
+        settings: &TabSettings,
+        state: &mut DataInspectorState,
+        signals: &mut SignalData,
+    ) {
+        ui.horizontal(|ui| {
+            ui.label("X:");
+            egui::ComboBox::from_id_salt(format!("xy_x_signal_{}", pane_id))
+                .selected_text(
+                    self.x_signal
+                        .and_then(|id| signals.signals().get_signals().get(&id))
+                        .map(|s| s.name().to_string())
+                        .unwrap_or("Select signal".to_string()),
+                )
+                .show_ui(ui, |ui| {
+                    let mut names: Vec<_> = signals
+                        .signals()
+                        .get_signals()
+                        .iter()
+                        .map(|(id, s)| (s.name(), *id))
+                        .collect();
+                    names.sort_by_key(|(name, _)| *name);
+
+                    for (name, id) in names {
+                        ui.selectable_value(&mut self.x_signal, Some(id), name);
+                    }
+                });
+
+            ui.toggle_value(&mut self.link_time, "Link time")
+                .on_hover_text("Only plot samples in the time range visible in the time plots");
+            ui.toggle_value(&mut self.color_by_time, "Color by time");
+            ui.toggle_value(&mut self.equal_aspect, "Equal aspect");
+        });
+
+        let time_window = if self.link_time { state.x_range } else { None };
+
+        let mut plot = settings.apply(egui_plot::Plot::new(format!("plot_{}", pane_id)));
+        if self.equal_aspect {
+            plot = plot.data_aspect(1.0);
+        }
+
+        plot.show(ui, |plot_ui| {
+            let Some(x_signal) = self
+                .x_signal
+                .and_then(|id| signals.signals().get_signals().get(&id))
+            else {
+                return;
+            };
+
+            for (id, signal) in signals.signals().get_signals() {
+                if let Some(sig_state) = state.signal_state.get(id) {
+                    if sig_state.used_by_tile.contains(&pane_id) {
+                        let points = Self::pair_samples(x_signal, signal, time_window);
+
+                        if self.color_by_time {
+                            Self::plot_gradient(plot_ui, points, sig_state.color, signal.name());
+                        } else {
+                            plot_ui.line(
+                                Line::new(PlotPoints::from(points))
+                                    .color(sig_state.color)
+                                    .name(signal.name()),
+                            );
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    /// Pairs each sample of `y` with the value of `x` at the same time.
+    fn pair_samples(
+        x: &PlotSignal,
+        y: &PlotSignal,
+        time_window: Option<[f64; 2]>,
+    ) -> Vec<[f64; 2]> {
+        let range = match time_window {
+            Some([min, max]) => {
+                y.time().partition_point(|&t| t < min)..y.time().partition_point(|&t| t <= max)
+            }
+            None => 0..y.time().len(),
+        };
+
+        let time = &y.time()[range.clone()];
+        let data = &y.data()[range];
+
+        decimate(time, MAX_XY_POINTS)
+            .into_iter()
+            .filter_map(|i| x.value_at(time[i]).map(|x| [x, data[i]]))
+            .collect()
+    }
+
+    /// Splits the line in segments, fading from transparent (oldest) to opaque (newest).
+    fn plot_gradient(
+        plot_ui: &mut egui_plot::PlotUi,
+        points: Vec<[f64; 2]>,
+        color: Color32,
+        name: &str,
+    ) {
+        let segment_len = points.len().div_ceil(TIME_COLOR_SEGMENTS).max(1);
+        let num_segments = points.len().div_ceil(segment_len);
+
+        for (i, segment) in points.chunks(segment_len).enumerate() {
+            let mut segment = segment.to_vec();
+            // Join with the start of the next segment to avoid gaps
+            if let Some(next) = points.get((i + 1) * segment_len) {
+                segment.push(*next);
+            }
+
+            let alpha = (i + 1) as f32 / num_segments as f32;
+            plot_ui.line(
+                Line::new(PlotPoints::from(segment))
+                    .color(color.gamma_multiply(alpha.max(0.1)))
+                    .name(name),
+            );
+        }
+    }
+}
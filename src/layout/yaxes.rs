@@ -0,0 +1,280 @@
+use std::collections::HashSet;
+
+use egui_plot::{AxisHints, GridMark, HPlacement, PlotBounds};
+use rust_data_inspector_signals::{PlotSignal, PlotSignalID};
+use serde::{Deserialize, Serialize};
+
+const RIGHT_AXIS_MARGIN_PC: f64 = 0.05;
+const STACKED_BAND_MARGIN: f64 = 0.05;
+const FIT_MARGIN_PC: f64 = 0.05;
+/// When fitting with hysteresis, bounds are expanded with this additional margin...
+const HYSTERESIS_EXPAND_PC: f64 = 0.1;
+/// ...and shrunk only when the data occupies less than this fraction of them
+const HYSTERESIS_SHRINK_THRESHOLD: f64 = 0.5;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum YAxisLayout {
+    /// All signals share the same y axis
+    #[default]
+    Shared,
+    /// Signals are plotted either against the left or the right y axis
+    Dual,
+    /// Each signal is scaled into its own horizontal band
+    Stacked,
+    /// Each signal is scaled so that its visible range spans the whole plot
+    Normalized,
+}
+
+/// How the range of the y axis is determined
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum YAxisMode {
+    /// Range is controlled by the user
+    #[default]
+    Manual,
+    /// Range always fits the visible data
+    Fit,
+    /// Range fits the visible data, but is only updated when the data goes out of bounds or
+    /// occupies a small portion of the plot, to avoid jittering
+    FitHysteresis,
+    /// Range is fixed to the provided values
+    Fixed([f64; 2]),
+}
+
+/// Linear transformation from signal values to plot coordinates
+#[derive(Debug, Clone, Copy)]
+pub struct YTransform {
+    pub scale: f64,
+    pub offset: f64,
+}
+
+impl YTransform {
+    pub const IDENTITY: YTransform = YTransform {
+        scale: 1.0,
+        offset: 0.0,
+    };
+
+    /// Maps the `from` range onto the `to` range
+    pub fn mapping(from: [f64; 2], to: [f64; 2]) -> Self {
+        let width = from[1] - from[0];
+        if width <= 0.0 || !width.is_finite() {
+            // Flat signal: center it in the target range
+            return YTransform {
+                scale: 1.0,
+                offset: (to[0] + to[1]) / 2.0 - from[0],
+            };
+        }
+
+        let scale = (to[1] - to[0]) / width;
+        YTransform {
+            scale,
+            offset: to[0] - from[0] * scale,
+        }
+    }
+
+    pub fn apply(&self, y: f64) -> f64 {
+        y * self.scale + self.offset
+    }
+}
+
+fn with_margin([min, max]: [f64; 2], margin_pc: f64) -> [f64; 2] {
+    // Flat signals still get a non-empty range
+    let margin = if max > min {
+        (max - min) * margin_pc
+    } else {
+        0.5
+    };
+    [min - margin, max + margin]
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct YAxes {
+    pub layout: YAxisLayout,
+    pub mode: YAxisMode,
+    /// Signals plotted against the right axis in the dual layout
+    pub right: HashSet<PlotSignalID>,
+    /// Range of the right axis, fit to the visible data of its signals
+    pub right_range: Option<[f64; 2]>,
+}
+
+impl YAxes {
+    /// Returns the transformation of each signal, given the extent of their visible data.
+    /// Signals must be provided in a stable order.
+    pub fn transforms(
+        &mut self,
+        signals: &[(&PlotSignal, Option<[f64; 2]>)],
+        bounds: &PlotBounds,
+    ) -> Vec<YTransform> {
+        let n = signals.len() as f64;
+
+        match self.layout {
+            YAxisLayout::Shared => vec![YTransform::IDENTITY; signals.len()],
+            YAxisLayout::Dual => {
+                self.right_range = signals
+                    .iter()
+                    .filter(|(s, _)| self.right.contains(&s.id()))
+                    .filter_map(|(_, extent)| *extent)
+                    .reduce(|a, b| [a[0].min(b[0]), a[1].max(b[1])])
+                    .map(|[min, max]| {
+                        let margin = (max - min) * RIGHT_AXIS_MARGIN_PC;
+                        [min - margin, max + margin]
+                    });
+
+                let left = [bounds.min()[1], bounds.max()[1]];
+                signals
+                    .iter()
+                    .map(|(s, _)| match self.right_range {
+                        Some(right) if self.right.contains(&s.id()) => {
+                            YTransform::mapping(right, left)
+                        }
+                        _ => YTransform::IDENTITY,
+                    })
+                    .collect()
+            }
+            YAxisLayout::Stacked => signals
+                .iter()
+                .enumerate()
+                .map(|(i, (_, extent))| {
+                    // First signal on top
+                    let band = n - 1.0 - i as f64;
+                    extent.map_or(YTransform::IDENTITY, |e| {
+                        YTransform::mapping(
+                            e,
+                            [band + STACKED_BAND_MARGIN, band + 1.0 - STACKED_BAND_MARGIN],
+                        )
+                    })
+                })
+                .collect(),
+            YAxisLayout::Normalized => signals
+                .iter()
+                .map(|(_, extent)| {
+                    extent.map_or(YTransform::IDENTITY, |e| YTransform::mapping(e, [0.0, 1.0]))
+                })
+                .collect(),
+        }
+    }
+
+    /// Returns the new y bounds of the plot, if they need to be changed.
+    /// `current` are the current bounds, `signals` the plotted signals with the extent of their visible data.
+    pub fn y_bounds(
+        &self,
+        current: [f64; 2],
+        signals: &[(&PlotSignal, Option<[f64; 2]>)],
+    ) -> Option<[f64; 2]> {
+        match self.layout {
+            YAxisLayout::Stacked => return Some([0.0, signals.len().max(1) as f64]),
+            YAxisLayout::Normalized => {
+                return Some([-STACKED_BAND_MARGIN, 1.0 + STACKED_BAND_MARGIN])
+            }
+            YAxisLayout::Shared | YAxisLayout::Dual => {}
+        }
+
+        match self.mode {
+            YAxisMode::Manual => None,
+            YAxisMode::Fixed(range) => Some(range),
+            YAxisMode::Fit => self.fit_bounds(signals),
+            YAxisMode::FitHysteresis => {
+                let [min, max] = self.left_extent(signals)?;
+                let height = current[1] - current[0];
+
+                if min < current[0] || max > current[1] {
+                    Some(with_margin(
+                        [min, max],
+                        FIT_MARGIN_PC + HYSTERESIS_EXPAND_PC,
+                    ))
+                } else if max - min < height * HYSTERESIS_SHRINK_THRESHOLD {
+                    Some(with_margin([min, max], FIT_MARGIN_PC))
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    /// Returns y bounds fitting the visible data of the signals plotted against the left axis
+    pub fn fit_bounds(&self, signals: &[(&PlotSignal, Option<[f64; 2]>)]) -> Option<[f64; 2]> {
+        self.left_extent(signals)
+            .map(|e| with_margin(e, FIT_MARGIN_PC))
+    }
+
+    /// Extent of the signals plotted against the left axis
+    fn left_extent(&self, signals: &[(&PlotSignal, Option<[f64; 2]>)]) -> Option<[f64; 2]> {
+        signals
+            .iter()
+            .filter(|(s, _)| self.layout != YAxisLayout::Dual || !self.right.contains(&s.id()))
+            .filter_map(|(_, extent)| *extent)
+            .reduce(|a, b| [a[0].min(b[0]), a[1].max(b[1])])
+    }
+
+    pub fn axis_hints(&self) -> Vec<AxisHints<'static>> {
+        match self.layout {
+            YAxisLayout::Shared => vec![AxisHints::new_y()],
+            YAxisLayout::Dual => {
+                let mut hints = vec![AxisHints::new_y()];
+                if let Some(right) = self.right_range {
+                    hints.push(AxisHints::new_y().placement(HPlacement::Right).formatter(
+                        move |mark: GridMark, range| {
+                            let t = YTransform::mapping([*range.start(), *range.end()], right);
+                            let step = mark.step_size * t.scale.abs();
+                            let decimals = (-step.log10()).ceil().max(0.0) as usize;
+                            egui_plot::format_number(t.apply(mark.value), decimals)
+                        },
+                    ));
+                }
+                hints
+            }
+            // Values in plot coordinates are meaningless
+            YAxisLayout::Stacked | YAxisLayout::Normalized => {
+                vec![AxisHints::new_y().formatter(|_, _| String::new())]
+            }
+        }
+    }
+
+    pub fn settings_ui(&mut self, ui: &mut egui::Ui, plotted: &[&PlotSignal]) {
+        ui.label("Y axes");
+        ui.radio_value(&mut self.layout, YAxisLayout::Shared, "Shared");
+        ui.radio_value(&mut self.layout, YAxisLayout::Dual, "Left / right");
+        ui.radio_value(&mut self.layout, YAxisLayout::Stacked, "Stacked");
+        ui.radio_value(&mut self.layout, YAxisLayout::Normalized, "Normalize view");
+
+        if matches!(self.layout, YAxisLayout::Shared | YAxisLayout::Dual) {
+            ui.separator();
+            ui.label("Y range");
+            ui.radio_value(&mut self.mode, YAxisMode::Manual, "Manual");
+            ui.radio_value(&mut self.mode, YAxisMode::Fit, "Fit visible data");
+            ui.radio_value(
+                &mut self.mode,
+                YAxisMode::FitHysteresis,
+                "Fit visible data (hysteresis)",
+            );
+
+            let fixed = matches!(self.mode, YAxisMode::Fixed(_));
+            ui.horizontal(|ui| {
+                if ui.radio(fixed, "Fixed").clicked() && !fixed {
+                    self.mode = YAxisMode::Fixed([0.0, 1.0]);
+                }
+                if let YAxisMode::Fixed([min, max]) = &mut self.mode {
+                    ui.add(egui::DragValue::new(min).speed(0.1));
+                    ui.label("to");
+                    ui.add(egui::DragValue::new(max).speed(0.1));
+                    *max = max.max(*min);
+                }
+            });
+        }
+
+        if self.layout == YAxisLayout::Dual {
+            ui.separator();
+            ui.label("Right axis signals");
+            for signal in plotted {
+                let mut right = self.right.contains(&signal.id());
+                if ui.checkbox(&mut right, signal.name()).changed() {
+                    if right {
+                        self.right.insert(signal.id());
+                    } else {
+                        self.right.remove(&signal.id());
+                    }
+                }
+            }
+        }
+    }
+}
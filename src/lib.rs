@@ -10,4 +10,6 @@ pub(crate) mod state;
 
 pub use rust_data_inspector_signals::*;
 
-pub use datainspector::{DataInspector, DataInspectorAPI};
\ No newline at end of file
+pub use datainspector::{DataInspector, DataInspectorAPI, DataInspectorOptions};
+pub use errors::DataInspectorError;
+pub use layout::figure::{export_figures, FigureFormat, FigureOptions, FigureRange};
\ No newline at end of file
@@ -1,4 +1,4 @@
-use std::collections::{BTreeSet, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 
 use crate::utils::downsampling::DownsamplingMethod;
 use eframe::Storage;
@@ -8,13 +8,15 @@ use rust_data_inspector_signals::{PlotSignalID, PlotSignals};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    layout::tabs::Tab,
-    utils::{auto_color, VecTree},
+    layout::{tabs::Tab, template::LayoutTemplate},
+    utils::{auto_color, pattern::PatternKind, VecTree},
 };
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DataInspectorState {
     pub show_debug_info: bool,
+    #[serde(default = "default_hover_readout")]
+    pub hover_readout: bool,
     pub x_axis_mode: XAxisMode,
     pub link_x: bool,
     pub downsample_mode: DownsamplingMethod,
@@ -24,14 +26,51 @@ pub struct DataInspectorState {
 
     pub signal_color_counter: usize,
 
+    #[serde(default)]
+    pub annotations: Vec<Annotation>,
+
+    #[serde(default)]
+    pub signal_filter: SignalFilter,
+    /// Last layout file saved or loaded
+    #[serde(default)]
+    pub layout_path: Option<String>,
+    #[serde(default)]
+    pub template: LayoutTemplate,
+    /// Names of the signals the layout template has already been applied to
+    #[serde(default)]
+    pub templated_signals: HashSet<String>,
+
+    /// Visible time range of the time plots, as of the last drawn frame
+    #[serde(skip)]
+    pub x_range: Option<[f64; 2]>,
+    /// Last time hovered by the pointer in a time plot
+    #[serde(skip)]
+    pub pointer_time: Option<f64>,
+    /// Position of the measurement cursors shared by linked plots
+    #[serde(skip)]
+    pub linked_cursors: Option<[f64; 2]>,
+    /// Time hovered in the previous frame, and the pane it was hovered in
+    #[serde(skip)]
+    pub hover_time: Option<(u64, f64)>,
+    #[serde(skip)]
+    pub next_hover_time: Option<(u64, f64)>,
+    /// Time range the time plots should navigate to
+    #[serde(skip)]
+    pub x_range_request: Option<[f64; 2]>,
+
     #[serde(skip)]
     pub debug_info: DebugInfo,
 }
 
+fn default_hover_readout() -> bool {
+    true
+}
+
 impl DataInspectorState {
     pub fn new(signals: &PlotSignals) -> Self {
         DataInspectorState {
             show_debug_info: false,
+            hover_readout: true,
             x_axis_mode: XAxisMode::default(),
             link_x: true,
             downsample_mode: DownsamplingMethod::Lttb,
@@ -40,17 +79,20 @@ impl DataInspectorState {
                 .get_signals()
                 .iter()
                 .enumerate()
-                .map(|(i, (id, _))| {
-                    (
-                        *id,
-                        SignalState {
-                            color: auto_color(i),
-                            used_by_tile: BTreeSet::new(),
-                        },
-                    )
-                })
+                .map(|(i, (id, _))| (*id, SignalState::new(auto_color(i))))
                 .collect(),
             signal_color_counter: signals.get_signals().len(),
+            annotations: vec![],
+            signal_filter: SignalFilter::default(),
+            layout_path: None,
+            template: LayoutTemplate::default(),
+            x_range: None,
+            pointer_time: None,
+            linked_cursors: None,
+            hover_time: None,
+            next_hover_time: None,
+            x_range_request: None,
+            templated_signals: HashSet::new(),
             debug_info: DebugInfo::default(),
         }
     }
@@ -61,22 +103,7 @@ impl DataInspectorState {
             slf.signal_state
                 .retain(|id, _| signals.get_signals().contains_key(id));
 
-            signals
-                .get_signals()
-                .iter()
-                .enumerate()
-                .for_each(|(i, (id, _))| {
-                    if !slf.signal_state.contains_key(id) {
-                        slf.signal_state.insert(
-                            *id,
-                            SignalState {
-                                color: auto_color(i + slf.signal_color_counter),
-                                used_by_tile: BTreeSet::new(),
-                            },
-                        );
-                        slf.signal_color_counter += 1;
-                    }
-                });
+            slf.add_new_signals(signals);
 
             Some(slf)
         } else {
@@ -84,9 +111,32 @@ impl DataInspectorState {
         }
     }
 
+    /// Adds the state of signals added after it was created
+    pub fn add_new_signals(&mut self, signals: &PlotSignals) {
+        signals
+            .get_signals()
+            .iter()
+            .enumerate()
+            .for_each(|(i, (id, _))| {
+                if !self.signal_state.contains_key(id) {
+                    self.signal_state.insert(
+                        *id,
+                        SignalState::new(auto_color(i + self.signal_color_counter)),
+                    );
+                    self.signal_color_counter += 1;
+                }
+            });
+    }
+
     pub fn to_storage(&self, storage: &mut dyn Storage) {
         eframe::set_value(storage, "state", self);
     }
+
+    /// Must be called at the start of every frame
+    pub fn new_frame(&mut self) {
+        self.hover_time = self.next_hover_time.take();
+        self.x_range_request = None;
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -108,10 +158,79 @@ impl Default for TabState {
 #[derive(Debug, Default, Clone)]
 pub struct DebugInfo {}
 
+/// Search query used to filter the signal list
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct SignalFilter {
+    pub query: String,
+    pub kind: PatternKind,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SignalState {
     pub color: Color32,
     pub used_by_tile: BTreeSet<u64>,
+    #[serde(default)]
+    pub style: SignalStyle,
+    /// Styles overriding `style` in specific tiles
+    #[serde(default)]
+    pub style_overrides: BTreeMap<u64, SignalStyle>,
+}
+
+impl SignalState {
+    pub fn new(color: Color32) -> Self {
+        SignalState {
+            color,
+            used_by_tile: BTreeSet::new(),
+            style: SignalStyle::default(),
+            style_overrides: BTreeMap::new(),
+        }
+    }
+
+    pub fn style_for(&self, tile: u64) -> &SignalStyle {
+        self.style_overrides.get(&tile).unwrap_or(&self.style)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SignalStyle {
+    pub width: f32,
+    pub dash: LineDash,
+    /// Draw a marker on each sample
+    pub markers: bool,
+    /// Draw only the samples, without connecting them
+    pub points_only: bool,
+    pub interpolation: Interpolation,
+    /// Fill the area between the line and zero
+    pub fill: bool,
+}
+
+impl Default for SignalStyle {
+    fn default() -> Self {
+        SignalStyle {
+            width: 1.5,
+            dash: LineDash::Solid,
+            markers: false,
+            points_only: false,
+            interpolation: Interpolation::Linear,
+            fill: false,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum LineDash {
+    Solid,
+    Dashed,
+    Dotted,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum Interpolation {
+    Linear,
+    /// Each sample holds its value until the next one
+    StepAfter,
+    /// Each sample is reached with a step right at its time
+    StepBefore,
 }
 
 #[derive(PartialEq, Eq, Clone, Copy, Debug, Default, Serialize, Deserialize)]
@@ -122,6 +241,29 @@ pub enum XAxisMode {
     Free,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Annotation {
+    pub label: String,
+    pub kind: AnnotationKind,
+    pub color: Color32,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum AnnotationKind {
+    Point { time: f64, value: f64 },
+    Line { time: f64 },
+    Region { start: f64, end: f64 },
+}
+
+impl AnnotationKind {
+    pub fn time_range(&self) -> (f64, f64) {
+        match *self {
+            AnnotationKind::Point { time, .. } | AnnotationKind::Line { time } => (time, time),
+            AnnotationKind::Region { start, end } => (start, end),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct SignalNode {
     pub name: String,
@@ -132,6 +274,8 @@ pub struct SignalNode {
 pub struct SignalData {
     signals: PlotSignals,
     signal_tree: VecTree<SignalNode>,
+    /// Number of signals in the signal tree
+    signal_count: usize,
 
     time_span: Option<[f64; 2]>,
     pub all_signals_have_data: bool,
@@ -142,6 +286,7 @@ impl SignalData {
         let signal_tree = Self::grow_signal_tree(&signals);
 
         SignalData {
+            signal_count: signals.get_signals().len(),
             signals,
             signal_tree,
             time_span: None,
@@ -152,7 +297,7 @@ impl SignalData {
     pub fn signals(&self) -> &PlotSignals {
         &self.signals
     }
-    
+
     pub fn signals_mut(&mut self) -> &mut PlotSignals {
         &mut self.signals
     }
@@ -165,9 +310,22 @@ impl SignalData {
         self.time_span
     }
 
+    pub fn clear_timeseries(&mut self) {
+        self.signals.clear_timeseries();
+        self.time_span = None;
+        self.all_signals_have_data = false;
+    }
+
     pub fn update(&mut self) {
         self.signals.update();
 
+        // Signals may be added while running, through a registry
+        if self.signals.get_signals().len() != self.signal_count {
+            self.signal_tree = Self::grow_signal_tree(&self.signals);
+            self.signal_count = self.signals.get_signals().len();
+            self.all_signals_have_data = false;
+        }
+
         for sig in self.signals.get_signals().values() {
             let mut min = self.time_span.map(|v| v[0]);
             let mut max = self.time_span.map(|v| v[1]);
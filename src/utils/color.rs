@@ -1,8 +1,16 @@
-use egui::{Color32, epaint::Hsva};
+use egui::{epaint::Hsva, Color32};
 
+/// Color derived from the name of a signal, the same across sessions
+pub fn name_color(name: &str) -> Color32 {
+    // FNV-1a, as the std hasher is not guaranteed to be stable
+    let hash = name.bytes().fold(0xcbf29ce484222325_u64, |hash, b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    });
+    auto_color((hash % 1024) as usize)
+}
 
 pub fn auto_color(index: usize) -> Color32 {
     let golden_ratio = (5.0_f32.sqrt() - 1.0) / 2.0; // 0.61803398875
     let h = index as f32 * golden_ratio;
     Hsva::new(h, 0.85, 0.6, 1.0).into()
-}
\ No newline at end of file
+}
@@ -1,6 +1,7 @@
 mod vectree;
 mod color;
 pub mod downsampling;
+pub mod pattern;
 
 pub use vectree::VecTree;
-pub use color::auto_color;
+pub use color::{auto_color, name_color};
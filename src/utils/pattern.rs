@@ -0,0 +1,165 @@
+use std::ops::Range;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub enum PatternKind {
+    /// Case insensitive substring
+    #[default]
+    Substring,
+    /// Glob over signal paths: `*` matches within a path segment, `**` across segments, `?` a single character
+    Glob,
+    Regex,
+}
+
+/// A compiled pattern used to match signal names
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    kind: PatternKind,
+    regex: Regex,
+}
+
+impl Pattern {
+    pub fn new(kind: PatternKind, pattern: &str) -> Result<Self, regex::Error> {
+        let regex = match kind {
+            PatternKind::Substring => Regex::new(&format!("(?i){}", regex::escape(pattern)))?,
+            PatternKind::Glob => Regex::new(&glob_to_regex(pattern))?,
+            PatternKind::Regex => Regex::new(pattern)?,
+        };
+
+        Ok(Pattern { kind, regex })
+    }
+
+    pub fn is_match(&self, name: &str) -> bool {
+        self.regex.is_match(name)
+    }
+
+    /// Returns the byte ranges of `name` to be highlighted: the matched text for substrings and
+    /// regexes, the literal parts of the pattern for globs. Empty if `name` does not match.
+    pub fn match_ranges(&self, name: &str) -> Vec<Range<usize>> {
+        match self.kind {
+            PatternKind::Glob => self
+                .regex
+                .captures(name)
+                .map(|captures| {
+                    captures
+                        .iter()
+                        .skip(1)
+                        .flatten()
+                        .map(|m| m.range())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            PatternKind::Substring | PatternKind::Regex => self
+                .regex
+                .find_iter(name)
+                .map(|m| m.range())
+                .filter(|r| !r.is_empty())
+                .collect(),
+        }
+    }
+}
+
+/// Translates a glob into an anchored regex, capturing each literal part of the glob
+fn glob_to_regex(glob: &str) -> String {
+    let mut regex = String::from("^");
+    let mut literal = String::new();
+    let mut chars = glob.chars().peekable();
+
+    let flush = |regex: &mut String, literal: &mut String| {
+        if !literal.is_empty() {
+            regex.push_str(&format!("({})", regex::escape(literal)));
+            literal.clear();
+        }
+    };
+
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                flush(&mut regex, &mut literal);
+                regex.push_str(".*");
+            }
+            '*' => {
+                flush(&mut regex, &mut literal);
+                regex.push_str("[^/]*");
+            }
+            '?' => {
+                flush(&mut regex, &mut literal);
+                regex.push_str("[^/]");
+            }
+            c => literal.push(c),
+        }
+    }
+    flush(&mut regex, &mut literal);
+    regex.push('$');
+
+    regex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_matches() {
+        let cases = [
+            ("/imu/*", "/imu/accel", true),
+            ("/imu/*", "/imu/accel/x", false),
+            ("/imu/**", "/imu/accel/x", true),
+            ("**/x", "/imu/accel/x", true),
+            ("/imu/acc?l", "/imu/accel", true),
+            ("/imu/acc?l", "/imu/acc/l", false),
+            ("/imu", "/imu/accel", false),
+            // Regex metacharacters are literal
+            ("/a.b", "/a.b", true),
+            ("/a.b", "/axb", false),
+            ("/a+(b)", "/a+(b)", true),
+            ("/a[1]", "/a[1]", true),
+            ("/a$|^b", "/a$|^b", true),
+        ];
+        for (glob, name, expected) in cases {
+            let pattern = Pattern::new(PatternKind::Glob, glob).unwrap();
+            assert_eq!(pattern.is_match(name), expected, "{} on {}", glob, name);
+        }
+    }
+
+    fn assert_ranges(kind: PatternKind, pattern: &str, name: &str, expected: &[(usize, usize)]) {
+        let ranges: Vec<_> = Pattern::new(kind, pattern)
+            .unwrap()
+            .match_ranges(name)
+            .into_iter()
+            .map(|r| (r.start, r.end))
+            .collect();
+        assert_eq!(ranges, expected, "{:?} {} on {}", kind, pattern, name);
+    }
+
+    #[test]
+    fn match_ranges() {
+        assert_ranges(
+            PatternKind::Substring,
+            "acc",
+            "/imu/ACCEL/acc",
+            &[(5, 8), (11, 14)],
+        );
+        assert_ranges(PatternKind::Substring, "a.c", "/abc", &[]);
+        assert_ranges(PatternKind::Regex, "[xy]$", "/imu/x", &[(5, 6)]);
+        assert_ranges(PatternKind::Regex, "z*", "/imu", &[]);
+        assert_ranges(
+            PatternKind::Glob,
+            "/imu/*/x",
+            "/imu/accel/x",
+            &[(0, 5), (10, 12)],
+        );
+        assert_ranges(PatternKind::Glob, "**.c?", "/a/b.cd", &[(4, 6)]);
+        assert_ranges(PatternKind::Glob, "/imu/*", "/gps/x", &[]);
+    }
+
+    #[test]
+    fn invalid_regex() {
+        assert!(Pattern::new(PatternKind::Regex, "(").is_err());
+        assert!(Pattern::new(PatternKind::Glob, "(").is_ok());
+        assert!(Pattern::new(PatternKind::Substring, "(").is_ok());
+    }
+}